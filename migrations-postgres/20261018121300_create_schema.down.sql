@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS todo_events;
+DROP TYPE IF EXISTS todo_event_kind;
+DROP TABLE IF EXISTS todo_tags;
+DROP TABLE IF EXISTS tags;
+DROP TABLE IF EXISTS todos;
+DROP TABLE IF EXISTS lists;
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,111 @@
+-- The Postgres schema starts out where the SQLite migrations have got to, so
+-- the two have the same tables, columns and constraints from here on.
+
+CREATE TABLE IF NOT EXISTS users (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  username TEXT NOT NULL,
+  -- argon2 PHC string; includes the salt and parameters
+  password_hash TEXT NOT NULL
+);
+
+-- usernames are unique regardless of case, like SQLite's COLLATE NOCASE
+CREATE UNIQUE INDEX users_username ON users (lower(username));
+
+CREATE TABLE IF NOT EXISTS sessions (
+  -- random token handed to the browser in the session cookie
+  id TEXT PRIMARY KEY,
+  user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX sessions_user_id ON sessions (user_id);
+
+CREATE TABLE IF NOT EXISTS lists (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  -- NULL until the first user to sign up claims it
+  user_id BIGINT REFERENCES users (id) ON DELETE CASCADE,
+  name TEXT NOT NULL CHECK (length(trim(name)) > 0)
+);
+
+CREATE INDEX lists_user_id ON lists (user_id);
+
+-- the first user to sign up gets this list, just like on SQLite
+INSERT INTO lists (id, name) VALUES (1, 'Todos');
+SELECT setval(pg_get_serial_sequence('lists', 'id'), 1);
+
+CREATE TABLE IF NOT EXISTS todos (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  list_id BIGINT NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+  -- subtasks point at the todo they belong to and go when it does
+  parent_id BIGINT REFERENCES todos (id) ON DELETE CASCADE,
+  -- blank after trimming any whitespace the server would, not just spaces
+  description TEXT NOT NULL CHECK (length(btrim(description, E' \t\n\r')) > 0),
+  completed_at BIGINT,
+  -- millis since the epoch at midnight UTC on the day the todo is due
+  due_at BIGINT,
+  -- 0 = low, 1 = normal, 2 = high, 3 = urgent; stored as a number so it sorts
+  priority BIGINT NOT NULL DEFAULT 1 CHECK (priority BETWEEN 0 AND 3),
+  -- where the todo sits when ordered by hand; fractional so a todo can be
+  -- moved between two others without renumbering
+  position DOUBLE PRECISION NOT NULL DEFAULT 0,
+  -- an RRULE like FREQ=WEEKLY;BYDAY=MO; NULL for todos that don't come back
+  recurrence TEXT CHECK (recurrence IS NULL OR recurrence LIKE 'FREQ=%'),
+  -- deleted todos sit in the trash until they're restored or purged
+  deleted_at BIGINT,
+  created_at BIGINT NOT NULL DEFAULT 0,
+  updated_at BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX todos_list_id ON todos (list_id);
+CREATE INDEX todos_list_position ON todos (list_id, position);
+CREATE INDEX todos_list_updated_at ON todos (list_id, updated_at);
+CREATE INDEX todos_parent_id ON todos (parent_id);
+CREATE INDEX todos_deleted_at ON todos (deleted_at);
+
+-- full-text index over todo descriptions; searches have to use the same
+-- expression for it to be picked up
+CREATE INDEX todos_description_search
+  ON todos USING GIN (to_tsvector('simple', description));
+
+CREATE TABLE IF NOT EXISTS tags (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  -- stored lowercase without the leading '#'
+  name TEXT NOT NULL CHECK (length(name) > 0),
+  UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS todo_tags (
+  todo_id BIGINT NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  tag_id BIGINT NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+  PRIMARY KEY (todo_id, tag_id)
+);
+
+CREATE INDEX todo_tags_tag_id ON todo_tags (tag_id);
+
+CREATE TYPE todo_event_kind AS ENUM (
+  'created',
+  'renamed',
+  'completed',
+  'reopened',
+  'prioritized',
+  'repeated',
+  'tagged',
+  'untagged',
+  'moved',
+  'trashed',
+  'restored'
+);
+
+-- an append-only record of every change made to a todo, and who made it
+CREATE TABLE IF NOT EXISTS todo_events (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  todo_id BIGINT NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  user_id BIGINT REFERENCES users (id) ON DELETE SET NULL,
+  kind todo_event_kind NOT NULL,
+  -- whatever the todo was changed to, for the kinds of change that need it
+  detail TEXT,
+  created_at BIGINT NOT NULL
+);
+
+CREATE INDEX todo_events_todo_id ON todo_events (todo_id);
@@ -0,0 +1 @@
+INSERT INTO lists (id, name) VALUES (1, 'Todos') ON CONFLICT (id) DO NOTHING;
@@ -0,0 +1,12 @@
+-- signing up no longer hands out lists nobody owns, so drop the empty one the
+-- schema started with, and let the first user's list take its id
+DELETE FROM lists
+WHERE user_id IS NULL AND NOT EXISTS (
+  SELECT 1 FROM todos WHERE todos.list_id = lists.id
+);
+
+SELECT setval(
+  pg_get_serial_sequence('lists', 'id'),
+  COALESCE(MAX(id), 0) + 1,
+  false
+) FROM lists;
@@ -0,0 +1,6 @@
+DROP INDEX todos_description_search;
+CREATE INDEX todos_description_search
+  ON todos USING GIN (to_tsvector('simple', description));
+
+-- the extension stays, since other schemas may be using it
+DROP TEXT SEARCH CONFIGURATION todo_search;
@@ -0,0 +1,13 @@
+-- SQLite's full-text tokenizer ignores accents, so searching for "creme"
+-- finds "crème"; do the same here with a config that runs words through
+-- unaccent before indexing them. The extension goes in public so that every
+-- schema in the database can find it
+CREATE EXTENSION IF NOT EXISTS unaccent SCHEMA public;
+
+CREATE TEXT SEARCH CONFIGURATION todo_search (COPY = simple);
+ALTER TEXT SEARCH CONFIGURATION todo_search
+  ALTER MAPPING FOR word, hword, hword_part WITH public.unaccent, simple;
+
+DROP INDEX todos_description_search;
+CREATE INDEX todos_description_search
+  ON todos USING GIN (to_tsvector('todo_search', description));
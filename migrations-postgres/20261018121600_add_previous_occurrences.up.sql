@@ -0,0 +1,6 @@
+-- The next occurrence of a recurring todo points at the one it came from, so
+-- completing that one again doesn't schedule another
+ALTER TABLE todos ADD COLUMN previous_id BIGINT
+  REFERENCES todos (id) ON DELETE SET NULL;
+
+CREATE INDEX todos_previous_id ON todos (previous_id);
@@ -0,0 +1,11 @@
+CREATE TABLE todos_new (
+  id INTEGER PRIMARY KEY NOT NULL,
+  description TEXT NOT NULL,
+  completed_at BIGINT
+);
+
+INSERT INTO todos_new (id, description, completed_at)
+SELECT id, description, completed_at FROM todos;
+
+DROP TABLE todos;
+ALTER TABLE todos_new RENAME TO todos;
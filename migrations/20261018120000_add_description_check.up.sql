@@ -0,0 +1,24 @@
+-- SQLite can't add a constraint to an existing table, so rebuild it. Its trim
+-- only strips spaces unless told otherwise, so spell out the rest of the
+-- whitespace the server trims: tabs, line feeds and carriage returns
+CREATE TABLE todos_new (
+  id INTEGER PRIMARY KEY NOT NULL,
+  description TEXT NOT NULL CHECK (length(trim(description, ' ' || char(9) || char(10) || char(13))) > 0),
+  completed_at BIGINT
+);
+
+-- existing descriptions are trimmed the way new ones are, and any that were
+-- blank, which the check above no longer allows, become '(no description)'.
+-- The down migration can't tell those apart from real ones, so they stay
+INSERT INTO todos_new (id, description, completed_at)
+SELECT
+  id,
+  CASE
+    WHEN length(trim(description, ' ' || char(9) || char(10) || char(13))) > 0 THEN trim(description, ' ' || char(9) || char(10) || char(13))
+    ELSE '(no description)'
+  END,
+  completed_at
+FROM todos;
+
+DROP TABLE todos;
+ALTER TABLE todos_new RENAME TO todos;
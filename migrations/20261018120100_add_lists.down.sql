@@ -0,0 +1,13 @@
+CREATE TABLE todos_new (
+  id INTEGER PRIMARY KEY NOT NULL,
+  description TEXT NOT NULL CHECK (length(trim(description, ' ' || char(9) || char(10) || char(13))) > 0),
+  completed_at BIGINT
+);
+
+INSERT INTO todos_new (id, description, completed_at)
+SELECT id, description, completed_at FROM todos;
+
+DROP TABLE todos;
+ALTER TABLE todos_new RENAME TO todos;
+
+DROP TABLE IF EXISTS lists;
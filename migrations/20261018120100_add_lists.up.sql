@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS lists (
+  id INTEGER PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL CHECK (length(trim(name)) > 0)
+);
+
+-- every existing todo ends up on this list
+INSERT INTO lists (id, name) VALUES (1, 'Todos');
+
+-- SQLite won't add a REFERENCES column with a non-NULL default while foreign
+-- keys are on, so rebuild the table instead
+CREATE TABLE todos_new (
+  id INTEGER PRIMARY KEY NOT NULL,
+  list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+  description TEXT NOT NULL CHECK (length(trim(description, ' ' || char(9) || char(10) || char(13))) > 0),
+  completed_at BIGINT
+);
+
+INSERT INTO todos_new (id, list_id, description, completed_at)
+SELECT id, 1, description, completed_at FROM todos;
+
+DROP TABLE todos;
+ALTER TABLE todos_new RENAME TO todos;
+
+CREATE INDEX todos_list_id ON todos (list_id);
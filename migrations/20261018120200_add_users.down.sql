@@ -0,0 +1,23 @@
+-- no-transaction
+-- lists.user_id is a foreign key so it can't be dropped in place, and
+-- rebuilding lists with foreign keys on would cascade-delete every todo
+PRAGMA foreign_keys = OFF;
+
+BEGIN;
+
+CREATE TABLE lists_new (
+  id INTEGER PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL CHECK (length(trim(name)) > 0)
+);
+
+INSERT INTO lists_new (id, name) SELECT id, name FROM lists;
+
+DROP TABLE lists;
+ALTER TABLE lists_new RENAME TO lists;
+
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS users;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS users (
+  id INTEGER PRIMARY KEY NOT NULL,
+  username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+  -- argon2 PHC string; includes the salt and parameters
+  password_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+  -- random token handed to the browser in the session cookie
+  id TEXT PRIMARY KEY NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  expires_at BIGINT NOT NULL
+);
+
+CREATE INDEX sessions_user_id ON sessions (user_id);
+
+-- lists created before accounts existed have no owner; the first user to sign
+-- up claims them
+ALTER TABLE lists ADD COLUMN user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+
+CREATE INDEX lists_user_id ON lists (user_id);
@@ -0,0 +1,2 @@
+-- millis since the epoch at midnight UTC on the day the todo is due
+ALTER TABLE todos ADD COLUMN due_at BIGINT;
@@ -0,0 +1,3 @@
+-- 0 = low, 1 = normal, 2 = high, 3 = urgent; stored as a number so it sorts
+ALTER TABLE todos ADD COLUMN priority INTEGER NOT NULL DEFAULT 1
+  CHECK (priority BETWEEN 0 AND 3);
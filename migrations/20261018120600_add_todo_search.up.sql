@@ -0,0 +1,24 @@
+-- full-text index over todo descriptions; the text itself stays in todos
+CREATE VIRTUAL TABLE todos_fts USING fts5 (
+  description,
+  content = 'todos',
+  content_rowid = 'id'
+);
+
+INSERT INTO todos_fts (rowid, description) SELECT id, description FROM todos;
+
+-- keep the index in sync with the table
+CREATE TRIGGER todos_fts_insert AFTER INSERT ON todos BEGIN
+  INSERT INTO todos_fts (rowid, description) VALUES (new.id, new.description);
+END;
+
+CREATE TRIGGER todos_fts_delete AFTER DELETE ON todos BEGIN
+  INSERT INTO todos_fts (todos_fts, rowid, description)
+  VALUES ('delete', old.id, old.description);
+END;
+
+CREATE TRIGGER todos_fts_update AFTER UPDATE OF description ON todos BEGIN
+  INSERT INTO todos_fts (todos_fts, rowid, description)
+  VALUES ('delete', old.id, old.description);
+  INSERT INTO todos_fts (rowid, description) VALUES (new.id, new.description);
+END;
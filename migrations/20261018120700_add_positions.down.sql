@@ -0,0 +1,2 @@
+DROP INDEX todos_list_position;
+ALTER TABLE todos DROP COLUMN position;
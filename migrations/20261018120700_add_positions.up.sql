@@ -0,0 +1,8 @@
+-- Where each todo sits in its list when ordered by hand. Positions are
+-- fractional so a todo can be moved between two others without renumbering.
+ALTER TABLE todos ADD COLUMN position REAL NOT NULL DEFAULT 0;
+
+-- Keep existing todos in the order they were added
+UPDATE todos SET position = id;
+
+CREATE INDEX todos_list_position ON todos (list_id, position);
@@ -0,0 +1,5 @@
+-- Subtasks point at the todo they belong to and go when it does
+ALTER TABLE todos ADD COLUMN parent_id INTEGER
+  REFERENCES todos (id) ON DELETE CASCADE;
+
+CREATE INDEX todos_parent_id ON todos (parent_id);
@@ -0,0 +1,3 @@
+-- An RRULE like FREQ=WEEKLY;BYDAY=MO; NULL for todos that don't come back
+ALTER TABLE todos ADD COLUMN recurrence TEXT
+  CHECK (recurrence IS NULL OR recurrence LIKE 'FREQ=%');
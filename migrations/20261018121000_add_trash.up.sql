@@ -0,0 +1,4 @@
+-- Deleted todos sit in the trash until they're restored or purged
+ALTER TABLE todos ADD COLUMN deleted_at BIGINT;
+
+CREATE INDEX todos_deleted_at ON todos (deleted_at);
@@ -0,0 +1,2 @@
+DROP INDEX todo_events_todo_id;
+DROP TABLE todo_events;
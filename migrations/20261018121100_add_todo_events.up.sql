@@ -0,0 +1,12 @@
+-- An append-only record of every change made to a todo, and who made it
+CREATE TABLE IF NOT EXISTS todo_events (
+  id INTEGER PRIMARY KEY NOT NULL,
+  todo_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  user_id INTEGER REFERENCES users (id) ON DELETE SET NULL,
+  kind TEXT NOT NULL,
+  -- whatever the todo was changed to, for the kinds of change that need it
+  detail TEXT,
+  created_at BIGINT NOT NULL
+);
+
+CREATE INDEX todo_events_todo_id ON todo_events (todo_id);
@@ -0,0 +1,3 @@
+DROP INDEX todos_list_updated_at;
+ALTER TABLE todos DROP COLUMN updated_at;
+ALTER TABLE todos DROP COLUMN created_at;
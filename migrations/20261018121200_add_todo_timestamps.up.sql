@@ -0,0 +1,22 @@
+ALTER TABLE todos ADD COLUMN created_at BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE todos ADD COLUMN updated_at BIGINT NOT NULL DEFAULT 0;
+
+-- existing todos get the best guess we have: their history if they have one,
+-- then when they were completed, then now
+UPDATE todos SET created_at = COALESCE(
+  (SELECT MIN(created_at) FROM todo_events WHERE todo_id = todos.id),
+  completed_at,
+  unixepoch() * 1000
+);
+
+UPDATE todos SET updated_at = MAX(
+  created_at,
+  COALESCE(completed_at, 0),
+  COALESCE((
+    SELECT MAX(created_at) FROM todo_events
+    WHERE todo_id = todos.id
+    AND kind NOT IN ('moved', 'trashed', 'restored')
+  ), 0)
+);
+
+CREATE INDEX todos_list_updated_at ON todos (list_id, updated_at);
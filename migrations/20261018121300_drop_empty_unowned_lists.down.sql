@@ -0,0 +1,3 @@
+-- the lists the up migration dropped were empty; put back the one a fresh
+-- database starts with, unless its id has been taken since
+INSERT OR IGNORE INTO lists (id, name) VALUES (1, 'Todos');
@@ -0,0 +1,8 @@
+-- signing up no longer hands out the lists that predate accounts; someone has
+-- to claim them from the command line instead. A fresh database only has the
+-- empty list the lists migration made, so drop that rather than leave it
+-- lying around with nobody to claim it
+DELETE FROM lists
+WHERE user_id IS NULL AND NOT EXISTS (
+  SELECT 1 FROM todos WHERE todos.list_id = lists.id
+);
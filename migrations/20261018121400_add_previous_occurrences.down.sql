@@ -0,0 +1,2 @@
+DROP INDEX todos_previous_id;
+ALTER TABLE todos DROP COLUMN previous_id;
@@ -0,0 +1,73 @@
+// Drag-and-drop reordering for todo lists marked with `data-sortable`. Once a
+// todo is dropped, its new neighbours are sent to the server so the order
+// sticks.
+(function () {
+  let dragged = null;
+  let startingNext = null;
+
+  const todoId = (item) => item.id.replace("todo-", "");
+
+  document.addEventListener("dragstart", (event) => {
+    const item = event.target.closest("ul[data-sortable] > li");
+    if (!item) {
+      return;
+    }
+    dragged = item;
+    startingNext = item.nextElementSibling;
+    event.dataTransfer.effectAllowed = "move";
+    item.classList.add("is-dragging");
+  });
+
+  document.addEventListener("dragover", (event) => {
+    if (!dragged) {
+      return;
+    }
+    // todos only move among their siblings, so skip past any subtasks
+    let item = event.target.closest("li");
+    while (item && item.parentElement !== dragged.parentElement) {
+      item = item.parentElement.closest("li");
+    }
+    if (!item) {
+      return;
+    }
+    event.preventDefault();
+    if (item === dragged) {
+      return;
+    }
+    const box = item.getBoundingClientRect();
+    const below = event.clientY > box.top + box.height / 2;
+    item.parentElement.insertBefore(
+      dragged,
+      below ? item.nextElementSibling : item,
+    );
+  });
+
+  document.addEventListener("drop", (event) => {
+    if (dragged) {
+      event.preventDefault();
+    }
+  });
+
+  document.addEventListener("dragend", () => {
+    if (!dragged) {
+      return;
+    }
+    const item = dragged;
+    dragged = null;
+    item.classList.remove("is-dragging");
+    if (item.nextElementSibling === startingNext) {
+      return;
+    }
+    const values = {};
+    if (item.previousElementSibling) {
+      values.after = todoId(item.previousElementSibling);
+    }
+    if (item.nextElementSibling) {
+      values.before = todoId(item.nextElementSibling);
+    }
+    htmx.ajax("PUT", `/api/v1/todos/${todoId(item)}/position`, {
+      values: values,
+      swap: "none",
+    });
+  });
+})();
@@ -0,0 +1,217 @@
+use crate::{
+    errors::TodoError,
+    negotiate::Format,
+    state::Dao,
+    users::{SESSION_TTL, User, UserDao},
+};
+use argon2::{
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+    password_hash::SaltString,
+};
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{StatusCode, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use rand::{Rng, distr::Alphanumeric};
+use std::sync::LazyLock;
+
+/// Name of the cookie holding the session token.
+pub const SESSION_COOKIE: &str = "session";
+
+/// Hashes a password with argon2. This is deliberately slow, so it runs on the
+/// blocking thread pool.
+pub async fn hash_password(password: String) -> Result<String, TodoError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+            .map_err(|e| TodoError::Storage(e.to_string().into()))?;
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| TodoError::Storage(e.to_string().into()))
+    })
+    .await
+    .map_err(|e| TodoError::Storage(e.into()))?
+}
+
+/// Checks a password against a stored argon2 hash.
+pub async fn verify_password(
+    password: String,
+    password_hash: String,
+) -> Result<bool, TodoError> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&password_hash)
+            .map_err(|e| TodoError::Storage(e.to_string().into()))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|e| TodoError::Storage(e.into()))?
+}
+
+/// A hash of a random password nobody knows, made the first time someone
+/// tries to log in as a user who doesn't exist.
+static UNKNOWN_USER_HASH: LazyLock<String> = LazyLock::new(|| {
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .expect("16 bytes should make a valid salt");
+    Argon2::default()
+        .hash_password(&rand::random::<[u8; 32]>(), &salt)
+        .expect("argon2's default parameters should be valid")
+        .to_string()
+});
+
+/// Checks a password against a hash that nothing matches, so that logging in
+/// as someone who doesn't exist takes as long as getting a password wrong.
+pub async fn verify_password_for_unknown_user(
+    password: String,
+) -> Result<(), TodoError> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&UNKNOWN_USER_HASH)
+            .map_err(|e| TodoError::Storage(e.to_string().into()))?;
+        let _ = Argon2::default().verify_password(password.as_bytes(), &hash);
+        Ok(())
+    })
+    .await
+    .map_err(|e| TodoError::Storage(e.into()))?
+}
+
+/// Generates a random, unguessable session token.
+pub fn new_session_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect()
+}
+
+/// The cookie that keeps a user logged in. It's only sent over https unless
+/// `secure` is off, which is only meant for trying the app out locally.
+pub fn session_cookie(token: String, secure: bool) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .secure(secure)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(SESSION_TTL.try_into().unwrap_or_default())
+        .build()
+}
+
+pub fn removal_cookie(secure: bool) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE)
+        .path("/")
+        .secure(secure)
+        .build()
+}
+
+/// The logged in user, as found by [`require_user`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CurrentUser(pub User);
+
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    S: Send + Sync,
+{
+    type Rejection = TodoError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or(TodoError::Unauthorized)
+    }
+}
+
+/// Middleware that only lets requests with a valid session through, making
+/// the user available to handlers as a [`CurrentUser`].
+///
+/// Anyone else gets sent to the login page: browsers via a redirect, htmx via
+/// `HX-Redirect`, and API clients get a 401.
+pub async fn require_user<T: UserDao>(
+    State(Dao(dao)): State<Dao<T>>,
+    jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user = match jar.get(SESSION_COOKIE) {
+        Some(cookie) => dao.get_session_user(cookie.value().to_string()).await,
+        None => Err(TodoError::Unauthorized),
+    };
+    match user {
+        Ok(user) => {
+            request.extensions_mut().insert(CurrentUser(user));
+            next.run(request).await
+        }
+        Err(TodoError::NotFound | TodoError::Unauthorized) => {
+            if request.headers().contains_key("HX-Request") {
+                (StatusCode::UNAUTHORIZED, [("HX-Redirect", "/login")])
+                    .into_response()
+            } else if Format::from_headers(request.headers()) == Format::Json
+                || request.uri().path().starts_with("/api/")
+            {
+                TodoError::Unauthorized.into_response()
+            } else {
+                Redirect::to("/login").into_response()
+            }
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_and_verify_password() {
+        let hash = hash_password("hunter22".to_string()).await.unwrap();
+
+        assert_ne!(hash, "hunter22");
+        assert!(
+            verify_password("hunter22".to_string(), hash.clone())
+                .await
+                .unwrap()
+        );
+        assert!(!verify_password("hunter2".to_string(), hash).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_verify_password_for_unknown_user() {
+        verify_password_for_unknown_user("hunter22".to_string())
+            .await
+            .unwrap();
+
+        // it's a real hash that goes through the same checks as a user's, and
+        // a guess doesn't match it
+        assert!(
+            !verify_password("hunter22".to_string(), UNKNOWN_USER_HASH.clone())
+                .await
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_new_session_token() {
+        let token = new_session_token();
+
+        assert_eq!(token.len(), 48);
+        assert_ne!(token, new_session_token());
+    }
+
+    #[test]
+    fn test_session_cookie() {
+        let cookie = session_cookie("token".to_string(), true);
+
+        assert_eq!(cookie.name(), SESSION_COOKIE);
+        assert_eq!(cookie.value(), "token");
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    }
+}
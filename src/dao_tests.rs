@@ -0,0 +1,2175 @@
+//! The DAO tests, written once and run against every backend. Each backend
+//! implements `TestDao` and calls `dao_tests!` from its own tests.
+
+use crate::{
+    clock::FakeClock,
+    errors::TodoError,
+    recurrence::Recurrence,
+    todos::*,
+    users::{SESSION_TTL, UserDao},
+};
+use chrono::NaiveDate;
+use std::{collections::HashSet, time::Duration};
+
+/// A backend the DAO tests can run against.
+pub(crate) trait TestDao: TodoDao + UserDao + Sized {
+    /// A DAO over a fresh, empty database that gets the time from `clock`.
+    async fn empty(clock: FakeClock) -> Self;
+    /// Adds a list with no owner, like the ones that predate accounts, and
+    /// returns its id.
+    async fn add_unowned_list(&self, name: &str) -> i64;
+}
+
+/// Stamps out a `#[tokio::test]` for each of the tests below, run against
+/// the given backend. Any attributes after it, like `#[ignore]`, go on every
+/// test.
+macro_rules! dao_tests {
+    (@each $dao:ty, $attrs:tt, $($name:ident),* $(,)?) => {
+        $($crate::dao_tests::dao_tests!(@test $dao, $attrs, $name);)*
+    };
+    (@test $dao:ty, [$($attr:tt)*], $name:ident) => {
+        #[tokio::test]
+        $($attr)*
+        async fn $name() {
+            $crate::dao_tests::$name::<$dao>().await;
+        }
+    };
+    ($dao:ty $(, #[$attr:meta])*) => {
+        $crate::dao_tests::dao_tests!(
+            @each $dao,
+            [$(#[$attr])*],
+            test_create_user,
+            test_create_duplicate_user,
+            test_signup_never_claims_unowned_lists,
+            test_claim_unowned_lists,
+            test_get_credentials_unknown_user,
+            test_sessions,
+            test_session_expires_after_ttl,
+            test_add_blank_todo_rejected_by_database,
+            test_get_lists_default,
+            test_create_list,
+            test_rename_list,
+            test_rename_nonexistent_list,
+            test_delete_list,
+            test_delete_last_list,
+            test_delete_nonexistent_list,
+            test_lists_keep_todos_separate,
+            test_add_todo_to_nonexistent_list,
+            test_get_todos_empty,
+            test_get_todos,
+            test_get_todos_by_completion,
+            test_add_todo,
+            test_add_todo_with_due_date,
+            test_get_todos_by_due_date,
+            test_get_todo,
+            test_get_nonexistent_todo,
+            test_update_description,
+            test_update_description_nonexistent_todo,
+            test_add_todo_with_priority,
+            test_set_priority,
+            test_complete_recurring_todo,
+            test_complete_recurring_todo_again,
+            test_complete_todo_with_recurring_subtask,
+            test_complete_recurring_todo_with_recurring_subtask,
+            test_complete_todo_without_recurrence,
+            test_set_recurrence,
+            test_set_priority_nonexistent_todo,
+            test_get_todos_by_priority,
+            test_timestamps,
+            test_get_todos_by_updated,
+            test_move_todo,
+            test_move_todo_renumbers_when_out_of_room,
+            test_move_todo_invalid,
+            test_subtasks,
+            test_add_subtask_nonexistent_parent,
+            test_toggle_todo_with_subtasks,
+            test_delete_todo_with_subtasks,
+            test_move_subtask,
+            test_add_todo_with_tags,
+            test_tag_and_untag_todo,
+            test_tag_nonexistent_todo,
+            test_get_todos_by_tag,
+            test_toggle_todo,
+            test_toggle_nonexistent_todo,
+            test_delete_todo,
+            test_delete_nonexistent_todo,
+            test_complete_all,
+            test_delete_completed,
+            test_bulk_update,
+            test_bulk_update_is_all_or_nothing,
+            test_bulk_delete_with_subtasks,
+            test_import,
+            test_import_is_all_or_nothing,
+            test_import_into_nonexistent_list,
+            test_trash_and_restore,
+            test_restore_todo_with_subtasks,
+            test_complete_todo_with_trashed_subtask,
+            test_restore_todo_not_in_trash,
+            test_purge_todo,
+            test_empty_trash,
+            test_purge_expired_trash,
+            test_history,
+            test_history_of_subtasks,
+            test_history_nonexistent_todo,
+            test_search,
+            test_search_follows_changes,
+            test_search_ignores_query_syntax,
+            test_search_folds_accents,
+            test_other_users_todos_are_hidden,
+        );
+    };
+}
+
+pub(crate) use dao_tests;
+
+/// The first user in a fresh database; owns the default list.
+const USER_ID: i64 = 1;
+
+/// Where the fake clock starts: midnight UTC on 2025-12-31.
+const NOW: i64 = 1_767_139_200_000;
+
+/// A DAO with nobody signed up yet.
+async fn get_empty_dao<D: TestDao>() -> D {
+    D::empty(FakeClock::new(NOW)).await
+}
+
+async fn get_dao<D: TestDao>() -> D {
+    get_dao_with_clock::<D>().await.0
+}
+
+/// A DAO whose clock only moves when the test moves it.
+async fn get_dao_with_clock<D: TestDao>() -> (D, FakeClock) {
+    let clock = FakeClock::new(NOW);
+    let dao = D::empty(clock.clone()).await;
+    let user = dao
+        .create_user("alice".to_string(), "hash".to_string())
+        .await
+        .expect("failed to create user");
+    assert_eq!(user.id, USER_ID);
+    (dao, clock)
+}
+
+pub(crate) async fn test_create_user<D: TestDao>() {
+    let dao = get_empty_dao::<D>().await;
+
+    let user = dao
+        .create_user("alice".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(user.username, "alice");
+    let credentials = dao.get_credentials("alice".to_string()).await.unwrap();
+    assert_eq!(credentials.id, user.id);
+    assert_eq!(credentials.password_hash, "hash");
+}
+
+pub(crate) async fn test_create_duplicate_user<D: TestDao>() {
+    let dao = get_empty_dao::<D>().await;
+
+    dao.create_user("alice".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+    let result = dao
+        .create_user("Alice".to_string(), "hash".to_string())
+        .await;
+
+    assert!(matches!(result, Err(TodoError::Conflict(_))));
+}
+
+pub(crate) async fn test_signup_never_claims_unowned_lists<D: TestDao>() {
+    let dao = get_empty_dao::<D>().await;
+    let unowned = dao.add_unowned_list("Before accounts").await;
+
+    for username in ["alice", "bob"] {
+        let user = dao
+            .create_user(username.to_string(), "hash".to_string())
+            .await
+            .unwrap();
+
+        let lists = dao.get_lists(user.id).await.unwrap();
+        assert_eq!(lists.len(), 1);
+        assert_ne!(lists[0].id, unowned);
+        assert_eq!(lists[0].name, "Todos");
+        assert!(matches!(
+            dao.get_list(user.id, unowned).await,
+            Err(TodoError::NotFound)
+        ));
+    }
+}
+
+pub(crate) async fn test_claim_unowned_lists<D: TestDao>() {
+    let dao = get_empty_dao::<D>().await;
+    let unowned = dao.add_unowned_list("Before accounts").await;
+    let alice = dao
+        .create_user("alice".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+    let bob = dao
+        .create_user("bob".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(dao.claim_unowned_lists(bob.id).await.unwrap(), 1);
+
+    assert_eq!(
+        dao.get_list(bob.id, unowned).await.unwrap(),
+        List::new(unowned, "Before accounts")
+    );
+    assert_eq!(dao.get_lists(bob.id).await.unwrap().len(), 2);
+    assert_eq!(dao.get_lists(alice.id).await.unwrap().len(), 1);
+    // once they're claimed there's nothing left for anyone else
+    assert_eq!(dao.claim_unowned_lists(alice.id).await.unwrap(), 0);
+}
+
+pub(crate) async fn test_get_credentials_unknown_user<D: TestDao>() {
+    let dao = get_empty_dao::<D>().await;
+
+    let result = dao.get_credentials("alice".to_string()).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_sessions<D: TestDao>() {
+    let dao = get_empty_dao::<D>().await;
+
+    let user = dao
+        .create_user("alice".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+    dao.create_session("token".to_string(), user.id)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        dao.get_session_user("token".to_string()).await.unwrap(),
+        user
+    );
+
+    dao.delete_session("token".to_string()).await.unwrap();
+
+    assert!(matches!(
+        dao.get_session_user("token".to_string()).await,
+        Err(TodoError::NotFound)
+    ));
+}
+
+pub(crate) async fn test_session_expires_after_ttl<D: TestDao>() {
+    let clock = FakeClock::new(0);
+    let dao = D::empty(clock.clone()).await;
+
+    let user = dao
+        .create_user("alice".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+    dao.create_session("token".to_string(), user.id)
+        .await
+        .unwrap();
+
+    clock.advance(SESSION_TTL - Duration::from_millis(1));
+    assert_eq!(
+        dao.get_session_user("token".to_string()).await.unwrap(),
+        user
+    );
+
+    clock.advance(Duration::from_millis(1));
+    let result = dao.get_session_user("token".to_string()).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_add_blank_todo_rejected_by_database<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    for blank in ["   ", "\t", "\n", " \r\n\t "] {
+        let result = dao
+            .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, blank))
+            .await;
+
+        assert!(
+            matches!(result, Err(TodoError::Validation(_))),
+            "{:?} should be rejected",
+            blank
+        );
+    }
+}
+
+pub(crate) async fn test_get_lists_default<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let lists = dao.get_lists(USER_ID).await.unwrap();
+
+    assert_eq!(lists, vec![List::new(DEFAULT_LIST_ID, "Todos")]);
+}
+
+pub(crate) async fn test_create_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let list = dao
+        .create_list(USER_ID, "Groceries".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(list.name, "Groceries");
+    assert_eq!(dao.get_list(USER_ID, list.id).await.unwrap(), list);
+    assert_eq!(dao.get_lists(USER_ID).await.unwrap().len(), 2);
+}
+
+pub(crate) async fn test_rename_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let list = dao
+        .rename_list(USER_ID, DEFAULT_LIST_ID, "Chores".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(list, List::new(DEFAULT_LIST_ID, "Chores"));
+}
+
+pub(crate) async fn test_rename_nonexistent_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.rename_list(USER_ID, 999, "Chores".to_string()).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_delete_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let list = dao
+        .create_list(USER_ID, "Groceries".to_string())
+        .await
+        .unwrap();
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(list.id, "Buy milk"))
+        .await
+        .unwrap();
+    dao.delete_list(USER_ID, list.id).await.unwrap();
+
+    assert!(matches!(
+        dao.get_list(USER_ID, list.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.get_todo(USER_ID, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+}
+
+pub(crate) async fn test_delete_last_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.delete_list(USER_ID, DEFAULT_LIST_ID).await;
+
+    assert!(matches!(result, Err(TodoError::Conflict(_))));
+    assert_eq!(dao.get_lists(USER_ID).await.unwrap().len(), 1);
+}
+
+pub(crate) async fn test_delete_nonexistent_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.delete_list(USER_ID, 999).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_lists_keep_todos_separate<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let list = dao
+        .create_list(USER_ID, "Groceries".to_string())
+        .await
+        .unwrap();
+    dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Do laundry"))
+        .await
+        .unwrap();
+    let milk = dao
+        .add_todo(USER_ID, NewTodo::new(list.id, "Buy milk"))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        dao.get_todos(USER_ID, TodoQuery::new(list.id))
+            .await
+            .unwrap(),
+        vec![milk]
+    );
+    assert_eq!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+pub(crate) async fn test_add_todo_to_nonexistent_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.add_todo(USER_ID, NewTodo::new(999, "Buy milk")).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_get_todos_empty<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todos = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+
+    assert!(todos.is_empty());
+}
+
+pub(crate) async fn test_get_todos<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy eggs"))
+        .await
+        .unwrap();
+    dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Make breakfast"))
+        .await
+        .unwrap();
+    let todos = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+
+    assert_eq!(3, todos.len());
+    let descriptions = todos
+        .into_iter()
+        .map(|t| t.description)
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        descriptions,
+        HashSet::from([
+            "Buy milk".to_string(),
+            "Buy eggs".to_string(),
+            "Make breakfast".to_string()
+        ])
+    );
+}
+
+pub(crate) async fn test_get_todos_by_completion<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let milk = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy eggs"))
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, milk.id).await.unwrap();
+
+    let descriptions = async |filter| {
+        dao.get_todos(
+            USER_ID,
+            TodoQuery {
+                filter,
+                ..TodoQuery::new(DEFAULT_LIST_ID)
+            },
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.description)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        descriptions(TodoFilter::All).await,
+        ["Buy milk", "Buy eggs"]
+    );
+    assert_eq!(descriptions(TodoFilter::Active).await, ["Buy eggs"]);
+    assert_eq!(descriptions(TodoFilter::Completed).await, ["Buy milk"]);
+}
+
+pub(crate) async fn test_add_todo<D: TestDao>() {
+    let pool = get_dao::<D>().await;
+
+    let todo = pool
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+
+    assert_eq!(todo.description, "Buy milk");
+    assert!(todo.completed_at.is_none());
+    assert_eq!(todo.created_at, NOW);
+    assert_eq!(todo.updated_at, NOW);
+}
+
+pub(crate) async fn test_add_todo_with_due_date<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let due_at = parse_due_date("2025-12-31").unwrap();
+
+    let todo = dao
+        .add_todo(
+            USER_ID,
+            NewTodo {
+                due_at,
+                ..NewTodo::new(DEFAULT_LIST_ID, "Buy milk")
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(todo.due_at, due_at);
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), todo);
+}
+
+pub(crate) async fn test_get_todos_by_due_date<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    for (description, due_date) in [
+        ("Someday", ""),
+        ("Later", "2025-12-31"),
+        ("Sooner", "2025-12-01"),
+    ] {
+        dao.add_todo(
+            USER_ID,
+            NewTodo {
+                due_at: parse_due_date(due_date).unwrap(),
+                ..NewTodo::new(DEFAULT_LIST_ID, description)
+            },
+        )
+        .await
+        .unwrap();
+    }
+    let todos = dao
+        .get_todos(
+            USER_ID,
+            TodoQuery {
+                order: TodoOrder::DueDate,
+                ..TodoQuery::new(DEFAULT_LIST_ID)
+            },
+        )
+        .await
+        .unwrap();
+
+    let descriptions = todos
+        .iter()
+        .map(|t| t.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(descriptions, vec!["Sooner", "Later", "Someday"]);
+}
+
+pub(crate) async fn test_get_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    let fetched = dao.get_todo(USER_ID, todo.id).await.unwrap();
+
+    assert_eq!(fetched, todo);
+}
+
+pub(crate) async fn test_get_nonexistent_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.get_todo(USER_ID, 999).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_update_description<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy mlik"))
+        .await
+        .unwrap();
+    let updated = dao
+        .update_description(USER_ID, todo.id, "Buy milk".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(updated.id, todo.id);
+    assert_eq!(updated.description, "Buy milk");
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), updated);
+}
+
+pub(crate) async fn test_update_description_nonexistent_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao
+        .update_description(USER_ID, 999, "Buy milk".to_string())
+        .await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_add_todo_with_priority<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(
+            USER_ID,
+            NewTodo {
+                priority: Priority::Urgent,
+                ..NewTodo::new(DEFAULT_LIST_ID, "Buy milk")
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(todo.priority, Priority::Urgent);
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), todo);
+}
+
+pub(crate) async fn test_set_priority<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    let updated = dao
+        .set_priority(USER_ID, todo.id, Priority::High)
+        .await
+        .unwrap();
+
+    assert_eq!(updated.priority, Priority::High);
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), updated);
+}
+
+pub(crate) async fn test_complete_recurring_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let weekly: Recurrence = "FREQ=WEEKLY".parse().unwrap();
+    let due = NaiveDate::from_ymd_opt(2099, 1, 5).unwrap();
+
+    let todo = dao
+        .add_todo(
+            USER_ID,
+            NewTodo {
+                due_at: parse_due_date("2099-01-05").unwrap(),
+                priority: Priority::High,
+                tags: vec!["chores".to_string()],
+                recurrence: Some(weekly.clone()),
+                ..NewTodo::new(DEFAULT_LIST_ID, "Take out the bins")
+            },
+        )
+        .await
+        .unwrap();
+    let completed = dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+
+    assert_eq!(completed.completed_at, Some(NOW));
+    let todos = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+    assert_eq!(todos.len(), 2);
+    let next = &todos[1];
+    assert!(!next.is_completed());
+    assert_eq!(next.description, "Take out the bins");
+    assert_eq!(next.due_date(), Some(weekly.next_after(due)));
+    assert_eq!(next.priority, Priority::High);
+    assert_eq!(next.tags, vec!["chores"]);
+    assert_eq!(next.recurrence, Some(weekly));
+
+    // reopening it leaves the next one alone
+    dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+    assert_eq!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+pub(crate) async fn test_complete_recurring_todo_again<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let weekly: Recurrence = "FREQ=WEEKLY".parse().unwrap();
+
+    let todo = dao
+        .add_todo(
+            USER_ID,
+            NewTodo {
+                recurrence: Some(weekly),
+                ..NewTodo::new(DEFAULT_LIST_ID, "Take out the bins")
+            },
+        )
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+    dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+    dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+
+    // the occurrence from the first time is still open, so that's the one
+    let todos = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+    assert_eq!(todos.len(), 2);
+    assert!(todos[0].is_completed());
+    assert!(!todos[1].is_completed());
+
+    // once that one's done too, it has a next occurrence of its own
+    dao.toggle_todo(USER_ID, todos[1].id).await.unwrap();
+    assert_eq!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+}
+
+pub(crate) async fn test_complete_todo_with_recurring_subtask<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let weekly: Recurrence = "FREQ=WEEKLY".parse().unwrap();
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Clean the house"))
+        .await
+        .unwrap();
+    let plants = dao
+        .add_subtask(USER_ID, parent.id, "Water the plants".to_string())
+        .await
+        .unwrap();
+    dao.set_recurrence(USER_ID, plants.id, Some(weekly.clone()))
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, parent.id).await.unwrap();
+
+    // it can't go back under the parent, which is done, so it goes next to it
+    let todos = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+    assert_eq!(todos.len(), 2);
+    assert!(todos[0].is_completed());
+    assert!(todos[0].subtasks[0].is_completed());
+    let next = &todos[1];
+    assert!(!next.is_completed());
+    assert_eq!(next.description, "Water the plants");
+    assert_eq!(next.parent_id, None);
+    assert_eq!(next.recurrence, Some(weekly));
+}
+
+pub(crate) async fn test_complete_recurring_todo_with_recurring_subtask<
+    D: TestDao,
+>() {
+    let dao = get_dao::<D>().await;
+    let weekly: Recurrence = "FREQ=WEEKLY".parse().unwrap();
+
+    let parent = dao
+        .add_todo(
+            USER_ID,
+            NewTodo {
+                recurrence: Some(weekly.clone()),
+                ..NewTodo::new(DEFAULT_LIST_ID, "Clean the house")
+            },
+        )
+        .await
+        .unwrap();
+    let plants = dao
+        .add_subtask(USER_ID, parent.id, "Water the plants".to_string())
+        .await
+        .unwrap();
+    dao.set_recurrence(USER_ID, plants.id, Some(weekly))
+        .await
+        .unwrap();
+    dao.add_subtask(USER_ID, parent.id, "Dust".to_string())
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, parent.id).await.unwrap();
+
+    // the recurring subtask comes back under the parent's next occurrence
+    let todos = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+    assert_eq!(todos.len(), 2);
+    let next = &todos[1];
+    assert!(!next.is_completed());
+    assert_eq!(next.description, "Clean the house");
+    assert_eq!(next.subtasks.len(), 1);
+    assert!(!next.subtasks[0].is_completed());
+    assert_eq!(next.subtasks[0].description, "Water the plants");
+}
+
+pub(crate) async fn test_complete_todo_without_recurrence<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+
+    assert_eq!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+pub(crate) async fn test_set_recurrence<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let monthly: Recurrence = "FREQ=MONTHLY".parse().unwrap();
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Pay rent"))
+        .await
+        .unwrap();
+    let updated = dao
+        .set_recurrence(USER_ID, todo.id, Some(monthly.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(updated.recurrence, Some(monthly));
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), updated);
+
+    let cleared = dao.set_recurrence(USER_ID, todo.id, None).await.unwrap();
+    assert_eq!(cleared.recurrence, None);
+}
+
+pub(crate) async fn test_set_priority_nonexistent_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.set_priority(USER_ID, 999, Priority::High).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_get_todos_by_priority<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    for (description, priority) in [
+        ("Whenever", Priority::Low),
+        ("Now", Priority::Urgent),
+        ("Soon", Priority::High),
+        ("Sometime", Priority::Normal),
+    ] {
+        dao.add_todo(
+            USER_ID,
+            NewTodo {
+                priority,
+                ..NewTodo::new(DEFAULT_LIST_ID, description)
+            },
+        )
+        .await
+        .unwrap();
+    }
+    let todos = dao
+        .get_todos(
+            USER_ID,
+            TodoQuery {
+                order: TodoOrder::Priority,
+                ..TodoQuery::new(DEFAULT_LIST_ID)
+            },
+        )
+        .await
+        .unwrap();
+
+    let descriptions = todos
+        .iter()
+        .map(|t| t.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(descriptions, vec!["Now", "Soon", "Sometime", "Whenever"]);
+}
+
+pub(crate) async fn test_timestamps<D: TestDao>() {
+    let (dao, clock) = get_dao_with_clock::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+
+    assert_eq!(todo.created_at, NOW);
+    assert_eq!(todo.updated_at, NOW);
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), todo);
+
+    clock.advance(Duration::from_secs(60));
+    let renamed = dao
+        .update_description(USER_ID, todo.id, "Buy oat milk".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(renamed.created_at, NOW);
+    assert_eq!(renamed.updated_at, NOW + 60_000);
+
+    clock.advance(Duration::from_secs(60));
+    let tagged = dao
+        .tag_todo(USER_ID, todo.id, "groceries".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(tagged.updated_at, NOW + 120_000);
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), tagged);
+
+    // moving it around isn't a change to the todo itself
+    let other = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy eggs"))
+        .await
+        .unwrap();
+    let moved = dao
+        .move_todo(USER_ID, todo.id, None, Some(other.id))
+        .await
+        .unwrap();
+
+    assert_eq!(moved.updated_at, NOW + 120_000);
+}
+
+pub(crate) async fn test_get_todos_by_updated<D: TestDao>() {
+    let (dao, clock) = get_dao_with_clock::<D>().await;
+
+    let mut ids = vec![];
+    for description in ["Buy milk", "Buy eggs", "Buy bread"] {
+        let todo = dao
+            .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, description))
+            .await
+            .unwrap();
+        ids.push(todo.id);
+        clock.advance(Duration::from_secs(1));
+    }
+    dao.toggle_todo(USER_ID, ids[0]).await.unwrap();
+
+    let todos = dao
+        .get_todos(
+            USER_ID,
+            TodoQuery {
+                order: TodoOrder::Updated,
+                ..TodoQuery::new(DEFAULT_LIST_ID)
+            },
+        )
+        .await
+        .unwrap();
+
+    let descriptions = todos
+        .iter()
+        .map(|t| t.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(descriptions, vec!["Buy milk", "Buy bread", "Buy eggs"]);
+}
+
+async fn descriptions_in_order(dao: &impl TodoDao) -> Vec<String> {
+    dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.description)
+        .collect()
+}
+
+pub(crate) async fn test_move_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let mut ids = vec![];
+    for description in ["A", "B", "C", "D"] {
+        let todo = dao
+            .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, description))
+            .await
+            .unwrap();
+        ids.push(todo.id);
+    }
+    let (a, b, c, d) = (ids[0], ids[1], ids[2], ids[3]);
+
+    // between two others
+    let moved = dao.move_todo(USER_ID, d, Some(b), Some(a)).await.unwrap();
+    assert_eq!(moved.description, "D");
+    assert_eq!(descriptions_in_order(&dao).await, ["A", "D", "B", "C"]);
+
+    // to the top
+    dao.move_todo(USER_ID, c, Some(a), None).await.unwrap();
+    assert_eq!(descriptions_in_order(&dao).await, ["C", "A", "D", "B"]);
+
+    // to the bottom
+    dao.move_todo(USER_ID, c, None, Some(b)).await.unwrap();
+    assert_eq!(descriptions_in_order(&dao).await, ["A", "D", "B", "C"]);
+
+    // only saying what it goes after still keeps it before the rest
+    dao.move_todo(USER_ID, c, None, Some(a)).await.unwrap();
+    assert_eq!(descriptions_in_order(&dao).await, ["A", "C", "D", "B"]);
+
+    // new todos go at the bottom
+    dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "E"))
+        .await
+        .unwrap();
+    assert_eq!(descriptions_in_order(&dao).await, ["A", "C", "D", "B", "E"]);
+}
+
+pub(crate) async fn test_move_todo_renumbers_when_out_of_room<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let a = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "A"))
+        .await
+        .unwrap();
+    let b = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "B"))
+        .await
+        .unwrap();
+    let c = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "C"))
+        .await
+        .unwrap();
+
+    // keep squeezing todos into the gap after A until it runs out
+    let (mut first, mut second) = (b.id, c.id);
+    for _ in 0..100 {
+        dao.move_todo(USER_ID, second, Some(first), Some(a.id))
+            .await
+            .unwrap();
+        std::mem::swap(&mut first, &mut second);
+    }
+
+    let expected = if first == b.id {
+        ["A", "B", "C"]
+    } else {
+        ["A", "C", "B"]
+    };
+    assert_eq!(descriptions_in_order(&dao).await, expected);
+}
+
+pub(crate) async fn test_move_todo_invalid<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let other_list =
+        dao.create_list(USER_ID, "Other".to_string()).await.unwrap();
+
+    let a = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "A"))
+        .await
+        .unwrap();
+    let b = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "B"))
+        .await
+        .unwrap();
+    let elsewhere = dao
+        .add_todo(USER_ID, NewTodo::new(other_list.id, "Elsewhere"))
+        .await
+        .unwrap();
+
+    for (before, after) in [
+        (None, None),
+        (Some(a.id), Some(b.id)),
+        (Some(elsewhere.id), None),
+        (Some(a.id), None),
+    ] {
+        let result = dao.move_todo(USER_ID, a.id, before, after).await;
+        assert!(
+            matches!(result, Err(TodoError::Validation(_))),
+            "{:?} {:?}",
+            before,
+            after
+        );
+    }
+    assert!(matches!(
+        dao.move_todo(USER_ID, 999, Some(a.id), None).await,
+        Err(TodoError::NotFound)
+    ));
+    assert_eq!(descriptions_in_order(&dao).await, ["A", "B"]);
+}
+
+pub(crate) async fn test_subtasks<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    let packing = dao
+        .add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    let boxes = dao
+        .add_subtask(USER_ID, packing.id, "Buy boxes".to_string())
+        .await
+        .unwrap();
+    let movers = dao
+        .add_subtask(USER_ID, parent.id, "Book movers".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(packing.parent_id, Some(parent.id));
+    assert_eq!(packing.list_id, DEFAULT_LIST_ID);
+
+    // only the top level comes back, with the rest nested inside
+    let todos = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].id, parent.id);
+    assert_eq!(todos[0].progress(), Some((0, 2)));
+    let subtasks = &todos[0].subtasks;
+    assert_eq!(
+        subtasks.iter().map(|t| t.id).collect::<Vec<_>>(),
+        vec![packing.id, movers.id]
+    );
+    assert_eq!(subtasks[0].subtasks, vec![boxes.clone()]);
+
+    assert_eq!(
+        dao.get_subtasks(USER_ID, parent.id).await.unwrap(),
+        *subtasks
+    );
+    assert_eq!(dao.get_todo(USER_ID, parent.id).await.unwrap(), todos[0]);
+}
+
+pub(crate) async fn test_add_subtask_nonexistent_parent<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.add_subtask(USER_ID, 999, "Pack".to_string()).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_toggle_todo_with_subtasks<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    let packing = dao
+        .add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    let boxes = dao
+        .add_subtask(USER_ID, packing.id, "Buy boxes".to_string())
+        .await
+        .unwrap();
+
+    // completing a todo completes everything underneath it
+    let completed = dao.toggle_todo(USER_ID, parent.id).await.unwrap();
+    assert!(completed.is_completed());
+    assert!(completed.subtasks[0].is_completed());
+    assert!(completed.subtasks[0].subtasks[0].is_completed());
+    assert_eq!(completed.progress(), Some((1, 1)));
+
+    // reopening a subtask reopens everything above it
+    let reopened = dao.toggle_todo(USER_ID, boxes.id).await.unwrap();
+    assert!(!reopened.is_completed());
+    assert!(
+        !dao.get_todo(USER_ID, packing.id)
+            .await
+            .unwrap()
+            .is_completed()
+    );
+    assert!(
+        !dao.get_todo(USER_ID, parent.id)
+            .await
+            .unwrap()
+            .is_completed()
+    );
+
+    // and so does adding a new subtask to a completed todo
+    dao.toggle_todo(USER_ID, parent.id).await.unwrap();
+    dao.add_subtask(USER_ID, packing.id, "Buy tape".to_string())
+        .await
+        .unwrap();
+    let parent = dao.get_todo(USER_ID, parent.id).await.unwrap();
+    assert!(!parent.is_completed());
+    assert!(!parent.subtasks[0].is_completed());
+    assert!(parent.subtasks[0].subtasks[0].is_completed());
+}
+
+pub(crate) async fn test_delete_todo_with_subtasks<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    let packing = dao
+        .add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    let boxes = dao
+        .add_subtask(USER_ID, packing.id, "Buy boxes".to_string())
+        .await
+        .unwrap();
+
+    dao.delete_todo(USER_ID, parent.id).await.unwrap();
+
+    for id in [packing.id, boxes.id] {
+        assert!(matches!(
+            dao.get_todo(USER_ID, id).await,
+            Err(TodoError::NotFound)
+        ));
+    }
+    assert!(
+        dao.search(USER_ID, "boxes".to_string())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+pub(crate) async fn test_move_subtask<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    let other = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    let packing = dao
+        .add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    let movers = dao
+        .add_subtask(USER_ID, parent.id, "Book movers".to_string())
+        .await
+        .unwrap();
+
+    dao.move_todo(USER_ID, movers.id, Some(packing.id), None)
+        .await
+        .unwrap();
+    let subtasks = dao.get_subtasks(USER_ID, parent.id).await.unwrap();
+    assert_eq!(
+        subtasks.iter().map(|t| t.id).collect::<Vec<_>>(),
+        vec![movers.id, packing.id]
+    );
+
+    // subtasks stay under their parent
+    assert!(matches!(
+        dao.move_todo(USER_ID, movers.id, Some(other.id), None)
+            .await,
+        Err(TodoError::Validation(_))
+    ));
+}
+
+pub(crate) async fn test_add_todo_with_tags<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(
+            USER_ID,
+            NewTodo {
+                tags: vec!["home".to_string(), "errands".to_string()],
+                ..NewTodo::new(DEFAULT_LIST_ID, "Buy milk")
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(todo.tags, vec!["errands", "home"]);
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), todo);
+}
+
+pub(crate) async fn test_tag_and_untag_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    let tagged = dao
+        .tag_todo(USER_ID, todo.id, "errands".to_string())
+        .await
+        .unwrap();
+    // tagging twice is harmless
+    dao.tag_todo(USER_ID, todo.id, "errands".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(tagged.tags, vec!["errands"]);
+    assert_eq!(dao.get_tags(USER_ID).await.unwrap(), vec!["errands"]);
+
+    let untagged = dao
+        .untag_todo(USER_ID, todo.id, "errands".to_string())
+        .await
+        .unwrap();
+
+    assert!(untagged.tags.is_empty());
+    assert!(dao.get_tags(USER_ID).await.unwrap().is_empty());
+}
+
+pub(crate) async fn test_tag_nonexistent_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.tag_todo(USER_ID, 999, "errands".to_string()).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_get_todos_by_tag<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    for (description, tags) in [
+        ("Buy milk", vec!["errands"]),
+        ("Write report", vec!["work"]),
+        ("Post letter", vec!["errands", "work"]),
+    ] {
+        dao.add_todo(
+            USER_ID,
+            NewTodo {
+                tags: tags.into_iter().map(str::to_string).collect(),
+                ..NewTodo::new(DEFAULT_LIST_ID, description)
+            },
+        )
+        .await
+        .unwrap();
+    }
+    let todos = dao
+        .get_todos(
+            USER_ID,
+            TodoQuery {
+                tag: Some("errands".to_string()),
+                ..TodoQuery::new(DEFAULT_LIST_ID)
+            },
+        )
+        .await
+        .unwrap();
+
+    let descriptions = todos
+        .iter()
+        .map(|t| t.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(descriptions, vec!["Buy milk", "Post letter"]);
+    assert_eq!(todos[1].tags, vec!["errands", "work"]);
+}
+
+pub(crate) async fn test_toggle_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let mut todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    todo = dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+
+    assert_eq!(todo.completed_at, Some(NOW));
+}
+
+pub(crate) async fn test_toggle_nonexistent_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.toggle_todo(USER_ID, 999).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_delete_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.delete_todo(USER_ID, todo.id).await.unwrap();
+
+    assert!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+pub(crate) async fn test_delete_nonexistent_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.delete_todo(USER_ID, 999).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+/// Adds todos to the default list, returning their ids.
+async fn add_todos(dao: &impl TodoDao, descriptions: &[&str]) -> Vec<i64> {
+    let mut ids = vec![];
+    for description in descriptions {
+        let todo = dao
+            .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, *description))
+            .await
+            .unwrap();
+        ids.push(todo.id);
+    }
+    ids
+}
+
+pub(crate) async fn test_complete_all<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let ids = add_todos(&dao, &["Buy milk", "Buy eggs", "Buy bread"]).await;
+    let packing = dao
+        .add_subtask(USER_ID, ids[0], "Find the list".to_string())
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, ids[1]).await.unwrap();
+
+    let completed = dao.complete_all(USER_ID, DEFAULT_LIST_ID).await.unwrap();
+
+    assert_eq!(
+        completed.iter().map(|todo| todo.id).collect::<Vec<_>>(),
+        vec![ids[0], ids[2]]
+    );
+    assert!(completed.iter().all(|todo| todo.completed_at == Some(NOW)));
+    assert_eq!(completed[0].subtasks[0].id, packing.id);
+    assert!(completed[0].subtasks[0].is_completed());
+
+    let reopened = dao.uncomplete_all(USER_ID, DEFAULT_LIST_ID).await.unwrap();
+
+    assert_eq!(reopened.len(), 3);
+    assert!(reopened.iter().all(|todo| !todo.is_completed()));
+    // like reopening them one at a time, subtasks stay done
+    assert!(reopened[0].subtasks[0].is_completed());
+}
+
+pub(crate) async fn test_delete_completed<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let ids = add_todos(&dao, &["Buy milk", "Buy eggs"]).await;
+    dao.add_subtask(USER_ID, ids[0], "Find the list".to_string())
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, ids[0]).await.unwrap();
+
+    let trashed = dao
+        .delete_completed(USER_ID, DEFAULT_LIST_ID)
+        .await
+        .unwrap();
+
+    assert_eq!(trashed.len(), 1);
+    assert_eq!(trashed[0].id, ids[0]);
+    assert_eq!(trashed[0].deleted_at, Some(NOW));
+    let remaining = dao
+        .get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+        .await
+        .unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].id, ids[1]);
+    // the subtask went with it, and comes back with it too
+    let restored = dao.restore_todo(USER_ID, ids[0]).await.unwrap();
+    assert_eq!(restored.subtasks.len(), 1);
+}
+
+pub(crate) async fn test_bulk_update<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let ids = add_todos(&dao, &["Buy milk", "Buy eggs", "Buy bread"]).await;
+
+    let completed = dao
+        .bulk_update(USER_ID, vec![ids[2], ids[0]], BulkAction::Complete)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        completed.iter().map(|todo| todo.id).collect::<Vec<_>>(),
+        vec![ids[2], ids[0]]
+    );
+    assert!(completed.iter().all(Todo::is_completed));
+    assert!(!dao.get_todo(USER_ID, ids[1]).await.unwrap().is_completed());
+
+    // already completed ones are left alone
+    let completed = dao
+        .bulk_update(USER_ID, vec![ids[0], ids[1]], BulkAction::Complete)
+        .await
+        .unwrap();
+
+    assert!(completed.iter().all(Todo::is_completed));
+    assert_eq!(
+        history_of(&dao, ids[0])
+            .await
+            .into_iter()
+            .filter(|(kind, _)| *kind == TodoEventKind::Completed)
+            .count(),
+        1
+    );
+}
+
+pub(crate) async fn test_bulk_update_is_all_or_nothing<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let ids = add_todos(&dao, &["Buy milk", "Buy eggs"]).await;
+
+    let result = dao
+        .bulk_update(USER_ID, vec![ids[0], 999], BulkAction::Delete)
+        .await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+    assert_eq!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+pub(crate) async fn test_bulk_delete_with_subtasks<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let ids = add_todos(&dao, &["Move house"]).await;
+    let packing = dao
+        .add_subtask(USER_ID, ids[0], "Pack".to_string())
+        .await
+        .unwrap();
+
+    // the subtask is already in the trash by the time it comes up
+    let trashed = dao
+        .bulk_update(USER_ID, vec![ids[0], packing.id], BulkAction::Delete)
+        .await
+        .unwrap();
+
+    assert!(trashed.iter().all(|todo| todo.deleted_at == Some(NOW)));
+    assert_eq!(dao.get_trash(USER_ID).await.unwrap().len(), 1);
+}
+
+pub(crate) async fn test_import<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let earlier = NOW - 60 * 60 * 1000;
+
+    let imported = dao
+        .import(
+            USER_ID,
+            DEFAULT_LIST_ID,
+            vec![
+                ImportedTodo {
+                    completed: true,
+                    completed_at: Some(earlier),
+                    created_at: Some(earlier - 1000),
+                    tags: vec!["home".to_string()],
+                    ..ImportedTodo::new("Water the plants")
+                },
+                ImportedTodo {
+                    list: Some("Groceries".to_string()),
+                    priority: Priority::High,
+                    ..ImportedTodo::new("Go shopping")
+                },
+                ImportedTodo {
+                    list: Some("Ignored".to_string()),
+                    parent: Some(1),
+                    ..ImportedTodo::new("Buy milk")
+                },
+                ImportedTodo {
+                    list: Some("Groceries".to_string()),
+                    completed: true,
+                    ..ImportedTodo::new("Put it all away")
+                },
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(imported.len(), 4);
+    // completion and timestamps are kept, and made up where they're missing
+    let watering = dao.get_todo(USER_ID, imported[0].id).await.unwrap();
+    assert_eq!(watering.list_id, DEFAULT_LIST_ID);
+    assert_eq!(watering.completed_at, Some(earlier));
+    assert_eq!(watering.created_at, earlier - 1000);
+    assert_eq!(watering.updated_at, earlier);
+    assert_eq!(watering.tags, vec!["home"]);
+    assert_eq!(imported[1].created_at, NOW);
+    assert_eq!(imported[1].updated_at, NOW);
+    assert_eq!(imported[3].completed_at, Some(NOW));
+    // todos naming a list share one new one, and subtasks follow their parent
+    let lists = dao.get_lists(USER_ID).await.unwrap();
+    assert_eq!(
+        lists
+            .iter()
+            .map(|list| list.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Todos", "Groceries"]
+    );
+    let groceries = dao
+        .get_todos(USER_ID, TodoQuery::new(lists[1].id))
+        .await
+        .unwrap();
+    assert_eq!(
+        groceries
+            .iter()
+            .map(|todo| todo.description.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Go shopping", "Put it all away"]
+    );
+    assert_eq!(groceries[0].priority, Priority::High);
+    assert_eq!(groceries[0].subtasks.len(), 1);
+    assert_eq!(groceries[0].subtasks[0].description, "Buy milk");
+    assert_eq!(
+        history_of(&dao, imported[2].id).await,
+        vec![(TodoEventKind::Created, Some("Buy milk".to_string()))]
+    );
+}
+
+pub(crate) async fn test_import_is_all_or_nothing<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let blank = dao
+        .import(
+            USER_ID,
+            DEFAULT_LIST_ID,
+            vec![
+                ImportedTodo {
+                    list: Some("Groceries".to_string()),
+                    ..ImportedTodo::new("Buy milk")
+                },
+                ImportedTodo::new(" "),
+            ],
+        )
+        .await;
+    let misplaced = dao
+        .import(
+            USER_ID,
+            DEFAULT_LIST_ID,
+            vec![ImportedTodo {
+                parent: Some(0),
+                ..ImportedTodo::new("Buy milk")
+            }],
+        )
+        .await;
+
+    assert!(matches!(blank, Err(TodoError::Validation(_))));
+    assert!(matches!(misplaced, Err(TodoError::Validation(_))));
+    assert_eq!(dao.get_lists(USER_ID).await.unwrap().len(), 1);
+    assert!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+pub(crate) async fn test_import_into_nonexistent_list<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao
+        .import(USER_ID, 999, vec![ImportedTodo::new("Buy milk")])
+        .await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_trash_and_restore<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.tag_todo(USER_ID, todo.id, "groceries".to_string())
+        .await
+        .unwrap();
+
+    let trashed = dao.delete_todo(USER_ID, todo.id).await.unwrap();
+
+    assert_eq!(trashed.deleted_at, Some(NOW));
+    assert!(matches!(
+        dao.get_todo(USER_ID, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(dao.get_tags(USER_ID).await.unwrap().is_empty());
+    assert!(
+        dao.search(USER_ID, "milk".to_string())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(dao.get_trash(USER_ID).await.unwrap(), vec![trashed]);
+
+    let restored = dao.restore_todo(USER_ID, todo.id).await.unwrap();
+
+    assert_eq!(restored.deleted_at, None);
+    assert_eq!(restored.tags, vec!["groceries".to_string()]);
+    assert!(dao.get_trash(USER_ID).await.unwrap().is_empty());
+    assert_eq!(
+        dao.get_todos(USER_ID, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap(),
+        vec![restored]
+    );
+}
+
+pub(crate) async fn test_restore_todo_with_subtasks<D: TestDao>() {
+    let (dao, clock) = get_dao_with_clock::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    let packing = dao
+        .add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    let boxes = dao
+        .add_subtask(USER_ID, parent.id, "Buy boxes".to_string())
+        .await
+        .unwrap();
+    // deleted on its own first, so it stays put when the parent comes back
+    dao.delete_todo(USER_ID, boxes.id).await.unwrap();
+    clock.advance(Duration::from_secs(1));
+    dao.delete_todo(USER_ID, parent.id).await.unwrap();
+
+    let trash = dao.get_trash(USER_ID).await.unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].id, parent.id);
+    assert!(matches!(
+        dao.restore_todo(USER_ID, packing.id).await,
+        Err(TodoError::Conflict(_))
+    ));
+
+    let restored = dao.restore_todo(USER_ID, parent.id).await.unwrap();
+
+    assert_eq!(restored.subtasks.len(), 1);
+    assert_eq!(restored.subtasks[0].id, packing.id);
+    let trash = dao.get_trash(USER_ID).await.unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].id, boxes.id);
+}
+
+pub(crate) async fn test_complete_todo_with_trashed_subtask<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    let packing = dao
+        .add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    let boxes = dao
+        .add_subtask(USER_ID, parent.id, "Buy boxes".to_string())
+        .await
+        .unwrap();
+    dao.delete_todo(USER_ID, boxes.id).await.unwrap();
+
+    let completed = dao.toggle_todo(USER_ID, parent.id).await.unwrap();
+    assert!(completed.is_completed());
+    assert_eq!(completed.subtasks.len(), 1);
+    assert_eq!(completed.subtasks[0].id, packing.id);
+    assert!(completed.subtasks[0].is_completed());
+
+    // what was in the trash comes back the way it went in
+    let restored = dao.restore_todo(USER_ID, boxes.id).await.unwrap();
+    assert!(!restored.is_completed());
+    assert_eq!(
+        history_of(&dao, boxes.id).await,
+        vec![
+            (TodoEventKind::Created, Some("Buy boxes".to_string())),
+            (TodoEventKind::Trashed, None),
+            (TodoEventKind::Restored, None),
+        ]
+    );
+}
+
+pub(crate) async fn test_restore_todo_not_in_trash<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        dao.restore_todo(USER_ID, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.purge_todo(USER_ID, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+}
+
+pub(crate) async fn test_purge_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.delete_todo(USER_ID, todo.id).await.unwrap();
+
+    dao.purge_todo(USER_ID, todo.id).await.unwrap();
+
+    assert!(dao.get_trash(USER_ID).await.unwrap().is_empty());
+    assert!(matches!(
+        dao.restore_todo(USER_ID, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+}
+
+pub(crate) async fn test_empty_trash<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    dao.add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    let kept = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.delete_todo(USER_ID, parent.id).await.unwrap();
+
+    assert_eq!(dao.empty_trash(USER_ID).await.unwrap(), 2);
+    assert!(dao.get_trash(USER_ID).await.unwrap().is_empty());
+    assert_eq!(dao.get_todo(USER_ID, kept.id).await.unwrap(), kept);
+}
+
+pub(crate) async fn test_purge_expired_trash<D: TestDao>() {
+    let (dao, clock) = get_dao_with_clock::<D>().await;
+    let retention = Duration::from_secs(30 * 24 * 60 * 60);
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+    dao.delete_todo(USER_ID, todo.id).await.unwrap();
+    clock.advance(retention);
+
+    assert_eq!(dao.purge_expired_trash(retention).await.unwrap(), 0);
+    assert_eq!(dao.get_trash(USER_ID).await.unwrap().len(), 1);
+
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(dao.purge_expired_trash(retention).await.unwrap(), 1);
+    assert!(dao.get_trash(USER_ID).await.unwrap().is_empty());
+}
+
+/// The kind and detail of each change in a todo's history, oldest first.
+async fn history_of(
+    dao: &impl TodoDao,
+    id: i64,
+) -> Vec<(TodoEventKind, Option<String>)> {
+    dao.history(USER_ID, id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| (event.kind, event.detail))
+        .collect()
+}
+
+pub(crate) async fn test_history<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy mlik"))
+        .await
+        .unwrap();
+    dao.update_description(USER_ID, todo.id, "Buy milk".to_string())
+        .await
+        .unwrap();
+    dao.set_priority(USER_ID, todo.id, Priority::High)
+        .await
+        .unwrap();
+    dao.tag_todo(USER_ID, todo.id, "groceries".to_string())
+        .await
+        .unwrap();
+    // tagging it again doesn't change anything, so it isn't recorded
+    dao.tag_todo(USER_ID, todo.id, "groceries".to_string())
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+    dao.toggle_todo(USER_ID, todo.id).await.unwrap();
+    dao.delete_todo(USER_ID, todo.id).await.unwrap();
+    dao.restore_todo(USER_ID, todo.id).await.unwrap();
+
+    let events = dao.history(USER_ID, todo.id).await.unwrap();
+
+    assert!(events.iter().all(|event| event.todo_id == todo.id
+        && event.username.as_deref() == Some("alice")
+        && event.created_at == NOW));
+    assert_eq!(
+        history_of(&dao, todo.id).await,
+        vec![
+            (TodoEventKind::Created, Some("Buy mlik".to_string())),
+            (TodoEventKind::Renamed, Some("Buy milk".to_string())),
+            (TodoEventKind::Prioritized, Some("high".to_string())),
+            (TodoEventKind::Tagged, Some("groceries".to_string())),
+            (TodoEventKind::Completed, None),
+            (TodoEventKind::Reopened, None),
+            (TodoEventKind::Trashed, None),
+            (TodoEventKind::Restored, None),
+        ]
+    );
+}
+
+pub(crate) async fn test_history_of_subtasks<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let parent = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Move house"))
+        .await
+        .unwrap();
+    let packing = dao
+        .add_subtask(USER_ID, parent.id, "Pack".to_string())
+        .await
+        .unwrap();
+    dao.toggle_todo(USER_ID, parent.id).await.unwrap();
+    dao.toggle_todo(USER_ID, packing.id).await.unwrap();
+
+    // changes that cascade are recorded against every todo they touch
+    for id in [parent.id, packing.id] {
+        let kinds = history_of(&dao, id)
+            .await
+            .into_iter()
+            .map(|(kind, _)| kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                TodoEventKind::Created,
+                TodoEventKind::Completed,
+                TodoEventKind::Reopened,
+            ]
+        );
+    }
+}
+
+pub(crate) async fn test_history_nonexistent_todo<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let result = dao.history(USER_ID, 999).await;
+
+    assert!(matches!(result, Err(TodoError::NotFound)));
+}
+
+pub(crate) async fn test_search<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let groceries = dao
+        .create_list(USER_ID, "Groceries".to_string())
+        .await
+        .unwrap();
+    for (list_id, description) in [
+        (DEFAULT_LIST_ID, "Call the milkman"),
+        (groceries.id, "Buy milk"),
+        (groceries.id, "Buy eggs"),
+    ] {
+        dao.add_todo(USER_ID, NewTodo::new(list_id, description))
+            .await
+            .unwrap();
+    }
+
+    let hits = dao.search(USER_ID, "milk".to_string()).await.unwrap();
+
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].todo.description, "Buy milk");
+    assert_eq!(hits[0].list_name, "Groceries");
+    assert_eq!(
+        hits[0].snippet,
+        vec![
+            SnippetPart::new("Buy ", false),
+            SnippetPart::new("milk", true)
+        ]
+    );
+    assert_eq!(hits[1].todo.description, "Call the milkman");
+
+    let hits = dao.search(USER_ID, "buy EGG".to_string()).await.unwrap();
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].todo.description, "Buy eggs");
+}
+
+pub(crate) async fn test_search_follows_changes<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy mlik"))
+        .await
+        .unwrap();
+    dao.update_description(USER_ID, todo.id, "Buy milk".to_string())
+        .await
+        .unwrap();
+
+    assert!(
+        dao.search(USER_ID, "mlik".to_string())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        dao.search(USER_ID, "milk".to_string()).await.unwrap().len(),
+        1
+    );
+
+    dao.delete_todo(USER_ID, todo.id).await.unwrap();
+
+    assert!(
+        dao.search(USER_ID, "milk".to_string())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+pub(crate) async fn test_search_ignores_query_syntax<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk AND eggs"))
+        .await
+        .unwrap();
+
+    for text in ["\"", "AND", "milk*", "NEAR(", "-", "   "] {
+        assert!(dao.search(USER_ID, text.to_string()).await.is_ok());
+    }
+}
+
+pub(crate) async fn test_search_folds_accents<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+
+    for description in ["Make crème brûlée", "Buy cream"] {
+        dao.add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, description))
+            .await
+            .unwrap();
+    }
+
+    // accents don't matter on either side, but the snippet keeps them
+    let hits = dao.search(USER_ID, "creme".to_string()).await.unwrap();
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+        hits[0].snippet,
+        vec![
+            SnippetPart::new("Make ", false),
+            SnippetPart::new("crème", true),
+            SnippetPart::new(" brûlée", false)
+        ]
+    );
+
+    let hits = dao.search(USER_ID, "BRÛLÉE".to_string()).await.unwrap();
+
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+        hits[0].snippet,
+        vec![
+            SnippetPart::new("Make crème ", false),
+            SnippetPart::new("brûlée", true)
+        ]
+    );
+
+    let hits = dao.search(USER_ID, "cre".to_string()).await.unwrap();
+
+    assert_eq!(
+        hits.iter()
+            .map(|hit| hit.todo.description.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Buy cream", "Make crème brûlée"]
+    );
+}
+
+pub(crate) async fn test_other_users_todos_are_hidden<D: TestDao>() {
+    let dao = get_dao::<D>().await;
+    let bob = dao
+        .create_user("bob".to_string(), "hash".to_string())
+        .await
+        .unwrap();
+
+    let todo = dao
+        .add_todo(USER_ID, NewTodo::new(DEFAULT_LIST_ID, "Buy milk"))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        dao.get_list(bob.id, DEFAULT_LIST_ID).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(
+        dao.get_todos(bob.id, TodoQuery::new(DEFAULT_LIST_ID))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(matches!(
+        dao.get_todo(bob.id, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.add_todo(bob.id, NewTodo::new(DEFAULT_LIST_ID, "Buy eggs"))
+            .await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.update_description(bob.id, todo.id, "Buy eggs".to_string())
+            .await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.set_priority(bob.id, todo.id, Priority::High).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.set_recurrence(bob.id, todo.id, None).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.toggle_todo(bob.id, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.tag_todo(bob.id, todo.id, "mine".to_string()).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.untag_todo(bob.id, todo.id, "mine".to_string()).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.add_subtask(bob.id, todo.id, "Buy eggs".to_string())
+            .await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.get_subtasks(bob.id, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.move_todo(bob.id, todo.id, None, None).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.delete_todo(bob.id, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.history(bob.id, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.bulk_update(bob.id, vec![todo.id], BulkAction::Complete)
+            .await,
+        Err(TodoError::NotFound)
+    ));
+    for result in [
+        dao.complete_all(bob.id, DEFAULT_LIST_ID).await,
+        dao.uncomplete_all(bob.id, DEFAULT_LIST_ID).await,
+        dao.delete_completed(bob.id, DEFAULT_LIST_ID).await,
+    ] {
+        assert!(matches!(result, Err(TodoError::NotFound)));
+    }
+    dao.delete_todo(USER_ID, todo.id).await.unwrap();
+    assert!(dao.get_trash(bob.id).await.unwrap().is_empty());
+    assert!(matches!(
+        dao.purge_todo(bob.id, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    assert_eq!(dao.empty_trash(bob.id).await.unwrap(), 0);
+    assert!(matches!(
+        dao.restore_todo(bob.id, todo.id).await,
+        Err(TodoError::NotFound)
+    ));
+    dao.restore_todo(USER_ID, todo.id).await.unwrap();
+    assert!(
+        dao.search(bob.id, "milk".to_string())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(matches!(
+        dao.rename_list(bob.id, DEFAULT_LIST_ID, "Mine".to_string())
+            .await,
+        Err(TodoError::NotFound)
+    ));
+    assert!(matches!(
+        dao.delete_list(bob.id, DEFAULT_LIST_ID).await,
+        Err(TodoError::NotFound)
+    ));
+    assert_eq!(dao.get_todo(USER_ID, todo.id).await.unwrap(), todo);
+}
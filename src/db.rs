@@ -1,12 +1,40 @@
 use std::str::FromStr;
 
 use sqlx::{
-    SqlitePool, error::Error, migrate::Migrator, sqlite::SqliteConnectOptions,
+    PgPool, SqlitePool, error::Error, migrate::Migrator,
+    sqlite::SqliteConnectOptions,
 };
 
 // Embeds all ./migrations into the application binary
 static MIGRATOR: Migrator = sqlx::migrate!();
 
+// Postgres gets its own set, since the SQL doesn't carry over
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations-postgres");
+
+/// The kinds of database the app can keep its data in.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+    /// Nothing is saved; everything's gone when the app stops.
+    Memory,
+}
+
+impl Backend {
+    /// Works out which database a connection string is for from its scheme.
+    pub fn from_url(connection_string: &str) -> Result<Self, Error> {
+        match connection_string.split_once(':') {
+            Some(("sqlite", _)) => Ok(Backend::Sqlite),
+            Some(("postgres" | "postgresql", _)) => Ok(Backend::Postgres),
+            Some(("memory", _)) => Ok(Backend::Memory),
+            _ => Err(Error::Configuration(
+                "database urls must start with sqlite:, postgres: or memory:"
+                    .into(),
+            )),
+        }
+    }
+}
+
 pub async fn create_pool(connection_string: &str) -> Result<SqlitePool, Error> {
     let pool = SqlitePool::connect_with(
         SqliteConnectOptions::from_str(connection_string)?
@@ -17,6 +45,16 @@ pub async fn create_pool(connection_string: &str) -> Result<SqlitePool, Error> {
     Ok(pool)
 }
 
+/// Connects to Postgres and brings its schema up to date. Unlike SQLite, the
+/// database itself has to exist already.
+pub async fn create_postgres_pool(
+    connection_string: &str,
+) -> Result<PgPool, Error> {
+    let pool = PgPool::connect(connection_string).await?;
+    POSTGRES_MIGRATOR.run(&pool).await?;
+    Ok(pool)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -36,4 +74,20 @@ mod tests {
 
         assert_eq!(actual, 1, "todos table does not exist");
     }
+
+    #[test]
+    fn test_backend_from_url() {
+        for (url, backend) in [
+            ("sqlite::memory:", Backend::Sqlite),
+            ("sqlite://db/app.db", Backend::Sqlite),
+            ("postgres://localhost/mash_todo", Backend::Postgres),
+            ("postgresql://user:pass@db:5432/todos", Backend::Postgres),
+            ("memory://", Backend::Memory),
+        ] {
+            assert_eq!(Backend::from_url(url).unwrap(), backend, "{}", url);
+        }
+        for url in ["mysql://localhost/todos", "db/app.db", ""] {
+            assert!(Backend::from_url(url).is_err(), "{}", url);
+        }
+    }
 }
@@ -0,0 +1,197 @@
+use crate::views::{ErrorNotification, RenderResponse, ValidationMessage};
+use axum::{
+    Extension,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use tracing::error;
+
+pub type Result<T, E = TodoError> = std::result::Result<T, E>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TodoError {
+    #[error("todo not found")]
+    NotFound,
+    #[error("you need to log in first")]
+    Unauthorized,
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("storage error: {0}")]
+    Storage(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl TodoError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            TodoError::NotFound => StatusCode::NOT_FOUND,
+            TodoError::Unauthorized => StatusCode::UNAUTHORIZED,
+            TodoError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            TodoError::Conflict(_) => StatusCode::CONFLICT,
+            TodoError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Attached to every error response so middleware can re-render the message
+/// for clients that don't want HTML.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorMessage(pub String);
+
+impl From<sqlx::Error> for TodoError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => TodoError::NotFound,
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                TodoError::Conflict(e.message().to_string())
+            }
+            sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                TodoError::Conflict(e.message().to_string())
+            }
+            sqlx::Error::Database(e) if e.is_check_violation() => {
+                TodoError::Validation(e.message().to_string())
+            }
+            e => TodoError::Storage(e.into()),
+        }
+    }
+}
+
+impl IntoResponse for TodoError {
+    fn into_response(self) -> Response {
+        let message = match &self {
+            TodoError::Storage(e) => {
+                error!("internal error: {:?}", e);
+                "something went wrong".to_string()
+            }
+            e => e.to_string(),
+        };
+        // htmx leaves the triggering element alone and drops the message into
+        // the page's error area instead
+        (
+            self.status(),
+            [("HX-Retarget", "#errors"), ("HX-Reswap", "innerHTML")],
+            Extension(ErrorMessage(message.clone())),
+            RenderResponse(ErrorNotification(message)),
+        )
+            .into_response()
+    }
+}
+
+/// A [`TodoError`] raised by a single form field. Validation failures are
+/// rendered next to the field instead of the page-wide error area.
+#[derive(Debug)]
+pub struct FieldError {
+    pub target: String,
+    pub error: TodoError,
+}
+
+impl FieldError {
+    pub fn new<S>(target: S, error: TodoError) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            target: target.into(),
+            error,
+        }
+    }
+}
+
+impl IntoResponse for FieldError {
+    fn into_response(self) -> Response {
+        match self.error {
+            TodoError::Validation(message) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                [
+                    ("HX-Retarget", self.target),
+                    ("HX-Reswap", "innerHTML".to_string()),
+                ],
+                Extension(ErrorMessage(message.clone())),
+                RenderResponse(ValidationMessage(message)),
+            )
+                .into_response(),
+            e => e.into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+
+    #[test]
+    fn test_status() {
+        assert_eq!(TodoError::NotFound.status(), StatusCode::NOT_FOUND);
+        assert_eq!(TodoError::Unauthorized.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            TodoError::Validation("bad".to_string()).status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            TodoError::Conflict("taken".to_string()).status(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            TodoError::Storage("nope".into()).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_from_row_not_found() {
+        let error = TodoError::from(sqlx::Error::RowNotFound);
+
+        assert!(matches!(error, TodoError::NotFound));
+    }
+
+    #[test]
+    fn test_into_response() {
+        let response = TodoError::NotFound.into_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["HX-Retarget"], "#errors");
+        assert_eq!(response.headers()["HX-Reswap"], "innerHTML");
+        assert_eq!(
+            response.extensions().get::<ErrorMessage>(),
+            Some(&ErrorMessage("todo not found".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_field_error_into_response() {
+        let error = FieldError::new(
+            "#add-todo-error",
+            TodoError::Validation("bad".to_string()),
+        );
+
+        let response = error.into_response();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#add-todo-error");
+    }
+
+    #[test]
+    fn test_field_error_falls_back_for_other_errors() {
+        let error = FieldError::new("#add-todo-error", TodoError::NotFound);
+
+        let response = error.into_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["HX-Retarget"], "#errors");
+    }
+
+    #[tokio::test]
+    async fn test_storage_error_is_masked() {
+        let error = TodoError::Storage("disk on fire".into());
+
+        let response = error.into_response();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("something went wrong"));
+        assert!(!body.contains("disk on fire"));
+    }
+}
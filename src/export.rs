@@ -0,0 +1,472 @@
+use crate::{
+    errors::{Result, TodoError},
+    recurrence::Recurrence,
+    todos::{List, Priority, Todo, TodoDao, TodoQuery},
+};
+use chrono::{DateTime, SecondsFormat};
+use futures_util::{Stream, StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use std::{future::ready, sync::Arc};
+
+/// The header row of a CSV export.
+pub const CSV_COLUMNS: [&str; 11] = [
+    "id",
+    "list",
+    "parent_id",
+    "description",
+    "completed_at",
+    "created_at",
+    "updated_at",
+    "due_date",
+    "priority",
+    "tags",
+    "recurrence",
+];
+
+/// The kinds of file todos can be exported as.
+#[derive(
+    Deserialize,
+    Serialize,
+    clap::ValueEnum,
+    PartialEq,
+    Eq,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// An array of todos.
+    #[default]
+    Json,
+    /// A row per todo, with tags separated by spaces.
+    Csv,
+    /// A checklist per list, with subtasks indented under their parents.
+    #[serde(rename = "md")]
+    #[value(name = "md")]
+    Markdown,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    fn header(self) -> String {
+        match self {
+            ExportFormat::Json => "[".to_string(),
+            ExportFormat::Csv => format!("{}\n", CSV_COLUMNS.join(",")),
+            ExportFormat::Markdown => String::new(),
+        }
+    }
+
+    fn footer(self) -> String {
+        match self {
+            ExportFormat::Json => "\n]\n".to_string(),
+            ExportFormat::Csv | ExportFormat::Markdown => String::new(),
+        }
+    }
+
+    /// Renders one list's todos. `written` is how many todos from earlier
+    /// lists have already gone out, since JSON needs to know where to put the
+    /// commas.
+    fn list(
+        self,
+        list: &List,
+        todos: &[ExportedTodo],
+        written: usize,
+        first_list: bool,
+    ) -> Result<String> {
+        let mut out = String::new();
+        match self {
+            ExportFormat::Json => {
+                for (i, todo) in todos.iter().enumerate() {
+                    let json = serde_json::to_string(todo)
+                        .map_err(|e| TodoError::Storage(e.into()))?;
+                    let separator = if written + i == 0 { "" } else { "," };
+                    out.push_str(&format!("{}\n  {}", separator, json));
+                }
+            }
+            ExportFormat::Csv => {
+                for todo in todos {
+                    out.push_str(&csv_row(todo));
+                }
+            }
+            ExportFormat::Markdown => {
+                if !first_list {
+                    out.push('\n');
+                }
+                out.push_str(&format!("## {}\n", list.name));
+                if !todos.is_empty() {
+                    out.push('\n');
+                }
+                for todo in todos {
+                    out.push_str(&markdown_item(todo));
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// A todo the way it's written out: flattened, with its list named and its
+/// timestamps in ISO-8601 instead of millis.
+#[derive(Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct ExportedTodo {
+    pub id: i64,
+    pub list: String,
+    pub parent_id: Option<i64>,
+    pub description: String,
+    pub completed_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// `YYYY-MM-DD`.
+    pub due_date: Option<String>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+    /// How deep in subtasks the todo is, 0 for the top of the list.
+    #[serde(skip)]
+    pub depth: usize,
+}
+
+impl ExportedTodo {
+    pub fn new(list: &List, todo: Todo, depth: usize) -> Self {
+        Self {
+            id: todo.id,
+            list: list.name.clone(),
+            parent_id: todo.parent_id,
+            description: todo.description,
+            completed_at: todo.completed_at.map(iso_8601),
+            created_at: iso_8601(todo.created_at),
+            updated_at: iso_8601(todo.updated_at),
+            due_date: todo
+                .due_at
+                .and_then(DateTime::from_timestamp_millis)
+                .map(|due| due.format("%Y-%m-%d").to_string()),
+            priority: todo.priority,
+            tags: todo.tags,
+            recurrence: todo.recurrence,
+            depth,
+        }
+    }
+}
+
+/// Millis since the epoch as a UTC timestamp, e.g.
+/// `2025-06-01T09:30:00.000Z`.
+pub fn iso_8601(millis: i64) -> String {
+    DateTime::from_timestamp_millis(millis)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Lists each todo followed by its subtasks, all the way down.
+fn flatten(
+    list: &List,
+    todos: Vec<Todo>,
+    depth: usize,
+    out: &mut Vec<ExportedTodo>,
+) {
+    for mut todo in todos {
+        let subtasks = std::mem::take(&mut todo.subtasks);
+        out.push(ExportedTodo::new(list, todo, depth));
+        flatten(list, subtasks, depth + 1, out);
+    }
+}
+
+/// Quotes a CSV field if it needs it.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_row(todo: &ExportedTodo) -> String {
+    let fields = [
+        todo.id.to_string(),
+        todo.list.clone(),
+        todo.parent_id.map(|id| id.to_string()).unwrap_or_default(),
+        todo.description.clone(),
+        todo.completed_at.clone().unwrap_or_default(),
+        todo.created_at.clone(),
+        todo.updated_at.clone(),
+        todo.due_date.clone().unwrap_or_default(),
+        todo.priority.as_str().to_string(),
+        todo.tags.join(" "),
+        todo.recurrence
+            .as_ref()
+            .map(|r| r.to_string())
+            .unwrap_or_default(),
+    ];
+    let fields: Vec<_> = fields.iter().map(|f| csv_field(f)).collect();
+    format!("{}\n", fields.join(","))
+}
+
+/// A checklist item, with everything but the description tacked on the end
+/// the way it'd be typed, e.g.
+/// `- [x] Buy milk #groceries (due 2025-06-01) (completed 2025-05-31T…)`.
+fn markdown_item(todo: &ExportedTodo) -> String {
+    let mut item = format!(
+        "{}- [{}] {}",
+        "  ".repeat(todo.depth),
+        if todo.completed_at.is_some() {
+            "x"
+        } else {
+            " "
+        },
+        todo.description
+    );
+    for tag in &todo.tags {
+        item.push_str(&format!(" #{}", tag));
+    }
+    if let Some(due_date) = &todo.due_date {
+        item.push_str(&format!(" (due {})", due_date));
+    }
+    if todo.priority != Priority::Normal {
+        item.push_str(&format!(" (priority {})", todo.priority.as_str()));
+    }
+    if let Some(recurrence) = &todo.recurrence {
+        item.push_str(&format!(" (repeats {})", recurrence));
+    }
+    if let Some(completed_at) = &todo.completed_at {
+        item.push_str(&format!(" (completed {})", completed_at));
+    }
+    item.push('\n');
+    item
+}
+
+/// Every todo in `lists` except the trashed ones, rendered as `format` one
+/// list at a time so a big export never has to be held in memory. The lists
+/// are looked up by the caller so that failing to load them can still be
+/// reported properly, before anything's been written.
+pub fn export<T>(
+    dao: T,
+    user_id: i64,
+    lists: Vec<List>,
+    format: ExportFormat,
+) -> impl Stream<Item = Result<String>> + Send + 'static
+where
+    T: TodoDao + Send + Sync + 'static,
+{
+    let dao = Arc::new(dao);
+    let todos = stream::iter(lists).then(move |list| {
+        let dao = dao.clone();
+        async move {
+            let todos = dao.get_todos(user_id, TodoQuery::new(list.id)).await?;
+            let mut rows = vec![];
+            flatten(&list, todos, 0, &mut rows);
+            Ok((list, rows))
+        }
+    });
+    // how many lists and todos have gone out so far
+    let body = todos.scan((0, 0), move |written, result: Result<_>| {
+        let chunk = result.and_then(|(list, rows): (List, Vec<_>)| {
+            let (lists, todos) = written;
+            let chunk = format.list(&list, &rows, *todos, *lists == 0);
+            *lists += 1;
+            *todos += rows.len();
+            chunk
+        });
+        ready(Some(chunk))
+    });
+    stream::once(ready(Ok(format.header())))
+        .chain(body)
+        .chain(stream::once(ready(Ok(format.footer()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        recurrence::{Frequency, Recurrence},
+        todos::MockTodoDao,
+    };
+    use futures_util::TryStreamExt;
+    use mockall::predicate;
+    use pretty_assertions::assert_eq;
+
+    /// Todos, Groceries with a subtask, and an empty list.
+    fn mock_dao() -> MockTodoDao {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_get_todos()
+            .with(predicate::eq(1), predicate::eq(TodoQuery::new(1)))
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(vec![Todo {
+                        completed_at: Some(1_748_770_200_000),
+                        created_at: 1_748_736_000_000,
+                        updated_at: 1_748_770_200_000,
+                        recurrence: Some(Recurrence::new(Frequency::Weekly)),
+                        ..Todo::new(1, "Water \"the\" plants, all of them")
+                    }])
+                })
+            });
+        mock_dao
+            .expect_get_todos()
+            .with(predicate::eq(1), predicate::eq(TodoQuery::new(2)))
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(vec![Todo {
+                        list_id: 2,
+                        due_at: Some(1_748_736_000_000),
+                        priority: Priority::High,
+                        tags: vec!["shop".to_string()],
+                        subtasks: vec![Todo {
+                            list_id: 2,
+                            parent_id: Some(2),
+                            ..Todo::new(3, "Milk")
+                        }],
+                        ..Todo::new(2, "Go shopping")
+                    }])
+                })
+            });
+        mock_dao
+            .expect_get_todos()
+            .with(predicate::eq(1), predicate::eq(TodoQuery::new(3)))
+            .returning(|_, _| Box::pin(async { Ok(vec![]) }));
+        mock_dao
+    }
+
+    async fn export_all(format: ExportFormat) -> Result<String> {
+        let lists = vec![
+            List::new(1, "Todos"),
+            List::new(2, "Groceries"),
+            List::new(3, "Someday"),
+        ];
+        let chunks: Vec<_> =
+            export(mock_dao(), 1, lists, format).try_collect().await?;
+        Ok(chunks.concat())
+    }
+
+    #[test]
+    fn test_iso_8601() {
+        assert_eq!(iso_8601(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(iso_8601(1_748_770_200_123), "2025-06-01T09:30:00.123Z");
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[tokio::test]
+    async fn test_export_json() -> Result<()> {
+        let json = export_all(ExportFormat::Json).await?;
+
+        let todos: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            todos,
+            serde_json::json!([
+                {
+                    "id": 1,
+                    "list": "Todos",
+                    "parent_id": null,
+                    "description": "Water \"the\" plants, all of them",
+                    "completed_at": "2025-06-01T09:30:00.000Z",
+                    "created_at": "2025-06-01T00:00:00.000Z",
+                    "updated_at": "2025-06-01T09:30:00.000Z",
+                    "due_date": null,
+                    "priority": "normal",
+                    "tags": [],
+                    "recurrence": "FREQ=WEEKLY",
+                },
+                {
+                    "id": 2,
+                    "list": "Groceries",
+                    "parent_id": null,
+                    "description": "Go shopping",
+                    "completed_at": null,
+                    "created_at": "1970-01-01T00:00:00.000Z",
+                    "updated_at": "1970-01-01T00:00:00.000Z",
+                    "due_date": "2025-06-01",
+                    "priority": "high",
+                    "tags": ["shop"],
+                    "recurrence": null,
+                },
+                {
+                    "id": 3,
+                    "list": "Groceries",
+                    "parent_id": 2,
+                    "description": "Milk",
+                    "completed_at": null,
+                    "created_at": "1970-01-01T00:00:00.000Z",
+                    "updated_at": "1970-01-01T00:00:00.000Z",
+                    "due_date": null,
+                    "priority": "normal",
+                    "tags": [],
+                    "recurrence": null,
+                },
+            ])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() -> Result<()> {
+        let csv = export_all(ExportFormat::Csv).await?;
+
+        assert_eq!(
+            csv,
+            "id,list,parent_id,description,completed_at,created_at,\
+             updated_at,due_date,priority,tags,recurrence\n\
+             1,Todos,,\"Water \"\"the\"\" plants, all of them\",\
+             2025-06-01T09:30:00.000Z,2025-06-01T00:00:00.000Z,\
+             2025-06-01T09:30:00.000Z,,normal,,FREQ=WEEKLY\n\
+             2,Groceries,,Go shopping,,1970-01-01T00:00:00.000Z,\
+             1970-01-01T00:00:00.000Z,2025-06-01,high,shop,\n\
+             3,Groceries,2,Milk,,1970-01-01T00:00:00.000Z,\
+             1970-01-01T00:00:00.000Z,,normal,,\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_markdown() -> Result<()> {
+        let markdown = export_all(ExportFormat::Markdown).await?;
+
+        assert_eq!(
+            markdown,
+            "## Todos\n\
+             \n\
+             - [x] Water \"the\" plants, all of them (repeats FREQ=WEEKLY) \
+             (completed 2025-06-01T09:30:00.000Z)\n\
+             \n\
+             ## Groceries\n\
+             \n\
+             - [ ] Go shopping #shop (due 2025-06-01) (priority high)\n  \
+             - [ ] Milk\n\
+             \n\
+             ## Someday\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_nothing() -> Result<()> {
+        let chunks: Vec<_> =
+            export(MockTodoDao::new(), 1, vec![], ExportFormat::Json)
+                .try_collect()
+                .await?;
+
+        assert_eq!(chunks.concat(), "[\n]\n");
+        Ok(())
+    }
+}
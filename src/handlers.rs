@@ -1,81 +1,1001 @@
 use crate::{
-    todos::TodoDao,
-    views::{AddedTodo, Home, Result, ToggledTodo},
+    auth::{
+        CurrentUser, SESSION_COOKIE, hash_password, new_session_token,
+        removal_cookie, session_cookie, verify_password,
+        verify_password_for_unknown_user,
+    },
+    errors::{self, FieldError, TodoError},
+    export::{self, ExportFormat},
+    import::{self, ImportFormat},
+    negotiate::{Format, JsonOrForm, Negotiated, Result},
+    recurrence::parse_recurrence,
+    state::{Dao, Settings},
+    todos::{
+        BulkAction, List, NewTodo, Priority, TodoDao, TodoFilter, TodoOrder,
+        TodoQuery, extract_tags, parse_due_date, validate_description,
+        validate_list_name, validate_tag,
+    },
+    users::{User, UserDao},
+    views::{
+        self, AddedSubtask, AddedTodo, BulkUpdated, CreatedList, DeletedTodo,
+        EditTodo, EditedTodo, EmptiedTrash, Home, ImportErrors, Imported,
+        Lists, Login, MovedTodo, RenamedList, ReprioritizedTodo,
+        RescheduledTodo, RestoredTodo, SearchResults, ShowTodo, Signup,
+        TaggedTodo, Tags, TodoHistory, TodoList, ToggledTodo, Trash, TrashList,
+    },
 };
 use axum::{
-    Form,
-    extract::{Path, State},
-    http::StatusCode,
-    response::ErrorResponse,
+    Json,
+    body::Body,
+    extract::{Multipart, Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Redirect, Response, Result as AxumResult},
 };
+use axum_extra::extract::CookieJar;
 use serde::{Deserialize, Serialize};
-use tracing::error;
 
-pub async fn home<T: TodoDao>(State(dao): State<T>) -> Result<Home> {
-    let all_todos = match dao.get_all_todos().await {
-        Ok(t) => t,
-        Err(e) => return Err(internal_server_error(e)),
-    };
-    Ok(Home(all_todos).into())
+/// Looks up the requested list, falling back to the user's first one when the
+/// client didn't ask for a specific list.
+async fn resolve_list<T: TodoDao>(
+    dao: &T,
+    user: &User,
+    list_id: Option<i64>,
+) -> errors::Result<List> {
+    match list_id {
+        Some(id) => dao.get_list(user.id, id).await,
+        None => dao
+            .get_lists(user.id)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(TodoError::NotFound),
+    }
+}
+
+/// Normalizes the `?tag=` filter; an empty one is the same as none at all.
+fn parse_tag_filter(tag: Option<String>) -> errors::Result<Option<String>> {
+    match tag.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(tag) => validate_tag(tag).map(Some),
+    }
+}
+
+async fn load_home<T: TodoDao>(
+    dao: &T,
+    user: User,
+    list_id: Option<i64>,
+    query: HomeQuery,
+) -> errors::Result<Home> {
+    let tag = parse_tag_filter(query.tag)?;
+    let list = resolve_list(dao, &user, list_id).await?;
+    let lists = dao.get_lists(user.id).await?;
+    let todos = dao
+        .get_todos(
+            user.id,
+            TodoQuery {
+                list_id: list.id,
+                filter: query.filter,
+                order: query.order,
+                tag: tag.clone(),
+            },
+        )
+        .await?;
+    Ok(Home {
+        user,
+        lists,
+        list,
+        filter: query.filter,
+        order: query.order,
+        tag,
+        todos,
+    })
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct HomeQuery {
+    #[serde(default)]
+    pub filter: TodoFilter,
+    #[serde(default)]
+    pub order: TodoOrder,
+    /// Only show todos with this tag.
+    pub tag: Option<String>,
+}
+
+pub async fn home<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Query(query): Query<HomeQuery>,
+) -> Result<Home> {
+    let home = load_home(&dao, user, None, query).await?;
+    Ok(Negotiated(format, home))
+}
+
+pub async fn show_list<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+    Query(query): Query<HomeQuery>,
+) -> Result<Home> {
+    let home = load_home(&dao, user, Some(id), query).await?;
+    Ok(Negotiated(format, home))
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ListQuery {
+    pub list_id: Option<i64>,
+    #[serde(default)]
+    pub filter: TodoFilter,
+    #[serde(default)]
+    pub order: TodoOrder,
+    pub tag: Option<String>,
+}
+
+pub async fn list_todos<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Query(query): Query<ListQuery>,
+) -> Result<TodoList> {
+    let tag = parse_tag_filter(query.tag)?;
+    let list = resolve_list(&dao, &user, query.list_id).await?;
+    let todos = dao
+        .get_todos(
+            user.id,
+            TodoQuery {
+                list_id: list.id,
+                filter: query.filter,
+                order: query.order,
+                tag,
+            },
+        )
+        .await?;
+    Ok(Negotiated(format, TodoList(todos)))
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct AddTodoForm {
     pub description: String,
+    /// Defaults to the first list when omitted.
+    #[serde(default)]
+    pub list_id: Option<i64>,
+    /// `YYYY-MM-DD`; blank or omitted for no due date.
+    #[serde(default)]
+    pub due_date: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// An RRULE like `FREQ=WEEKLY;BYDAY=MO`; blank or omitted if it doesn't
+    /// recur.
+    #[serde(default)]
+    pub recurrence: Option<String>,
 }
 
+/// Adds a todo. Any `#tag` words in the description are split out and
+/// attached as tags.
 pub async fn add_todo<T: TodoDao>(
-    State(dao): State<T>,
-    Form(add_todo): Form<AddTodoForm>,
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+    JsonOrForm(add_todo): JsonOrForm<AddTodoForm>,
 ) -> Result<AddedTodo> {
-    let new_todo = match dao.add_todo(add_todo.description.to_string()).await {
-        Ok(t) => t,
-        Err(e) => return Err(internal_server_error(e)),
+    let (description, tags) = extract_tags(&add_todo.description);
+    let description =
+        validate_description(&description, settings.max_description_length)
+            .map_err(|e| FieldError::new("#add-todo-error", e))?;
+    let due_at = parse_due_date(add_todo.due_date.as_deref().unwrap_or(""))
+        .map_err(|e| FieldError::new("#add-todo-error", e))?;
+    let recurrence =
+        parse_recurrence(add_todo.recurrence.as_deref().unwrap_or(""))
+            .map_err(|e| FieldError::new("#add-todo-error", e))?;
+    let list_id = match add_todo.list_id {
+        Some(id) => id,
+        None => resolve_list(&dao, &user, None).await?.id,
+    };
+    let new_todo = dao
+        .add_todo(
+            user.id,
+            NewTodo {
+                list_id,
+                description,
+                due_at,
+                priority: add_todo.priority,
+                tags,
+                recurrence,
+            },
+        )
+        .await?;
+    Ok(Negotiated(format, AddedTodo(new_todo)))
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+pub async fn search_todos<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<SearchResults> {
+    let hits = dao.search(user.id, query.q.clone()).await?;
+    Ok(Negotiated(
+        format,
+        SearchResults {
+            query: query.q,
+            hits,
+        },
+    ))
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Downloads every todo the user has, streamed out a list at a time.
+pub async fn export_todos<T>(
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Query(query): Query<ExportQuery>,
+) -> errors::Result<Response>
+where
+    T: TodoDao + Send + Sync + 'static,
+{
+    let format = query.format;
+    let lists = dao.get_lists(user.id).await?;
+    let disposition =
+        format!("attachment; filename=\"todos.{}\"", format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(export::export(dao, user.id, lists, format)),
+    )
+        .into_response())
+}
+
+/// Adds the todos in an uploaded file: all of them, or none if any line is
+/// wrong. The form has the `file`, and optionally its `format`, guessed from
+/// the file's name otherwise, and the `list_id` for todos that don't name a
+/// list.
+pub async fn import_todos<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+    mut multipart: Multipart,
+) -> Result<Imported> {
+    let (mut file, mut file_format, mut list_id) = (None, None, None);
+    while let Some(field) = multipart.next_field().await? {
+        match field.name() {
+            Some("file") => {
+                let name = field.file_name().unwrap_or_default().to_string();
+                file = Some((name, field.text().await?));
+            }
+            Some("format") => file_format = Some(field.text().await?),
+            Some("list_id") => list_id = Some(field.text().await?),
+            _ => {}
+        }
+    }
+    let invalid = |e| FieldError::new("#import-result", e);
+    let Some((name, text)) = file else {
+        return Err(invalid(TodoError::Validation(
+            "choose a file to import".to_string(),
+        ))
+        .into());
     };
+    let file_format = match file_format.as_deref().map(str::trim) {
+        None | Some("") => {
+            ImportFormat::from_filename(&name).ok_or_else(|| {
+                invalid(TodoError::Validation(format!(
+                    "can't tell what kind of file {} is; pick a format",
+                    name
+                )))
+            })?
+        }
+        Some(file_format) => file_format.parse().map_err(invalid)?,
+    };
+    let list_id = match list_id.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(id) => Some(id.parse().map_err(|_| {
+            invalid(TodoError::Validation("list_id must be a number".into()))
+        })?),
+    };
+    let list = resolve_list(&dao, &user, list_id).await?;
+
+    let todos =
+        import::parse(file_format, &text, settings.max_description_length)
+            .map_err(|lines| {
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Negotiated(format, ImportErrors::new(lines)),
+                )
+            })?;
+    let imported = dao.import(user.id, list.id, todos).await?;
+    Ok(Negotiated(format, Imported(imported)))
+}
+
+pub async fn get_todo<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> Result<ShowTodo> {
+    let todo = dao.get_todo(user.id, id).await?;
+    Ok(Negotiated(format, ShowTodo(todo)))
+}
+
+pub async fn todo_history<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> Result<TodoHistory> {
+    let events = dao.history(user.id, id).await?;
+    Ok(Negotiated(format, TodoHistory(events)))
+}
+
+pub async fn edit_todo<T: TodoDao>(
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> views::Result<EditTodo> {
+    let todo = dao.get_todo(user.id, id).await?;
+    Ok(EditTodo(todo).into())
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EditTodoForm {
+    pub description: String,
+}
+
+pub async fn update_todo<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+    Path(id): Path<i64>,
+    JsonOrForm(edit_todo): JsonOrForm<EditTodoForm>,
+) -> Result<EditedTodo> {
+    let description = validate_description(
+        &edit_todo.description,
+        settings.max_description_length,
+    )
+    .map_err(|e| FieldError::new(format!("#todo-{}-error", id), e))?;
+    let todo = dao.update_description(user.id, id, description).await?;
+    Ok(Negotiated(format, EditedTodo(todo)))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PriorityForm {
+    pub priority: Priority,
+}
+
+pub async fn set_priority<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+    JsonOrForm(priority_form): JsonOrForm<PriorityForm>,
+) -> Result<ReprioritizedTodo> {
+    let todo = dao
+        .set_priority(user.id, id, priority_form.priority)
+        .await?;
+    Ok(Negotiated(format, ReprioritizedTodo(todo)))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SubtaskForm {
+    pub description: String,
+}
+
+pub async fn add_subtask<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+    Path(id): Path<i64>,
+    JsonOrForm(subtask_form): JsonOrForm<SubtaskForm>,
+) -> Result<AddedSubtask> {
+    let description = validate_description(
+        &subtask_form.description,
+        settings.max_description_length,
+    )
+    .map_err(|e| FieldError::new(format!("#todo-{}-error", id), e))?;
+    let subtask = dao.add_subtask(user.id, id, description).await?;
+    let parent = dao.get_todo(user.id, id).await?;
+    Ok(Negotiated(format, AddedSubtask { subtask, parent }))
+}
+
+pub async fn list_subtasks<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> Result<TodoList> {
+    let subtasks = dao.get_subtasks(user.id, id).await?;
+    Ok(Negotiated(format, TodoList(subtasks)))
+}
+
+/// Where a dragged todo was dropped: between the todo it now comes `after`
+/// and the one it now comes `before`.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PositionForm {
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+}
+
+pub async fn move_todo<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+    JsonOrForm(position_form): JsonOrForm<PositionForm>,
+) -> Result<MovedTodo> {
+    let todo = dao
+        .move_todo(user.id, id, position_form.before, position_form.after)
+        .await?;
+    Ok(Negotiated(format, MovedTodo(todo)))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RecurrenceForm {
+    /// Blank or omitted to stop the todo recurring.
+    #[serde(default)]
+    pub recurrence: Option<String>,
+}
 
-    Ok(AddedTodo(new_todo).into())
+pub async fn set_recurrence<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+    JsonOrForm(recurrence_form): JsonOrForm<RecurrenceForm>,
+) -> Result<RescheduledTodo> {
+    let recurrence =
+        parse_recurrence(recurrence_form.recurrence.as_deref().unwrap_or(""))
+            .map_err(|e| FieldError::new(format!("#todo-{}-error", id), e))?;
+    let todo = dao.set_recurrence(user.id, id, recurrence).await?;
+    Ok(Negotiated(format, RescheduledTodo(todo)))
 }
 
 pub async fn toggle_todo<T: TodoDao>(
-    State(dao): State<T>,
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
     Path(id): Path<i64>,
 ) -> Result<ToggledTodo> {
-    match dao.toggle_todo(id).await {
-        Ok(todo) => Ok(ToggledTodo(todo).into()),
-        Err(e) => Err(internal_server_error(e)),
+    let todo = dao.toggle_todo(user.id, id).await?;
+    Ok(Negotiated(format, ToggledTodo(todo)))
+}
+
+pub async fn delete_todo<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> Result<DeletedTodo> {
+    // htmx swaps in any 2xx but a 204, so this has to be a 200 for the `li`
+    // to go; the toast is swapped in out of band, leaving nothing in its place
+    let todo = dao.delete_todo(user.id, id).await?;
+    Ok(Negotiated(format, DeletedTodo(todo)))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct BulkForm {
+    /// Checkboxes that aren't ticked aren't sent at all, so nothing picked
+    /// means no `ids`.
+    #[serde(default)]
+    pub ids: Vec<i64>,
+    pub action: BulkAction,
+}
+
+pub async fn bulk_update<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    JsonOrForm(form): JsonOrForm<BulkForm>,
+) -> Result<BulkUpdated> {
+    if form.ids.is_empty() {
+        return Err(
+            TodoError::Validation("pick some todos first".to_string()).into()
+        );
     }
+    let todos = dao.bulk_update(user.id, form.ids, form.action).await?;
+    Ok(Negotiated(format, BulkUpdated(todos)))
 }
 
-fn internal_server_error<E>(error: E) -> ErrorResponse
-where
-    E: std::fmt::Debug,
-{
-    error!("internal error: {:?}", error);
-    (StatusCode::INTERNAL_SERVER_ERROR, "something went wrong").into()
+pub async fn complete_all<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(list_id): Path<i64>,
+) -> Result<BulkUpdated> {
+    let todos = dao.complete_all(user.id, list_id).await?;
+    Ok(Negotiated(format, BulkUpdated(todos)))
+}
+
+pub async fn uncomplete_all<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(list_id): Path<i64>,
+) -> Result<BulkUpdated> {
+    let todos = dao.uncomplete_all(user.id, list_id).await?;
+    Ok(Negotiated(format, BulkUpdated(todos)))
+}
+
+pub async fn delete_completed<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(list_id): Path<i64>,
+) -> Result<BulkUpdated> {
+    let todos = dao.delete_completed(user.id, list_id).await?;
+    Ok(Negotiated(format, BulkUpdated(todos)))
+}
+
+pub async fn trash_page<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+) -> Result<Trash> {
+    let todos = dao.get_trash(user.id).await?;
+    Ok(Negotiated(
+        format,
+        Trash {
+            user,
+            retention_days: settings.trash_retention_days,
+            todos,
+        },
+    ))
+}
+
+pub async fn list_trash<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+) -> Result<TrashList> {
+    let todos = dao.get_trash(user.id).await?;
+    Ok(Negotiated(format, TrashList(todos)))
+}
+
+pub async fn restore_todo<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> Result<RestoredTodo> {
+    let todo = dao.restore_todo(user.id, id).await?;
+    Ok(Negotiated(format, RestoredTodo(todo)))
+}
+
+pub async fn purge_todo<T: TodoDao>(
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> AxumResult<StatusCode> {
+    dao.purge_todo(user.id, id).await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn empty_trash<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+) -> Result<EmptiedTrash> {
+    let purged = dao.empty_trash(user.id).await?;
+    Ok(Negotiated(format, EmptiedTrash { purged }))
+}
+
+pub async fn list_tags<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+) -> Result<Tags> {
+    let tags = dao.get_tags(user.id).await?;
+    Ok(Negotiated(format, Tags(tags)))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TagForm {
+    pub tag: String,
+}
+
+pub async fn tag_todo<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+    JsonOrForm(tag_form): JsonOrForm<TagForm>,
+) -> Result<TaggedTodo> {
+    let tag = validate_tag(&tag_form.tag)
+        .map_err(|e| FieldError::new(format!("#todo-{}-error", id), e))?;
+    let todo = dao.tag_todo(user.id, id, tag).await?;
+    Ok(Negotiated(format, TaggedTodo(todo)))
+}
+
+pub async fn untag_todo<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path((id, tag)): Path<(i64, String)>,
+) -> Result<TaggedTodo> {
+    let todo = dao.untag_todo(user.id, id, tag).await?;
+    Ok(Negotiated(format, TaggedTodo(todo)))
+}
+
+pub async fn list_lists<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+) -> Result<Lists> {
+    let lists = dao.get_lists(user.id).await?;
+    Ok(Negotiated(format, Lists(lists)))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ListForm {
+    pub name: String,
+}
+
+pub async fn create_list<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    JsonOrForm(list_form): JsonOrForm<ListForm>,
+) -> Result<CreatedList> {
+    let name = validate_list_name(&list_form.name)
+        .map_err(|e| FieldError::new("#new-list-error", e))?;
+    let list = dao.create_list(user.id, name).await?;
+    Ok(Negotiated(format, CreatedList(list)))
+}
+
+pub async fn rename_list<T: TodoDao>(
+    format: Format,
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+    JsonOrForm(list_form): JsonOrForm<ListForm>,
+) -> Result<RenamedList> {
+    let name = validate_list_name(&list_form.name)
+        .map_err(|e| FieldError::new("#rename-list-error", e))?;
+    let list = dao.rename_list(user.id, id, name).await?;
+    Ok(Negotiated(format, RenamedList(list)))
+}
+
+pub async fn delete_list<T: TodoDao>(
+    CurrentUser(user): CurrentUser,
+    State(Dao(dao)): State<Dao<T>>,
+    Path(id): Path<i64>,
+) -> AxumResult<impl IntoResponse> {
+    dao.delete_list(user.id, id).await?;
+    // the page we're on no longer exists, so send htmx back home
+    Ok((StatusCode::OK, [("HX-Redirect", "/")]))
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CredentialsForm {
+    pub username: String,
+    pub password: String,
+}
+
+/// Shortest password we'll accept at signup.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+fn validate_credentials(form: &CredentialsForm) -> errors::Result<String> {
+    let username = form.username.trim();
+    if username.len() < 3 || username.len() > 32 {
+        return Err(TodoError::Validation(
+            "username must be between 3 and 32 characters".to_string(),
+        ));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(TodoError::Validation(
+            "username may only contain letters, numbers, '_' and '-'"
+                .to_string(),
+        ));
+    }
+    if form.password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(TodoError::Validation(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+    Ok(username.to_string())
+}
+
+/// Starts a session for a freshly authenticated user. Browsers are sent to the
+/// home page; API clients get the user back.
+async fn start_session<T: UserDao>(
+    format: Format,
+    dao: &T,
+    jar: CookieJar,
+    settings: &Settings,
+    user: User,
+) -> errors::Result<Response> {
+    let token = new_session_token();
+    dao.create_session(token.clone(), user.id).await?;
+    let jar = jar.add(session_cookie(token, settings.secure_cookies));
+    Ok(match format {
+        Format::Html => (jar, Redirect::to("/")).into_response(),
+        Format::Json => (jar, Json(user)).into_response(),
+    })
+}
+
+pub async fn signup_page() -> views::Result<Signup> {
+    Ok(Signup::default().into())
+}
+
+async fn create_account<T: UserDao>(
+    format: Format,
+    dao: &T,
+    jar: CookieJar,
+    settings: &Settings,
+    form: CredentialsForm,
+) -> errors::Result<Response> {
+    let username = validate_credentials(&form)?;
+    let password_hash = hash_password(form.password).await?;
+    let user = dao.create_user(username, password_hash).await?;
+    start_session(format, dao, jar, settings, user).await
+}
+
+pub async fn signup<T: UserDao>(
+    format: Format,
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+    jar: CookieJar,
+    JsonOrForm(form): JsonOrForm<CredentialsForm>,
+) -> Response {
+    let username = form.username.clone();
+    match create_account(format, &dao, jar, &settings, form).await {
+        Ok(response) => response,
+        // re-render the page so the user can fix their input
+        Err(e @ (TodoError::Validation(_) | TodoError::Conflict(_)))
+            if format == Format::Html =>
+        {
+            let signup = Signup {
+                username,
+                error: Some(e.to_string()),
+            };
+            (e.status(), views::RenderResponse(signup)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn login_page() -> views::Result<Login> {
+    Ok(Login::default().into())
+}
+
+async fn authenticate<T: UserDao>(
+    format: Format,
+    dao: &T,
+    jar: CookieJar,
+    settings: &Settings,
+    form: CredentialsForm,
+) -> errors::Result<Response> {
+    // unknown users and wrong passwords look the same from the outside
+    let credentials =
+        match dao.get_credentials(form.username.trim().to_string()).await {
+            Ok(credentials) => credentials,
+            Err(TodoError::NotFound) => {
+                // a real user's password would be checked first, which takes
+                // a noticeable amount of time, so spend it here too
+                verify_password_for_unknown_user(form.password).await?;
+                return Err(TodoError::Unauthorized);
+            }
+            Err(e) => return Err(e),
+        };
+    let password_hash = credentials.password_hash.clone();
+    if !verify_password(form.password, password_hash).await? {
+        return Err(TodoError::Unauthorized);
+    }
+    start_session(format, dao, jar, settings, credentials.into()).await
+}
+
+pub async fn login<T: UserDao>(
+    format: Format,
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+    jar: CookieJar,
+    JsonOrForm(form): JsonOrForm<CredentialsForm>,
+) -> Response {
+    let username = form.username.clone();
+    match authenticate(format, &dao, jar, &settings, form).await {
+        Ok(response) => response,
+        Err(TodoError::Unauthorized) if format == Format::Html => {
+            let login = Login {
+                username,
+                error: Some("invalid username or password".to_string()),
+            };
+            (StatusCode::UNAUTHORIZED, views::RenderResponse(login))
+                .into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn logout<T: UserDao>(
+    State(Dao(dao)): State<Dao<T>>,
+    State(settings): State<Settings>,
+    jar: CookieJar,
+) -> AxumResult<impl IntoResponse> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        dao.delete_session(cookie.value().to_string()).await?;
+    }
+    let cookie = removal_cookie(settings.secure_cookies);
+    Ok((jar.remove(cookie), Redirect::to("/login")))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        todos::{MockTodoDao, Todo},
+        errors::TodoError,
+        recurrence::{Frequency, Recurrence},
+        todos::{
+            MockTodoDao, SearchHit, SnippetPart, Todo, TodoEvent, TodoEventKind,
+        },
+        users::MockUserDao,
         views::RenderResponse,
     };
     use anyhow::{Result, anyhow};
+    use axum::response::IntoResponse;
     use mockall::predicate;
 
+    fn current_user() -> CurrentUser {
+        CurrentUser(User::new(1, "alice"))
+    }
+
     #[tokio::test]
     async fn test_home() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_get_lists().returning(|_| {
+            Box::pin(async {
+                Ok(vec![List::new(1, "Todos"), List::new(2, "Groceries")])
+            })
+        });
+        mock_dao
+            .expect_get_todos()
+            .with(predicate::eq(1), predicate::eq(TodoQuery::new(1)))
+            .returning(|_, _| {
+                Box::pin(async { Ok(vec![Todo::new(1, "todo")]) })
+            });
+        let dao = State(Dao(mock_dao));
+
+        let Negotiated(_, home_result) = home(
+            Format::Html,
+            current_user(),
+            dao,
+            Query(HomeQuery::default()),
+        )
+        .await
+        .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(
+            home_result,
+            Home {
+                user: User::new(1, "alice"),
+                lists: vec![List::new(1, "Todos"), List::new(2, "Groceries")],
+                list: List::new(1, "Todos"),
+                filter: TodoFilter::All,
+                order: TodoOrder::Manual,
+                tag: None,
+                todos: vec![Todo::new(1, "todo")],
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_show_list() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_get_list()
+            .with(predicate::eq(1), predicate::eq(2))
+            .returning(|_, _| {
+                Box::pin(async { Ok(List::new(2, "Groceries")) })
+            });
+        mock_dao.expect_get_lists().returning(|_| {
+            Box::pin(async {
+                Ok(vec![List::new(1, "Todos"), List::new(2, "Groceries")])
+            })
+        });
+        mock_dao
+            .expect_get_todos()
+            .with(
+                predicate::eq(1),
+                predicate::eq(TodoQuery {
+                    filter: TodoFilter::Active,
+                    order: TodoOrder::DueDate,
+                    tag: Some("errands".to_string()),
+                    ..TodoQuery::new(2)
+                }),
+            )
+            .returning(|_, _| Box::pin(async { Ok(vec![]) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(2);
+        let order = Query(HomeQuery {
+            filter: TodoFilter::Active,
+            order: TodoOrder::DueDate,
+            tag: Some("#Errands".to_string()),
+        });
+
+        let Negotiated(_, home_result) =
+            show_list(Format::Html, current_user(), dao, path, order)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(home_result.list, List::new(2, "Groceries"));
+        assert!(home_result.todos.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_show_list_not_found() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_get_list()
+            .with(predicate::eq(1), predicate::eq(2))
+            .returning(|_, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(2);
+        let order = Query(HomeQuery::default());
+
+        let show_result =
+            show_list(Format::Html, current_user(), dao, path, order).await;
+
+        let response = show_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_to_default_list() -> Result<()> {
         let mut mock_dao = MockTodoDao::new();
         mock_dao
-            .expect_get_all_todos()
-            .returning(|| Box::pin(async { Ok(vec![Todo::new(1, "todo")]) }));
-        let dao = State(mock_dao);
+            .expect_get_lists()
+            .returning(|_| Box::pin(async { Ok(vec![List::new(3, "Todos")]) }));
+        mock_dao
+            .expect_add_todo()
+            .with(
+                predicate::eq(1),
+                predicate::eq(NewTodo::new(3, "description")),
+            )
+            .returning(|_, _| {
+                Box::pin(async { Ok(Todo::new(1, "description")) })
+            });
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
+            description: "description".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        });
 
-        let RenderResponse(home_result) =
-            home(dao).await.map_err(|e| anyhow!("{:?}", e))?;
+        let Negotiated(_, add_result) = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await
+        .map_err(|e| anyhow!("{:?}", e))?;
 
-        assert_eq!(home_result, Home(vec![Todo::new(1, "todo")]));
+        assert_eq!(add_result, AddedTodo(Todo::new(1, "description")));
         Ok(())
     }
 
@@ -84,17 +1004,33 @@ mod tests {
         let mut mock_dao = MockTodoDao::new();
         mock_dao
             .expect_add_todo()
-            .with(predicate::eq("description".to_string()))
-            .returning(|_| Box::pin(async { Ok(Todo::new(1, "description")) }));
-        let dao = State(mock_dao);
-        let form = Form(AddTodoForm {
+            .with(
+                predicate::eq(1),
+                predicate::eq(NewTodo::new(1, "description")),
+            )
+            .returning(|_, _| {
+                Box::pin(async { Ok(Todo::new(1, "description")) })
+            });
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
             description: "description".to_string(),
+            list_id: Some(1),
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
         });
 
-        let RenderResponse(add_result) =
-            add_todo(dao, form).await.map_err(|e| anyhow!("{:?}", e))?;
+        let Negotiated(_, add_result) = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await
+        .map_err(|e| anyhow!("{:?}", e))?;
 
-        assert_eq!(add_result, AddedTodo(Todo::new(1, "description")).into());
+        assert_eq!(add_result, AddedTodo(Todo::new(1, "description")));
         Ok(())
     }
 
@@ -103,50 +1039,1125 @@ mod tests {
         let mut mock_dao = MockTodoDao::new();
         mock_dao
             .expect_add_todo()
-            .with(predicate::eq("description".to_string()))
-            .returning(|_| Box::pin(async { Err(anyhow!("nope")) }));
-        let dao = State(mock_dao);
-        let form = Form(AddTodoForm {
+            .with(
+                predicate::eq(1),
+                predicate::eq(NewTodo::new(1, "description")),
+            )
+            .returning(|_, _| {
+                Box::pin(async { Err(TodoError::Storage("nope".into())) })
+            });
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
             description: "description".to_string(),
+            list_id: Some(1),
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
         });
 
-        let add_result = add_todo(dao, form).await;
+        let add_result = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await;
 
         assert!(add_result.is_err());
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_toggle_todo() -> Result<()> {
+    async fn test_add_todo_trims_description() -> Result<()> {
         let mut mock_dao = MockTodoDao::new();
         mock_dao
-            .expect_toggle_todo()
-            .with(predicate::eq(1))
-            .returning(|_| Box::pin(async { Ok(Todo::new(1, "todo")) }));
-        let dao = State(mock_dao);
+            .expect_add_todo()
+            .with(
+                predicate::eq(1),
+                predicate::eq(NewTodo::new(1, "description")),
+            )
+            .returning(|_, _| {
+                Box::pin(async { Ok(Todo::new(1, "description")) })
+            });
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
+            description: "  description  ".to_string(),
+            list_id: Some(1),
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        });
+
+        let Negotiated(_, add_result) = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await
+        .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(add_result, AddedTodo(Todo::new(1, "description")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_invalid() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_add_todo().never();
+        let dao = State(Dao(mock_dao));
+        let settings = State(Settings {
+            max_description_length: 5,
+            ..Settings::default()
+        });
+        let form = JsonOrForm(AddTodoForm {
+            description: "description".to_string(),
+            list_id: Some(1),
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        });
+
+        let add_result =
+            add_todo(Format::Html, current_user(), dao, settings, form).await;
+
+        let response = add_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#add-todo-error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_extracts_tags() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_add_todo()
+            .with(
+                predicate::eq(1),
+                predicate::eq(NewTodo {
+                    tags: vec!["errands".to_string()],
+                    ..NewTodo::new(1, "Buy milk")
+                }),
+            )
+            .returning(|_, _| Box::pin(async { Ok(Todo::new(1, "Buy milk")) }));
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
+            description: "Buy milk #errands".to_string(),
+            list_id: Some(1),
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        });
+
+        let add_result = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await;
+
+        assert!(add_result.is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_only_tags() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_add_todo().never();
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
+            description: "#errands".to_string(),
+            list_id: Some(1),
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        });
+
+        let add_result = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await;
+
+        let response = add_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_invalid_due_date() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_add_todo().never();
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
+            description: "description".to_string(),
+            list_id: Some(1),
+            due_date: Some("tomorrow".to_string()),
+            priority: Priority::Normal,
+            recurrence: None,
+        });
+
+        let add_result = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await;
+
+        let response = add_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#add-todo-error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_invalid_recurrence() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_add_todo().never();
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(AddTodoForm {
+            description: "description".to_string(),
+            list_id: Some(1),
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: Some("FREQ=HOURLY".to_string()),
+        });
+
+        let add_result = add_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            form,
+        )
+        .await;
+
+        let response = add_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#add-todo-error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_get_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Ok(Todo::new(1, "todo")) }));
+        let dao = State(Dao(mock_dao));
         let path = Path(1);
 
-        let RenderResponse(toggle_result) = toggle_todo(dao, path)
-            .await
-            .map_err(|e| anyhow!("{:?}", e))?;
+        let Negotiated(_, get_result) =
+            get_todo(Format::Html, current_user(), dao, path)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
 
-        assert_eq!(toggle_result, ToggledTodo(Todo::new(1, "todo")));
+        assert_eq!(get_result, ShowTodo(Todo::new(1, "todo")));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_toggle_todo_failed() -> Result<()> {
+    async fn test_get_todo_not_found() -> Result<()> {
         let mut mock_dao = MockTodoDao::new();
         mock_dao
-            .expect_toggle_todo()
-            .with(predicate::eq(1))
-            .returning(|_| Box::pin(async { Err(anyhow!("nope")) }));
-        let dao = State(mock_dao);
+            .expect_get_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
         let path = Path(1);
 
-        let toggle_result = toggle_todo(dao, path).await;
+        let get_result =
+            get_todo(Format::Html, current_user(), dao, path).await;
 
-        assert!(toggle_result.is_err());
+        let response = get_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_edit_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_get_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Ok(Todo::new(1, "todo")) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let RenderResponse(edit_result) = edit_todo(current_user(), dao, path)
+            .await
+            .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(edit_result, EditTodo(Todo::new(1, "todo")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_update_description()
+            .with(
+                predicate::eq(1),
+                predicate::eq(1),
+                predicate::eq("updated".to_string()),
+            )
+            .returning(|_, _, _| {
+                Box::pin(async { Ok(Todo::new(1, "updated")) })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(EditTodoForm {
+            description: "updated".to_string(),
+        });
+
+        let Negotiated(_, update_result) = update_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            path,
+            form,
+        )
+        .await
+        .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(update_result, EditedTodo(Todo::new(1, "updated")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_todo_invalid() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_update_description().never();
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(EditTodoForm {
+            description: " ".to_string(),
+        });
+
+        let update_result = update_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            path,
+            form,
+        )
+        .await;
+
+        let response = update_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#todo-1-error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_todo_not_found() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_update_description()
+            .with(
+                predicate::eq(1),
+                predicate::eq(1),
+                predicate::eq("updated".to_string()),
+            )
+            .returning(|_, _, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(EditTodoForm {
+            description: "updated".to_string(),
+        });
+
+        let update_result = update_todo(
+            Format::Html,
+            current_user(),
+            dao,
+            State(Settings::default()),
+            path,
+            form,
+        )
+        .await;
+
+        let response = update_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_priority() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_set_priority()
+            .with(
+                predicate::eq(1),
+                predicate::eq(1),
+                predicate::eq(Priority::Urgent),
+            )
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(Todo {
+                        priority: Priority::Urgent,
+                        ..Todo::new(1, "todo")
+                    })
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(PriorityForm {
+            priority: Priority::Urgent,
+        });
+
+        let Negotiated(_, set_result) =
+            set_priority(Format::Html, current_user(), dao, path, form)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(set_result.0.priority, Priority::Urgent);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_subtask() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_add_subtask()
+            .with(
+                predicate::eq(1),
+                predicate::eq(1),
+                predicate::eq("Pack".to_string()),
+            )
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(Todo {
+                        parent_id: Some(1),
+                        ..Todo::new(2, "Pack")
+                    })
+                })
+            });
+        mock_dao
+            .expect_get_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(Todo {
+                        subtasks: vec![Todo {
+                            parent_id: Some(1),
+                            ..Todo::new(2, "Pack")
+                        }],
+                        ..Todo::new(1, "Move house")
+                    })
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let settings = State(Settings::default());
+        let path = Path(1);
+        let form = JsonOrForm(SubtaskForm {
+            description: " Pack ".to_string(),
+        });
+
+        let Negotiated(_, add_result) = add_subtask(
+            Format::Html,
+            current_user(),
+            dao,
+            settings,
+            path,
+            form,
+        )
+        .await
+        .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(add_result.subtask.parent_id, Some(1));
+        assert_eq!(add_result.parent.progress(), Some((0, 1)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_subtask_invalid() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_add_subtask().never();
+        let dao = State(Dao(mock_dao));
+        let settings = State(Settings::default());
+        let path = Path(1);
+        let form = JsonOrForm(SubtaskForm {
+            description: "  ".to_string(),
+        });
+
+        let add_result = add_subtask(
+            Format::Html,
+            current_user(),
+            dao,
+            settings,
+            path,
+            form,
+        )
+        .await;
+
+        let response = add_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#todo-1-error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_move_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_move_todo()
+            .with(
+                predicate::eq(1),
+                predicate::eq(3),
+                predicate::eq(Some(1)),
+                predicate::eq(None),
+            )
+            .returning(|_, _, _, _| {
+                Box::pin(async { Ok(Todo::new(3, "todo")) })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(3);
+        let form = JsonOrForm(PositionForm {
+            before: Some(1),
+            after: None,
+        });
+
+        let Negotiated(_, move_result) =
+            move_todo(Format::Html, current_user(), dao, path, form)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(move_result, MovedTodo(Todo::new(3, "todo")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_recurrence() -> Result<()> {
+        let weekly = Recurrence::new(Frequency::Weekly);
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_set_recurrence()
+            .with(
+                predicate::eq(1),
+                predicate::eq(1),
+                predicate::eq(Some(weekly.clone())),
+            )
+            .returning(|_, _, recurrence| {
+                Box::pin(async {
+                    Ok(Todo {
+                        recurrence,
+                        ..Todo::new(1, "todo")
+                    })
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(RecurrenceForm {
+            recurrence: Some("FREQ=WEEKLY".to_string()),
+        });
+
+        let Negotiated(_, set_result) =
+            set_recurrence(Format::Html, current_user(), dao, path, form)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(set_result.0.recurrence, Some(weekly));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_priority_not_found() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_set_priority()
+            .returning(|_, _, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(PriorityForm {
+            priority: Priority::Urgent,
+        });
+
+        let set_result =
+            set_priority(Format::Html, current_user(), dao, path, form).await;
+
+        let response = set_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tag_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_tag_todo()
+            .with(
+                predicate::eq(1),
+                predicate::eq(1),
+                predicate::eq("errands".to_string()),
+            )
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Ok(Todo {
+                        tags: vec!["errands".to_string()],
+                        ..Todo::new(1, "todo")
+                    })
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(TagForm {
+            tag: "#Errands".to_string(),
+        });
+
+        let Negotiated(_, tag_result) =
+            tag_todo(Format::Html, current_user(), dao, path, form)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(tag_result.0.tags, vec!["errands"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tag_todo_invalid() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_tag_todo().never();
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+        let form = JsonOrForm(TagForm {
+            tag: "two words".to_string(),
+        });
+
+        let tag_result =
+            tag_todo(Format::Html, current_user(), dao, path, form).await;
+
+        let response = tag_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#todo-1-error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_untag_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_untag_todo()
+            .with(
+                predicate::eq(1),
+                predicate::eq(1),
+                predicate::eq("errands".to_string()),
+            )
+            .returning(|_, _, _| Box::pin(async { Ok(Todo::new(1, "todo")) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path((1, "errands".to_string()));
+
+        let Negotiated(_, untag_result) =
+            untag_todo(Format::Html, current_user(), dao, path)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert!(untag_result.0.tags.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search_todos() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_search()
+            .with(predicate::eq(1), predicate::eq("milk".to_string()))
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(vec![SearchHit {
+                        todo: Todo::new(1, "Buy milk"),
+                        list_name: "Todos".to_string(),
+                        snippet: vec![
+                            SnippetPart::new("Buy ", false),
+                            SnippetPart::new("milk", true),
+                        ],
+                    }])
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let query = Query(SearchQuery {
+            q: "milk".to_string(),
+        });
+
+        let Negotiated(_, search_result) =
+            search_todos(Format::Html, current_user(), dao, query)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(search_result.query, "milk");
+        assert_eq!(search_result.hits.len(), 1);
+        assert_eq!(search_result.hits[0].todo.description, "Buy milk");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_toggle_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_toggle_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Ok(Todo::new(1, "todo")) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let Negotiated(_, toggle_result) =
+            toggle_todo(Format::Html, current_user(), dao, path)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(toggle_result, ToggledTodo(Todo::new(1, "todo")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_toggle_todo_failed() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_toggle_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| {
+                Box::pin(async { Err(TodoError::Storage("nope".into())) })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let toggle_result =
+            toggle_todo(Format::Html, current_user(), dao, path).await;
+
+        assert!(toggle_result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_toggle_todo_not_found() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_toggle_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let toggle_result =
+            toggle_todo(Format::Html, current_user(), dao, path).await;
+
+        let response = toggle_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_delete_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(Todo {
+                        deleted_at: Some(1),
+                        ..Todo::new(1, "todo")
+                    })
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let Negotiated(_, DeletedTodo(todo)) =
+            delete_todo(Format::Html, current_user(), dao, path)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(todo.deleted_at, Some(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_todo_not_found() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_delete_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let delete_result =
+            delete_todo(Format::Html, current_user(), dao, path).await;
+
+        let response = delete_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_todo_failed() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_delete_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| {
+                Box::pin(async { Err(TodoError::Storage("nope".into())) })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let delete_result =
+            delete_todo(Format::Html, current_user(), dao, path).await;
+
+        let response = delete_result.into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_todo_history() -> Result<()> {
+        let event = TodoEvent {
+            id: 1,
+            todo_id: 1,
+            username: Some("alice".to_string()),
+            kind: TodoEventKind::Created,
+            detail: Some("todo".to_string()),
+            created_at: 0,
+        };
+        let expected = vec![event.clone()];
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_history()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(move |_, _| {
+                let events = vec![event.clone()];
+                Box::pin(async move { Ok(events) })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let Negotiated(_, TodoHistory(events)) =
+            todo_history(Format::Html, current_user(), dao, path)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(events, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_update() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_bulk_update()
+            .with(
+                predicate::eq(1),
+                predicate::eq(vec![1, 2]),
+                predicate::eq(BulkAction::Complete),
+            )
+            .returning(|_, ids, _| {
+                let todos = ids
+                    .into_iter()
+                    .map(|id| Todo {
+                        completed_at: Some(1),
+                        ..Todo::new(id, "todo")
+                    })
+                    .collect();
+                Box::pin(async move { Ok(todos) })
+            });
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(BulkForm {
+            ids: vec![1, 2],
+            action: BulkAction::Complete,
+        });
+
+        let Negotiated(_, BulkUpdated(todos)) =
+            bulk_update(Format::Html, current_user(), dao, form)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(todos.len(), 2);
+        assert!(todos.iter().all(Todo::is_completed));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_update_nothing_picked() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_bulk_update().never();
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(BulkForm {
+            ids: vec![],
+            action: BulkAction::Delete,
+        });
+
+        let bulk_result =
+            bulk_update(Format::Html, current_user(), dao, form).await;
+
+        let response = bulk_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_completed() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_delete_completed()
+            .with(predicate::eq(1), predicate::eq(2))
+            .returning(|_, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(2);
+
+        let delete_result =
+            delete_completed(Format::Html, current_user(), dao, path).await;
+
+        let response = delete_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_todo() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_restore_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Ok(Todo::new(1, "todo")) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let Negotiated(_, RestoredTodo(todo)) =
+            restore_todo(Format::Html, current_user(), dao, path)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(todo, Todo::new(1, "todo"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_restore_todo_conflict() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_restore_todo()
+            .with(predicate::eq(1), predicate::eq(2))
+            .returning(|_, _| {
+                Box::pin(async {
+                    Err(TodoError::Conflict("parent is trashed".into()))
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(2);
+
+        let restore_result =
+            restore_todo(Format::Html, current_user(), dao, path).await;
+
+        let response = restore_result.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_purge_todo_not_found() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_purge_todo()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| Box::pin(async { Err(TodoError::NotFound) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let purge_result = purge_todo(current_user(), dao, path).await;
+
+        let response = purge_result.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_empty_trash() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_empty_trash()
+            .with(predicate::eq(1))
+            .returning(|_| Box::pin(async { Ok(3) }));
+        let dao = State(Dao(mock_dao));
+
+        let Negotiated(_, emptied) =
+            empty_trash(Format::Html, current_user(), dao)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(emptied, EmptiedTrash { purged: 3 });
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_list() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_create_list()
+            .with(predicate::eq(1), predicate::eq("Groceries".to_string()))
+            .returning(|_, _| {
+                Box::pin(async { Ok(List::new(2, "Groceries")) })
+            });
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(ListForm {
+            name: " Groceries ".to_string(),
+        });
+
+        let Negotiated(_, create_result) =
+            create_list(Format::Html, current_user(), dao, form)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(create_result, CreatedList(List::new(2, "Groceries")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_list_invalid() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao.expect_create_list().never();
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(ListForm {
+            name: "".to_string(),
+        });
+
+        let create_result =
+            create_list(Format::Html, current_user(), dao, form).await;
+
+        let response = create_result.into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()["HX-Retarget"], "#new-list-error");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rename_list() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_rename_list()
+            .with(
+                predicate::eq(1),
+                predicate::eq(2),
+                predicate::eq("Chores".to_string()),
+            )
+            .returning(|_, _, _| {
+                Box::pin(async { Ok(List::new(2, "Chores")) })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(2);
+        let form = JsonOrForm(ListForm {
+            name: "Chores".to_string(),
+        });
+
+        let Negotiated(_, rename_result) =
+            rename_list(Format::Html, current_user(), dao, path, form)
+                .await
+                .map_err(|e| anyhow!("{:?}", e))?;
+
+        assert_eq!(rename_result, RenamedList(List::new(2, "Chores")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_list() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_delete_list()
+            .with(predicate::eq(1), predicate::eq(2))
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        let dao = State(Dao(mock_dao));
+        let path = Path(2);
+
+        let response =
+            delete_list(current_user(), dao, path).await.into_response();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["HX-Redirect"], "/");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_last_list() -> Result<()> {
+        let mut mock_dao = MockTodoDao::new();
+        mock_dao
+            .expect_delete_list()
+            .with(predicate::eq(1), predicate::eq(1))
+            .returning(|_, _| {
+                Box::pin(async {
+                    Err(TodoError::Conflict(
+                        "can't delete the last list".into(),
+                    ))
+                })
+            });
+        let dao = State(Dao(mock_dao));
+        let path = Path(1);
+
+        let response =
+            delete_list(current_user(), dao, path).await.into_response();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_signup_invalid() -> Result<()> {
+        let mut mock_dao = MockUserDao::new();
+        mock_dao.expect_create_user().never();
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(CredentialsForm {
+            username: "al".to_string(),
+            password: "correct horse".to_string(),
+        });
+
+        let response = signup(
+            Format::Html,
+            dao,
+            State(Settings::default()),
+            CookieJar::new(),
+            form,
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_login_unknown_user() -> Result<()> {
+        let mut mock_dao = MockUserDao::new();
+        mock_dao
+            .expect_get_credentials()
+            .with(predicate::eq("alice".to_string()))
+            .returning(|_| Box::pin(async { Err(TodoError::NotFound) }));
+        mock_dao.expect_create_session().never();
+        let dao = State(Dao(mock_dao));
+        let form = JsonOrForm(CredentialsForm {
+            username: "alice".to_string(),
+            password: "correct horse".to_string(),
+        });
+
+        let response = login(
+            Format::Json,
+            dao,
+            State(Settings::default()),
+            CookieJar::new(),
+            form,
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
         Ok(())
     }
 }
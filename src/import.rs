@@ -0,0 +1,870 @@
+use crate::{
+    errors::TodoError,
+    recurrence::parse_recurrence,
+    todos::{
+        ImportedTodo, Priority, extract_tags, parse_due_date,
+        validate_description, validate_list_name, validate_tag,
+    },
+};
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// The kinds of file todos can be imported from.
+#[derive(
+    Deserialize, Serialize, clap::ValueEnum, PartialEq, Eq, Clone, Copy, Debug,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// An array of todos, like the JSON export.
+    Json,
+    /// A header row naming the columns, like the CSV export; only
+    /// `description` is required.
+    Csv,
+    /// A todo per line, in the todo.txt format.
+    #[serde(rename = "txt")]
+    #[value(name = "txt")]
+    TodoTxt,
+    /// Checklist items, under headings naming their lists, like the
+    /// Markdown export.
+    #[serde(rename = "md")]
+    #[value(name = "md")]
+    Markdown,
+}
+
+impl ImportFormat {
+    /// Guesses the format from a file's extension.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let (_, extension) = filename.rsplit_once('.')?;
+        extension.to_lowercase().parse().ok()
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = TodoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ImportFormat::Json),
+            "csv" => Ok(ImportFormat::Csv),
+            "txt" => Ok(ImportFormat::TodoTxt),
+            "md" => Ok(ImportFormat::Markdown),
+            _ => Err(TodoError::Validation(
+                "format must be json, csv, txt or md".to_string(),
+            )),
+        }
+    }
+}
+
+/// Something wrong with one line of a file being imported.
+#[derive(Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct LineError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl LineError {
+    pub fn new<S>(line: usize, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Reads the todos out of a file, or says what's wrong with every line that
+/// can't be imported. `#tag` words in descriptions become tags, the same as
+/// when a todo's added, and open subtasks reopen whatever they're under.
+pub fn parse(
+    format: ImportFormat,
+    text: &str,
+    max_description_length: usize,
+) -> Result<Vec<ImportedTodo>, Vec<LineError>> {
+    let entries = match format {
+        ImportFormat::Json => parse_json(text),
+        ImportFormat::Csv => parse_csv(text),
+        ImportFormat::TodoTxt => parse_todo_txt(text),
+        ImportFormat::Markdown => parse_markdown(text),
+    };
+
+    let mut errors = vec![];
+    let mut todos = vec![];
+    // where each todo the file gave an id to ended up
+    let mut ids = HashMap::new();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+        let line = entry.line;
+        let id = entry.id;
+        match entry.check(&ids, max_description_length) {
+            Ok(todo) => todos.push(todo),
+            Err(message) => errors.push(LineError::new(line, message)),
+        }
+        if let Some(id) = id {
+            ids.insert(id, index);
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // a completed todo can't have anything left to do underneath it
+    for index in 0..todos.len() {
+        if todos[index].completed {
+            continue;
+        }
+        let mut parent = todos[index].parent;
+        while let Some(above) = parent {
+            todos[above].completed = false;
+            todos[above].completed_at = None;
+            parent = todos[above].parent;
+        }
+    }
+    Ok(todos)
+}
+
+/// A todo as the file has it, before anything's been checked.
+#[derive(Deserialize, Default, Debug)]
+struct Entry {
+    #[serde(skip)]
+    line: usize,
+    /// What the file calls the todo, for subtasks to refer to it by.
+    id: Option<i64>,
+    list: Option<String>,
+    parent_id: Option<i64>,
+    /// Where the parent is in the file, for formats that nest subtasks
+    /// instead of giving ids.
+    #[serde(skip)]
+    parent: Option<usize>,
+    description: String,
+    #[serde(default)]
+    completed: bool,
+    completed_at: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    due_date: Option<String>,
+    priority: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    recurrence: Option<String>,
+}
+
+impl Entry {
+    fn new(line: usize) -> Self {
+        Self {
+            line,
+            ..Self::default()
+        }
+    }
+
+    /// Turns the entry into a todo, given where the todos with ids above it
+    /// went, or says what's wrong with it.
+    fn check(
+        self,
+        ids: &HashMap<i64, usize>,
+        max_description_length: usize,
+    ) -> Result<ImportedTodo, String> {
+        let parent = match self.parent_id {
+            Some(parent_id) => Some(*ids.get(&parent_id).ok_or_else(|| {
+                format!("parent_id {} isn't a todo above this one", parent_id)
+            })?),
+            None => self.parent,
+        };
+        let (description, mut tags) = extract_tags(&self.description);
+        let description =
+            validate_description(&description, max_description_length)
+                .map_err(|e| e.to_string())?;
+        for tag in self.tags.iter() {
+            let tag = validate_tag(tag).map_err(|e| e.to_string())?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        let list = match self.list.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(list) => {
+                Some(validate_list_name(list).map_err(|e| e.to_string())?)
+            }
+        };
+        let completed_at =
+            parse_time("completed_at", self.completed_at.as_deref())?;
+        let priority = match self.priority.as_deref().map(str::trim) {
+            None | Some("") => Priority::default(),
+            Some(priority) => parse_priority(priority)?,
+        };
+        Ok(ImportedTodo {
+            list,
+            parent,
+            description,
+            completed: self.completed || completed_at.is_some(),
+            completed_at,
+            created_at: parse_time("created_at", self.created_at.as_deref())?,
+            updated_at: parse_time("updated_at", self.updated_at.as_deref())?,
+            due_at: parse_due_date(self.due_date.as_deref().unwrap_or(""))
+                .map_err(|e| e.to_string())?,
+            priority,
+            tags,
+            recurrence: parse_recurrence(
+                self.recurrence.as_deref().unwrap_or(""),
+            )
+            .map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+/// Reads an ISO-8601 time, or a date for midnight UTC on that day, into
+/// millis.
+fn parse_time(field: &str, time: Option<&str>) -> Result<Option<i64>, String> {
+    let time = time.unwrap_or("").trim();
+    if time.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+        return Ok(Some(time.timestamp_millis()));
+    }
+    match NaiveDate::parse_from_str(time, "%Y-%m-%d") {
+        Ok(date) => Ok(Some(
+            date.and_time(NaiveTime::MIN).and_utc().timestamp_millis(),
+        )),
+        Err(_) => Err(format!(
+            "{} must be a time like 2025-12-31T09:30:00Z or a date like \
+            2025-12-31",
+            field
+        )),
+    }
+}
+
+fn parse_priority(priority: &str) -> Result<Priority, String> {
+    Priority::ALL
+        .into_iter()
+        .find(|p| p.as_str().eq_ignore_ascii_case(priority))
+        .ok_or_else(|| {
+            "priority must be low, normal, high or urgent".to_string()
+        })
+}
+
+/// The line of `text` that `part`, a slice of it, starts on.
+fn line_of(text: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - text.as_ptr() as usize;
+    text[..offset].matches('\n').count() + 1
+}
+
+/// serde_json's message without the position it tacks on the end, since
+/// that's reported separately.
+fn json_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    }
+}
+
+fn parse_json(text: &str) -> Vec<Result<Entry, LineError>> {
+    let todos: Vec<&RawValue> = match serde_json::from_str(text) {
+        Ok(todos) => todos,
+        Err(e) => return vec![Err(LineError::new(e.line(), json_message(&e)))],
+    };
+    todos
+        .into_iter()
+        .map(|todo| {
+            let line = line_of(text, todo.get());
+            serde_json::from_str(todo.get())
+                .map(|entry| Entry { line, ..entry })
+                .map_err(|e| LineError::new(line, json_message(&e)))
+        })
+        .collect()
+}
+
+/// Splits CSV into records, each with the line it starts on. Quoted fields
+/// can have commas, doubled quotes and line breaks in them.
+fn csv_records(text: &str) -> Result<Vec<(usize, Vec<String>)>, LineError> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let (mut line, mut start) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push(chars.next().unwrap_or('"'));
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err(LineError::new(start, "a quoted field is never closed"));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+    // blank lines don't count for anything
+    records.retain(|(_, record)| record.len() > 1 || !record[0].is_empty());
+    Ok(records)
+}
+
+fn parse_csv(text: &str) -> Vec<Result<Entry, LineError>> {
+    let records = match csv_records(text) {
+        Ok(records) => records,
+        Err(e) => return vec![Err(e)],
+    };
+    let Some(((_, header), rows)) = records.split_first() else {
+        return vec![];
+    };
+    let columns: Vec<_> = header
+        .iter()
+        .map(|name| name.trim().to_lowercase())
+        .collect();
+    if !columns.iter().any(|name| name == "description") {
+        return vec![Err(LineError::new(
+            1,
+            "the header row needs a description column",
+        ))];
+    }
+
+    rows.iter()
+        .map(|(line, row)| {
+            if row.len() != columns.len() {
+                return Err(LineError::new(
+                    *line,
+                    format!(
+                        "expected {} fields but found {}",
+                        columns.len(),
+                        row.len()
+                    ),
+                ));
+            }
+            let mut entry = Entry::new(*line);
+            for (column, value) in columns.iter().zip(row) {
+                csv_cell(&mut entry, column, value)
+                    .map_err(|message| LineError::new(*line, message))?;
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Fills in the part of the entry a CSV column is for. Columns it doesn't
+/// know about are ignored.
+fn csv_cell(
+    entry: &mut Entry,
+    column: &str,
+    value: &str,
+) -> Result<(), String> {
+    let id = |value: &str| match value.trim() {
+        "" => Ok(None),
+        id => id
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{} must be a number", column)),
+    };
+    let text = (!value.trim().is_empty()).then(|| value.to_string());
+    match column {
+        "id" => entry.id = id(value)?,
+        "list" => entry.list = text,
+        "parent_id" => entry.parent_id = id(value)?,
+        "description" => entry.description = value.to_string(),
+        "completed" => {
+            entry.completed = match value.trim().to_lowercase().as_str() {
+                "" | "false" | "no" | "0" => false,
+                "true" | "yes" | "1" | "x" => true,
+                _ => return Err("completed must be true or false".to_string()),
+            }
+        }
+        "completed_at" => entry.completed_at = text,
+        "created_at" => entry.created_at = text,
+        "updated_at" => entry.updated_at = text,
+        "due_date" => entry.due_date = text,
+        "priority" => entry.priority = text,
+        "tags" => {
+            entry.tags = value
+                .split([' ', ','])
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect()
+        }
+        "recurrence" => entry.recurrence = text,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn is_date(word: &str) -> bool {
+    NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok()
+}
+
+/// todo.txt priorities run from `A` down; the first three map onto ours and
+/// everything after is low.
+fn todo_txt_priority(letter: &str) -> Option<Priority> {
+    match letter {
+        "A" => Some(Priority::Urgent),
+        "B" => Some(Priority::High),
+        "C" => Some(Priority::Normal),
+        letter
+            if letter.len() == 1
+                && letter.chars().all(|c| c.is_ascii_uppercase()) =>
+        {
+            Some(Priority::Low)
+        }
+        _ => None,
+    }
+}
+
+/// Reads todo.txt lines like `x 2025-06-02 2025-06-01 (A) Call mom +family
+/// @phone due:2025-06-05`. Projects and contexts become tags.
+fn parse_todo_txt(text: &str) -> Vec<Result<Entry, LineError>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let mut entry = Entry::new(index + 1);
+            let mut words = line.split_whitespace().peekable();
+            if words.next_if_eq(&"x").is_some() {
+                entry.completed = true;
+            }
+            if let Some(priority) = words
+                .peek()
+                .and_then(|word| word.strip_prefix('('))
+                .and_then(|word| word.strip_suffix(')'))
+                .and_then(todo_txt_priority)
+            {
+                entry.priority = Some(priority.as_str().to_string());
+                words.next();
+            }
+            // completed todos have the date they were done, then the date
+            // they were added; open ones only have the latter
+            let first = words.next_if(|word| is_date(word));
+            let second = words.next_if(|word| is_date(word));
+            match (entry.completed, first, second) {
+                (true, Some(completed), created) => {
+                    entry.completed_at = Some(completed.to_string());
+                    entry.created_at = created.map(str::to_string);
+                }
+                (_, created, _) => {
+                    entry.created_at = created.map(str::to_string)
+                }
+            }
+
+            let mut description = vec![];
+            for word in words {
+                let tag = word
+                    .strip_prefix('+')
+                    .or_else(|| word.strip_prefix('@'))
+                    .filter(|tag| validate_tag(tag).is_ok());
+                if let Some(tag) = tag {
+                    entry.tags.push(tag.to_string());
+                } else if let Some(due_date) = word.strip_prefix("due:") {
+                    entry.due_date = Some(due_date.to_string());
+                } else if let Some(priority) =
+                    word.strip_prefix("pri:").and_then(todo_txt_priority)
+                {
+                    entry.priority = Some(priority.as_str().to_string());
+                } else {
+                    description.push(word);
+                }
+            }
+            entry.description = description.join(" ");
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Takes the `(due 2025-06-01)` style notes the Markdown export adds off the
+/// end of an item, leaving the description.
+fn markdown_notes(entry: &mut Entry, mut text: &str) {
+    while let Some(rest) = text.strip_suffix(')') {
+        let Some((before, note)) = rest.rsplit_once(" (") else {
+            break;
+        };
+        let Some((key, value)) = note.split_once(' ') else {
+            break;
+        };
+        let value = Some(value.to_string());
+        match key {
+            "due" => entry.due_date = value,
+            "priority" => entry.priority = value,
+            "repeats" => entry.recurrence = value,
+            "completed" => entry.completed_at = value,
+            "created" => entry.created_at = value,
+            _ => break,
+        }
+        text = before;
+    }
+    entry.description = text.to_string();
+}
+
+/// Reads checklist items like `- [x] Buy milk`, nesting them by indentation.
+/// Headings name the list the items under them go in; anything else is
+/// skipped.
+fn parse_markdown(text: &str) -> Vec<Result<Entry, LineError>> {
+    let mut entries = vec![];
+    let mut list = None;
+    // the items that later ones could be nested in, and their indentation
+    let mut parents: Vec<(usize, usize)> = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let heading = trimmed.trim_start_matches('#');
+        if heading.len() < trimmed.len() && heading.starts_with(' ') {
+            list =
+                Some(heading.trim().trim_end_matches('#').trim().to_string());
+            parents.clear();
+            continue;
+        }
+        let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        else {
+            continue;
+        };
+        let completed = match item.get(..3) {
+            Some("[ ]") => false,
+            Some("[x]" | "[X]") => true,
+            _ => continue,
+        };
+
+        let indent = line[..line.len() - trimmed.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        while parents.last().is_some_and(|(depth, _)| *depth >= indent) {
+            parents.pop();
+        }
+        let mut entry = Entry {
+            list: list.clone(),
+            parent: parents.last().map(|(_, parent)| *parent),
+            completed,
+            ..Entry::new(index + 1)
+        };
+        markdown_notes(&mut entry, item[3..].trim());
+        parents.push((indent, entries.len()));
+        entries.push(Ok(entry));
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recurrence::{Frequency, Recurrence};
+    use pretty_assertions::assert_eq;
+
+    const MAX_LENGTH: usize = 100;
+
+    #[test]
+    fn test_from_filename() {
+        assert_eq!(
+            ImportFormat::from_filename("todos.CSV"),
+            Some(ImportFormat::Csv)
+        );
+        assert_eq!(
+            ImportFormat::from_filename("todo.txt"),
+            Some(ImportFormat::TodoTxt)
+        );
+        assert_eq!(ImportFormat::from_filename("todos"), None);
+        assert_eq!(ImportFormat::from_filename("todos.xlsx"), None);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let json = r#"[
+  {"id": 1, "list": "Todos", "description": "Water the plants #home",
+   "completed_at": "2025-06-01T09:30:00.000Z", "priority": "high"},
+  {"id": 2, "parent_id": 1, "description": "Fill the can",
+   "completed": true, "due_date": "2025-06-01", "recurrence": "FREQ=DAILY"}
+]"#;
+
+        let todos = parse(ImportFormat::Json, json, MAX_LENGTH).unwrap();
+
+        assert_eq!(
+            todos,
+            vec![
+                ImportedTodo {
+                    list: Some("Todos".to_string()),
+                    completed: true,
+                    completed_at: Some(1_748_770_200_000),
+                    priority: Priority::High,
+                    tags: vec!["home".to_string()],
+                    ..ImportedTodo::new("Water the plants")
+                },
+                ImportedTodo {
+                    parent: Some(0),
+                    completed: true,
+                    due_at: Some(1_748_736_000_000),
+                    recurrence: Some(Recurrence::new(Frequency::Daily)),
+                    ..ImportedTodo::new("Fill the can")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_json_errors() {
+        let json = r#"[
+  {"description": "Fine"},
+  {"description": " ", "priority": "high"},
+  {"description": "No such parent", "parent_id": 7},
+  {"desc": "Misspelled"}
+]"#;
+
+        let errors = parse(ImportFormat::Json, json, MAX_LENGTH).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                LineError::new(3, "description must not be empty"),
+                LineError::new(4, "parent_id 7 isn't a todo above this one"),
+                LineError::new(5, "missing field `description`"),
+            ]
+        );
+        assert_eq!(
+            parse(ImportFormat::Json, "[{", MAX_LENGTH).unwrap_err(),
+            vec![LineError::new(1, "EOF while parsing an object")]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "Description,Completed,Tags,Created_At\r\n\
+                   \"Buy milk, eggs\",yes,shop food,2025-06-01\r\n\
+                   \r\n\
+                   \"Say \"\"hi\"\"\nto everyone\",,,\r\n";
+
+        let todos = parse(ImportFormat::Csv, csv, MAX_LENGTH).unwrap_err();
+
+        // line breaks aren't allowed in descriptions, but the line number
+        // still comes out right
+        assert_eq!(
+            todos,
+            vec![LineError::new(
+                4,
+                "description must not contain control characters"
+            )]
+        );
+
+        let csv = "id,parent_id,description,completed,tags,created_at\n\
+                   5,,\"Buy milk, eggs\",yes,shop food,2025-06-01\n\
+                   6,5,\"Say \"\"hi\"\"\",,,\n";
+        let todos = parse(ImportFormat::Csv, csv, MAX_LENGTH).unwrap();
+
+        assert_eq!(
+            todos,
+            vec![
+                ImportedTodo {
+                    created_at: Some(1_748_736_000_000),
+                    tags: vec!["shop".to_string(), "food".to_string()],
+                    ..ImportedTodo::new("Buy milk, eggs")
+                },
+                ImportedTodo {
+                    parent: Some(0),
+                    ..ImportedTodo::new("Say \"hi\"")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_errors() {
+        let csv = "id,description,priority,due_date\n\
+                   one,Buy milk,,\n\
+                   2,Buy eggs,soon,\n\
+                   3,Buy bread\n\
+                   4,Buy jam,,tomorrow\n";
+
+        let errors = parse(ImportFormat::Csv, csv, MAX_LENGTH).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                LineError::new(2, "id must be a number"),
+                LineError::new(
+                    3,
+                    "priority must be low, normal, high or urgent"
+                ),
+                LineError::new(4, "expected 4 fields but found 2"),
+                LineError::new(5, "due date must be a date like 2025-12-31"),
+            ]
+        );
+        assert_eq!(
+            parse(ImportFormat::Csv, "name\nBuy milk\n", MAX_LENGTH)
+                .unwrap_err(),
+            vec![LineError::new(
+                1,
+                "the header row needs a description column"
+            )]
+        );
+        assert_eq!(
+            parse(ImportFormat::Csv, "description\n\"Buy milk\n", MAX_LENGTH)
+                .unwrap_err(),
+            vec![LineError::new(2, "a quoted field is never closed")]
+        );
+    }
+
+    #[test]
+    fn test_parse_todo_txt() {
+        let text = "(A) 2025-06-01 Call mom +family @phone due:2025-06-05\n\
+                    \n\
+                    x 2025-06-02 2025-05-30 Pay rent pri:B\n\
+                    x Take out trash\n\
+                    (d) Read +a.book\n";
+
+        let todos = parse(ImportFormat::TodoTxt, text, MAX_LENGTH).unwrap();
+
+        assert_eq!(
+            todos,
+            vec![
+                ImportedTodo {
+                    created_at: Some(1_748_736_000_000),
+                    due_at: Some(1_749_081_600_000),
+                    priority: Priority::Urgent,
+                    tags: vec!["family".to_string(), "phone".to_string()],
+                    ..ImportedTodo::new("Call mom")
+                },
+                ImportedTodo {
+                    completed: true,
+                    completed_at: Some(1_748_822_400_000),
+                    created_at: Some(1_748_563_200_000),
+                    priority: Priority::High,
+                    ..ImportedTodo::new("Pay rent")
+                },
+                ImportedTodo {
+                    completed: true,
+                    ..ImportedTodo::new("Take out trash")
+                },
+                // neither is quite right, so they're left as they were
+                ImportedTodo::new("(d) Read +a.book"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_todo_txt_errors() {
+        let text = "Call mom\n\
+                    x\n\
+                    Pay rent due:someday\n";
+
+        let errors =
+            parse(ImportFormat::TodoTxt, text, MAX_LENGTH).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                LineError::new(2, "description must not be empty"),
+                LineError::new(3, "due date must be a date like 2025-12-31"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown() {
+        let markdown = "# My todos\n\
+                        \n\
+                        Some notes, and a list that isn't a checklist:\n\
+                        - Not a todo\n\
+                        - [ ] Go shopping #errands (due 2025-06-01) (priority high)\n\
+                        \x20\x20- [x] Buy milk (completed 2025-06-01T09:30:00.000Z)\n\
+                        \x20\x20\x20\x20* [X] Check the date\n\
+                        \x20\x20- [x] Buy eggs\n\
+                        - [x] Water the plants (repeats FREQ=DAILY) (maybe)\n\
+                        \n\
+                        ## Someday\n\
+                        \x20\x20- [ ] Learn Rust\n";
+
+        let todos =
+            parse(ImportFormat::Markdown, markdown, MAX_LENGTH).unwrap();
+
+        let list = Some("My todos".to_string());
+        assert_eq!(
+            todos,
+            vec![
+                ImportedTodo {
+                    list: list.clone(),
+                    due_at: Some(1_748_736_000_000),
+                    priority: Priority::High,
+                    tags: vec!["errands".to_string()],
+                    ..ImportedTodo::new("Go shopping")
+                },
+                ImportedTodo {
+                    list: list.clone(),
+                    parent: Some(0),
+                    completed: true,
+                    completed_at: Some(1_748_770_200_000),
+                    ..ImportedTodo::new("Buy milk")
+                },
+                ImportedTodo {
+                    list: list.clone(),
+                    parent: Some(1),
+                    completed: true,
+                    ..ImportedTodo::new("Check the date")
+                },
+                ImportedTodo {
+                    list: list.clone(),
+                    parent: Some(0),
+                    completed: true,
+                    ..ImportedTodo::new("Buy eggs")
+                },
+                // only the notes the export writes are taken off
+                ImportedTodo {
+                    list,
+                    completed: true,
+                    ..ImportedTodo::new(
+                        "Water the plants (repeats FREQ=DAILY) (maybe)"
+                    )
+                },
+                ImportedTodo {
+                    list: Some("Someday".to_string()),
+                    ..ImportedTodo::new("Learn Rust")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_subtasks_reopen_their_parents() {
+        let markdown = "- [x] Move house\n\
+                        \x20\x20- [x] Pack\n\
+                        \x20\x20\x20\x20- [ ] Pack the kitchen\n\
+                        \x20\x20- [x] Book a van\n";
+
+        let todos =
+            parse(ImportFormat::Markdown, markdown, MAX_LENGTH).unwrap();
+
+        assert_eq!(
+            todos.iter().map(|todo| todo.completed).collect::<Vec<_>>(),
+            vec![false, false, false, true]
+        );
+    }
+}
@@ -1,6 +1,18 @@
+pub mod auth;
+pub mod clock;
+#[cfg(test)]
+mod dao_tests;
 pub mod db;
+pub mod errors;
+pub mod export;
 pub mod handlers;
+pub mod import;
+pub mod memory;
+pub mod negotiate;
+pub mod postgres;
+pub mod recurrence;
 pub mod routes;
 pub mod state;
 pub mod todos;
+pub mod users;
 pub mod views;
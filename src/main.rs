@@ -1,20 +1,105 @@
-use clap::Parser;
-use dotenvy;
-use mash_todo::{db, routes, state::AppState, todos::TodoSqliteDao};
-use tokio;
-use tracing::{self, info};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use clap::{Parser, Subcommand};
+use futures_util::{StreamExt, pin_mut};
+use mash_todo::{
+    db::{self, Backend},
+    errors::TodoError,
+    export::{self, ExportFormat},
+    import::{self, ImportFormat},
+    memory::TodoMemoryDao,
+    postgres::TodoPostgresDao,
+    routes,
+    state::{AppState, Settings},
+    todos::{TodoDao, TodoSqliteDao},
+    users::{Credentials, UserDao},
+};
+use tokio::io::AsyncWriteExt;
+use tracing::{self, error, info, warn};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
 #[derive(Parser)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(short = 'b', long = "bind-address", env = "BIND_ADDRESS", default_value_t = String::from("127.0.0.1"))]
     bind_address: String,
 
     #[arg(short, long = "port", env = "PORT", default_value_t = 3000)]
     port: u16,
 
-    #[arg(short = 'd', long = "database-url", env = "DATABASE_URL", default_value_t = String::from("sqlite://db/app.db"))]
+    #[arg(short = 'd', long = "database-url", env = "DATABASE_URL", global = true, default_value_t = String::from("sqlite://db/app.db"))]
     database_url: String,
+
+    #[arg(long = "max-description-length", env = "MAX_DESCRIPTION_LENGTH", global = true, default_value_t = Settings::default().max_description_length)]
+    max_description_length: usize,
+
+    #[arg(long = "trash-retention-days", env = "TRASH_RETENTION_DAYS", default_value_t = Settings::default().trash_retention_days, value_parser = clap::value_parser!(u64).range(..=MAX_TRASH_RETENTION_DAYS))]
+    trash_retention_days: u64,
+
+    /// Lets the session cookie be sent over plain http, for trying the app
+    /// out locally.
+    #[arg(long = "insecure-cookies", env = "INSECURE_COOKIES")]
+    insecure_cookies: bool,
+}
+
+// without one of these, the server runs
+#[derive(Subcommand)]
+enum Command {
+    /// Writes every todo a user has to stdout.
+    Export {
+        #[arg(short, long)]
+        username: String,
+
+        #[arg(short, long, value_enum, default_value_t)]
+        format: ExportFormat,
+    },
+    /// Adds the todos in a file to a user's lists: all of them, or none if
+    /// any line is wrong.
+    Import {
+        #[arg(short, long)]
+        username: String,
+
+        /// Guessed from the file's extension if left out.
+        #[arg(short, long, value_enum)]
+        format: Option<ImportFormat>,
+
+        /// Where todos that don't name a list go; the user's first list if
+        /// left out.
+        #[arg(short, long)]
+        list: Option<String>,
+
+        file: PathBuf,
+    },
+    /// Gives a user the lists and todos from before there were accounts.
+    Claim {
+        #[arg(short, long)]
+        username: String,
+    },
+}
+
+/// A hundred years is as good as forever, and keeps the purge cutoff well
+/// clear of overflowing.
+const MAX_TRASH_RETENTION_DAYS: u64 = 100 * 365;
+
+/// Hard deletes anything that's sat in the trash longer than the retention
+/// period, checking once an hour for as long as the server runs.
+async fn purge_trash<T: TodoDao>(dao: T, retention_days: u64) {
+    let retention = Duration::from_secs(retention_days * 24 * 60 * 60);
+    let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        match dao.purge_expired_trash(retention).await {
+            Ok(0) => {}
+            Ok(purged) => info!("purged {} todos from the trash", purged),
+            Err(e) => error!("failed to purge the trash: {}", e),
+        }
+    }
 }
 
 #[tokio::main]
@@ -22,9 +107,10 @@ async fn main() -> anyhow::Result<()> {
     // Initialize dotenvy
     dotenvy::dotenv().ok();
 
-    // Set up tracing with the default format subscriber
+    // Set up tracing with the default format subscriber, on stderr so that
+    // it stays out of anything written to stdout
     tracing_subscriber::registry()
-        .with(fmt::layer())
+        .with(fmt::layer().with_writer(std::io::stderr))
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| {
             format!("{}=debug,info", env!("CARGO_CRATE_NAME")).into()
         }))
@@ -32,11 +118,151 @@ async fn main() -> anyhow::Result<()> {
 
     let args = Cli::parse();
 
-    // database
-    let pool = db::create_pool(&args.database_url).await?;
+    // database; the url's scheme says which kind
+    match Backend::from_url(&args.database_url)? {
+        Backend::Sqlite => {
+            let pool = db::create_pool(&args.database_url).await?;
+            run(args, TodoSqliteDao::new(pool)).await
+        }
+        Backend::Postgres => {
+            let pool = db::create_postgres_pool(&args.database_url).await?;
+            run(args, TodoPostgresDao::new(pool)).await
+        }
+        Backend::Memory => {
+            warn!("nothing will be saved once the server stops");
+            run(args, TodoMemoryDao::new()).await
+        }
+    }
+}
+
+/// Does whatever the command line asked for with whichever backend the
+/// database url picked.
+async fn run<T>(args: Cli, dao: T) -> anyhow::Result<()>
+where
+    T: TodoDao + UserDao + Clone + Send + Sync + 'static,
+{
+    match args.command {
+        Some(Command::Export {
+            ref username,
+            format,
+        }) => export(dao, username, format).await,
+        Some(Command::Import {
+            ref username,
+            format,
+            ref list,
+            ref file,
+        }) => {
+            import(
+                dao,
+                username,
+                format,
+                list.as_deref(),
+                file,
+                args.max_description_length,
+            )
+            .await
+        }
+        Some(Command::Claim { ref username }) => claim(dao, username).await,
+        None => serve(args, dao).await,
+    }
+}
+
+async fn find_user<T: UserDao>(
+    dao: &T,
+    username: &str,
+) -> anyhow::Result<Credentials> {
+    match dao.get_credentials(username.to_string()).await {
+        Ok(credentials) => Ok(credentials),
+        Err(TodoError::NotFound) => Err(anyhow!("no user called {}", username)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn claim<T: UserDao>(dao: T, username: &str) -> anyhow::Result<()> {
+    let user = find_user(&dao, username).await?;
+    let claimed = dao.claim_unowned_lists(user.id).await?;
+    println!("{} now has {} more lists", username, claimed);
+    Ok(())
+}
 
+async fn export<T>(
+    dao: T,
+    username: &str,
+    format: ExportFormat,
+) -> anyhow::Result<()>
+where
+    T: TodoDao + UserDao + Send + Sync + 'static,
+{
+    let user = find_user(&dao, username).await?;
+    let lists = dao.get_lists(user.id).await?;
+    let chunks = export::export(dao, user.id, lists, format);
+    pin_mut!(chunks);
+    let mut stdout = tokio::io::stdout();
+    while let Some(chunk) = chunks.next().await {
+        stdout.write_all(chunk?.as_bytes()).await?;
+    }
+    stdout.flush().await?;
+    Ok(())
+}
+
+async fn import<T>(
+    dao: T,
+    username: &str,
+    format: Option<ImportFormat>,
+    list: Option<&str>,
+    file: &Path,
+    max_description_length: usize,
+) -> anyhow::Result<()>
+where
+    T: TodoDao + UserDao,
+{
+    let user = find_user(&dao, username).await?;
+    let Some(format) =
+        format.or_else(|| file.to_str().and_then(ImportFormat::from_filename))
+    else {
+        bail!(
+            "can't tell what kind of file {} is; pass --format",
+            file.display()
+        );
+    };
+    let lists = dao.get_lists(user.id).await?;
+    let list = match list {
+        Some(name) => lists.iter().find(|list| list.name == name),
+        None => lists.first(),
+    }
+    .ok_or_else(|| {
+        anyhow!("{} has no list called {}", username, list.unwrap_or(""))
+    })?;
+
+    let text = tokio::fs::read_to_string(file).await?;
+    let todos = match import::parse(format, &text, max_description_length) {
+        Ok(todos) => todos,
+        Err(lines) => {
+            for line in lines.iter() {
+                eprintln!("{}: {}", file.display(), line);
+            }
+            bail!("nothing was imported; fix those lines and try again");
+        }
+    };
+    let imported = dao.import(user.id, list.id, todos).await?;
+    println!("imported {} todos", imported.len());
+    Ok(())
+}
+
+/// Runs the server.
+async fn serve<T>(args: Cli, dao: T) -> anyhow::Result<()>
+where
+    T: TodoDao + UserDao + Clone + Send + Sync + 'static,
+{
     // construct app dependenciess
-    let app_state = AppState::new(TodoSqliteDao::new(pool));
+    let app_state = AppState::new(dao.clone()).with_settings(Settings {
+        max_description_length: args.max_description_length,
+        trash_retention_days: args.trash_retention_days,
+        secure_cookies: !args.insecure_cookies,
+    });
+
+    // background jobs
+    tokio::spawn(purge_trash(dao, args.trash_retention_days));
 
     // serve the app
     let app = routes::create_router(app_state);
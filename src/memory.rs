@@ -0,0 +1,1714 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    errors::{Result, TodoError},
+    recurrence::Recurrence,
+    todos::{
+        BulkAction, HIGHLIGHT_END, HIGHLIGHT_START, ImportedTodo, List,
+        NewTodo, Priority, SEARCH_LIMIT, SearchHit, Todo, TodoDao, TodoEvent,
+        TodoEventKind, TodoFilter, TodoOrder, TodoQuery,
+        next_occurrence_parent, parse_snippet, position_between,
+    },
+    users::{Credentials, SESSION_TTL, User, UserDao},
+};
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::Duration,
+};
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+/// Keeps everything in memory behind a lock, and forgets it all when the
+/// process exits. It behaves exactly like `TodoSqliteDao`, which makes it
+/// handy for demos and for tests that don't care where the data lives.
+#[derive(Clone, Debug)]
+pub struct TodoMemoryDao {
+    tables: Arc<RwLock<Tables>>,
+    clock: Arc<dyn Clock>,
+}
+
+/// The rows the SQL backends keep in their tables, keyed by id.
+#[derive(Debug, Default)]
+struct Tables {
+    users: BTreeMap<i64, Credentials>,
+    sessions: HashMap<String, Session>,
+    lists: BTreeMap<i64, ListRow>,
+    todos: BTreeMap<i64, TodoRow>,
+    events: BTreeMap<i64, EventRow>,
+}
+
+#[derive(Debug)]
+struct Session {
+    user_id: i64,
+    expires_at: i64,
+}
+
+#[derive(Debug)]
+struct ListRow {
+    list: List,
+    /// `None` for lists that predate accounts, until someone claims them.
+    user_id: Option<i64>,
+}
+
+#[derive(Debug)]
+struct TodoRow {
+    /// Its tags are kept here too, sorted by name, but its subtasks are only
+    /// filled in on the way out.
+    todo: Todo,
+    position: f64,
+    /// The todo this is the next occurrence of, if it was scheduled from one.
+    previous_id: Option<i64>,
+}
+
+#[derive(Debug)]
+struct EventRow {
+    todo_id: i64,
+    user_id: i64,
+    kind: TodoEventKind,
+    detail: Option<String>,
+    created_at: i64,
+}
+
+/// The id the next row in a table gets: one more than the highest, which is
+/// how SQLite picks them too.
+fn next_id<V>(table: &BTreeMap<i64, V>) -> i64 {
+    table.last_key_value().map_or(1, |(id, _)| id + 1)
+}
+
+/// Stands in for the `CHECK` constraints on the SQL tables' text columns.
+/// Like SQL's `trim`, only spaces count as blank, except in descriptions,
+/// whose check trims tabs and line breaks too.
+fn check_not_blank(column: &str, text: &str) -> Result<()> {
+    let blank: &[char] = match column {
+        "description" => &[' ', '\t', '\n', '\r'],
+        _ => &[' '],
+    };
+    if text.trim_matches(blank).is_empty() {
+        return Err(TodoError::Validation(format!(
+            "{} must not be empty",
+            column
+        )));
+    }
+    Ok(())
+}
+
+/// Sorts todos the way `TodoOrder::order_by` has the database sort them.
+fn compare(order: TodoOrder, a: &TodoRow, b: &TodoRow) -> Ordering {
+    let (x, y) = (&a.todo, &b.todo);
+    match order {
+        TodoOrder::Manual => a.position.total_cmp(&b.position),
+        TodoOrder::Added => x.created_at.cmp(&y.created_at),
+        TodoOrder::Updated => y.updated_at.cmp(&x.updated_at),
+        TodoOrder::DueDate => {
+            (x.due_at.is_none(), x.due_at).cmp(&(y.due_at.is_none(), y.due_at))
+        }
+        TodoOrder::Priority => y.priority.cmp(&x.priority),
+    }
+    .then(x.id.cmp(&y.id))
+}
+
+/// Whether a todo gets through the filter, like `TodoFilter::condition`.
+fn passes(filter: TodoFilter, todo: &Todo) -> bool {
+    match filter {
+        TodoFilter::All => true,
+        TodoFilter::Active => !todo.is_completed(),
+        TodoFilter::Completed => todo.is_completed(),
+    }
+}
+
+fn poisoned<T>(_: PoisonError<T>) -> TodoError {
+    TodoError::Storage("a panic left the in-memory tables half written".into())
+}
+
+impl Tables {
+    fn owns_list(&self, user_id: i64, list_id: i64) -> bool {
+        self.lists
+            .get(&list_id)
+            .is_some_and(|row| row.user_id == Some(user_id))
+    }
+
+    /// The user's first list with the name, created if they don't have one.
+    fn list_named(&mut self, user_id: i64, name: &str) -> i64 {
+        let existing = self
+            .lists
+            .values()
+            .find(|row| row.user_id == Some(user_id) && row.list.name == name);
+        if let Some(row) = existing {
+            return row.list.id;
+        }
+        let id = next_id(&self.lists);
+        self.lists.insert(
+            id,
+            ListRow {
+                list: List::new(id, name),
+                user_id: Some(user_id),
+            },
+        );
+        id
+    }
+
+    fn owned_list_mut(&mut self, user_id: i64, id: i64) -> Result<&mut List> {
+        self.lists
+            .get_mut(&id)
+            .filter(|row| row.user_id == Some(user_id))
+            .map(|row| &mut row.list)
+            .ok_or(TodoError::NotFound)
+    }
+
+    /// A todo the user owns, whether it's in the trash or not.
+    fn owned_any(&self, user_id: i64, id: i64) -> Result<&Todo> {
+        self.todos
+            .get(&id)
+            .map(|row| &row.todo)
+            .filter(|todo| self.owns_list(user_id, todo.list_id))
+            .ok_or(TodoError::NotFound)
+    }
+
+    /// A todo the user owns. Trashed todos don't count.
+    fn owned(&self, user_id: i64, id: i64) -> Result<&Todo> {
+        match self.owned_any(user_id, id) {
+            Ok(todo) if todo.deleted_at.is_none() => Ok(todo),
+            _ => Err(TodoError::NotFound),
+        }
+    }
+
+    fn owned_mut(&mut self, user_id: i64, id: i64) -> Result<&mut Todo> {
+        self.owned(user_id, id)?;
+        Ok(&mut self.row_mut(id)?.todo)
+    }
+
+    fn row_mut(&mut self, id: i64) -> Result<&mut TodoRow> {
+        self.todos.get_mut(&id).ok_or(TodoError::NotFound)
+    }
+
+    /// The todo and everything underneath it, trashed or not, by id.
+    fn subtree(&self, id: i64) -> Vec<i64> {
+        self.subtree_where(id, |_| true)
+    }
+
+    /// The todo and everything underneath it that isn't in the trash, by id.
+    fn live_subtree(&self, id: i64) -> Vec<i64> {
+        self.subtree_where(id, |todo| todo.deleted_at.is_none())
+    }
+
+    fn subtree_where(&self, id: i64, keep: impl Fn(&Todo) -> bool) -> Vec<i64> {
+        let mut ids = vec![id];
+        let mut next = 0;
+        while let Some(&parent_id) = ids.get(next) {
+            ids.extend(
+                self.todos
+                    .values()
+                    .filter(|row| row.todo.parent_id == Some(parent_id))
+                    .filter(|row| keep(&row.todo))
+                    .map(|row| row.todo.id),
+            );
+            next += 1;
+        }
+        ids.sort();
+        ids
+    }
+
+    /// The todo and everything it's a subtask of, by id.
+    fn ancestors(&self, id: i64) -> Vec<i64> {
+        let mut ids = vec![];
+        let mut next = self.todos.get(&id);
+        while let Some(row) = next {
+            ids.push(row.todo.id);
+            next = row.todo.parent_id.and_then(|id| self.todos.get(&id));
+        }
+        ids.sort();
+        ids
+    }
+
+    /// Fills in a todo's subtasks, and theirs, all the way down. Trashed ones
+    /// are left out.
+    fn with_details(&self, mut todo: Todo) -> Todo {
+        let mut subtasks = self
+            .todos
+            .values()
+            .filter(|row| {
+                row.todo.parent_id == Some(todo.id)
+                    && row.todo.deleted_at.is_none()
+            })
+            .collect::<Vec<_>>();
+        subtasks.sort_by(|a, b| compare(TodoOrder::Manual, a, b));
+        todo.subtasks = subtasks
+            .into_iter()
+            .map(|row| self.with_details(row.todo.clone()))
+            .collect();
+        todo
+    }
+
+    /// Fetches todos by id with all their details, in the order given,
+    /// whether they're in the trash or not.
+    fn todos_by_id(&self, ids: &[i64]) -> Vec<Todo> {
+        ids.iter()
+            .filter_map(|id| self.todos.get(id))
+            .map(|row| self.with_details(row.todo.clone()))
+            .collect()
+    }
+
+    /// Where a new todo at the bottom of a list goes.
+    fn next_position(&self, list_id: i64) -> f64 {
+        self.todos
+            .values()
+            .filter(|row| row.todo.list_id == list_id)
+            .map(|row| row.position)
+            .reduce(f64::max)
+            .unwrap_or(0.0)
+            + 1.0
+    }
+
+    fn insert_todo(&mut self, todo: Todo) {
+        let position = self.next_position(todo.list_id);
+        self.todos.insert(
+            todo.id,
+            TodoRow {
+                todo,
+                position,
+                previous_id: None,
+            },
+        );
+    }
+
+    /// Adds an entry to a todo's history.
+    fn record_event(
+        &mut self,
+        user_id: i64,
+        todo_id: i64,
+        kind: TodoEventKind,
+        detail: Option<&str>,
+        now: i64,
+    ) {
+        let id = next_id(&self.events);
+        self.events.insert(
+            id,
+            EventRow {
+                todo_id,
+                user_id,
+                kind,
+                detail: detail.map(str::to_string),
+                created_at: now,
+            },
+        );
+    }
+
+    /// Changes something about a todo the user owns and records it in the
+    /// todo's history.
+    fn update<F>(
+        &mut self,
+        user_id: i64,
+        id: i64,
+        now: i64,
+        kind: TodoEventKind,
+        detail: Option<&str>,
+        change: F,
+    ) -> Result<Todo>
+    where
+        F: FnOnce(&mut Todo),
+    {
+        let todo = self.owned_mut(user_id, id)?;
+        change(todo);
+        todo.updated_at = now;
+        let todo = todo.clone();
+        self.record_event(user_id, id, kind, detail, now);
+        Ok(self.with_details(todo))
+    }
+
+    /// Completes a todo along with whatever is still open underneath it and
+    /// not in the trash, and schedules the next occurrence of each of those
+    /// that repeats.
+    fn complete(&mut self, user_id: i64, id: i64, now: i64) -> Result<()> {
+        let mut completed = vec![];
+        for id in self.live_subtree(id) {
+            let todo = &mut self.row_mut(id)?.todo;
+            if todo.completed_at.is_none() {
+                todo.completed_at = Some(now);
+                todo.updated_at = now;
+                completed.push(todo.clone());
+                self.record_event(
+                    user_id,
+                    id,
+                    TodoEventKind::Completed,
+                    None,
+                    now,
+                );
+            }
+        }
+
+        let root = self.row_mut(id)?.todo.clone();
+        let today = DateTime::from_timestamp_millis(now)
+            .unwrap_or_default()
+            .date_naive();
+        let mut occurrences = HashMap::new();
+        for done in &completed {
+            let Some(recurrence) = &done.recurrence else {
+                continue;
+            };
+            let due = recurrence.next_due(done.due_date(), today);
+            let parent_id =
+                next_occurrence_parent(done, &root, &completed, &occurrences);
+            let Some(next_id) = self.schedule_next(done, parent_id, due, now)
+            else {
+                continue;
+            };
+            occurrences.insert(done.id, next_id);
+            self.record_event(
+                user_id,
+                next_id,
+                TodoEventKind::Created,
+                Some(&done.description),
+                now,
+            );
+        }
+        Ok(())
+    }
+
+    /// Adds a copy of a recurring todo under `parent_id` that's due next on
+    /// `due`, returning its id, unless the todo still has an open one from
+    /// the last time it was completed.
+    fn schedule_next(
+        &mut self,
+        todo: &Todo,
+        parent_id: Option<i64>,
+        due: NaiveDate,
+        now: i64,
+    ) -> Option<i64> {
+        let pending = self.todos.values().any(|row| {
+            row.previous_id == Some(todo.id)
+                && row.todo.completed_at.is_none()
+                && row.todo.deleted_at.is_none()
+        });
+        if pending {
+            return None;
+        }
+        let id = next_id(&self.todos);
+        self.insert_todo(Todo {
+            id,
+            completed_at: None,
+            created_at: now,
+            updated_at: now,
+            due_at: Some(
+                due.and_time(NaiveTime::MIN).and_utc().timestamp_millis(),
+            ),
+            parent_id,
+            deleted_at: None,
+            subtasks: vec![],
+            ..todo.clone()
+        });
+        self.todos.get_mut(&id)?.previous_id = Some(todo.id);
+        Some(id)
+    }
+
+    /// Marks a todo as not done, along with everything it's a subtask of.
+    fn reopen(&mut self, user_id: i64, id: i64, now: i64) -> Result<()> {
+        for id in self.ancestors(id) {
+            let todo = &mut self.row_mut(id)?.todo;
+            if todo.completed_at.is_some() {
+                todo.completed_at = None;
+                todo.updated_at = now;
+                self.record_event(
+                    user_id,
+                    id,
+                    TodoEventKind::Reopened,
+                    None,
+                    now,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves a todo and its subtasks to the trash.
+    fn trash(&mut self, user_id: i64, id: i64, now: i64) -> Result<()> {
+        // subtasks go in the trash with it, marked with the same time so
+        // that they come back out together too
+        for id in self.subtree(id) {
+            let todo = &mut self.row_mut(id)?.todo;
+            if todo.deleted_at.is_none() {
+                todo.deleted_at = Some(now);
+                self.record_event(
+                    user_id,
+                    id,
+                    TodoEventKind::Trashed,
+                    None,
+                    now,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes todos for good, along with everything underneath them and
+    /// their history, like the foreign keys do in SQL.
+    fn purge(&mut self, ids: &[i64]) {
+        for &id in ids {
+            for id in self.subtree(id) {
+                self.todos.remove(&id);
+            }
+        }
+        let todos = &self.todos;
+        self.events
+            .retain(|_, event| todos.contains_key(&event.todo_id));
+        let live = self.todos.keys().copied().collect::<HashSet<_>>();
+        for row in self.todos.values_mut() {
+            if row.previous_id.is_some_and(|id| !live.contains(&id)) {
+                row.previous_id = None;
+            }
+        }
+    }
+
+    /// Applies a bulk action to each of the todos, which must all belong to
+    /// the user, and returns their ids in the order they were given.
+    fn apply_bulk(
+        &mut self,
+        user_id: i64,
+        ids: &[i64],
+        action: BulkAction,
+        now: i64,
+    ) -> Result<Vec<i64>> {
+        // everything has to be there before anything changes
+        for &id in ids {
+            self.owned(user_id, id)?;
+        }
+        let mut applied: Vec<i64> = Vec::with_capacity(ids.len());
+        for &id in ids {
+            if applied.contains(&id) {
+                continue;
+            }
+            // looked at again one at a time, since doing one can change
+            // another further down, e.g. completing a todo completes its
+            // subtasks
+            let todo = &self.todos.get(&id).ok_or(TodoError::NotFound)?.todo;
+            if todo.deleted_at.is_none() {
+                match action {
+                    BulkAction::Complete if !todo.is_completed() => {
+                        self.complete(user_id, id, now)?
+                    }
+                    BulkAction::Uncomplete if todo.is_completed() => {
+                        self.reopen(user_id, id, now)?
+                    }
+                    BulkAction::Delete => self.trash(user_id, id, now)?,
+                    _ => {}
+                }
+            }
+            applied.push(id);
+        }
+        Ok(applied)
+    }
+
+    /// Runs a bulk action on the top-level todos of a list that get through
+    /// the filter.
+    fn bulk_update_list(
+        &mut self,
+        user_id: i64,
+        list_id: i64,
+        filter: TodoFilter,
+        action: BulkAction,
+        now: i64,
+    ) -> Result<Vec<Todo>> {
+        if !self.owns_list(user_id, list_id) {
+            return Err(TodoError::NotFound);
+        }
+        let mut rows = self
+            .todos
+            .values()
+            .filter(|row| {
+                row.todo.list_id == list_id
+                    && row.todo.parent_id.is_none()
+                    && row.todo.deleted_at.is_none()
+                    && passes(filter, &row.todo)
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| compare(TodoOrder::Manual, a, b));
+        let ids = rows.iter().map(|row| row.todo.id).collect::<Vec<_>>();
+        let ids = self.apply_bulk(user_id, &ids, action, now)?;
+        Ok(self.todos_by_id(&ids))
+    }
+
+    /// The positions of the todos a todo is ordered among: the others in the
+    /// same list under the same parent.
+    fn sibling_positions(&self, todo: &Todo) -> impl Iterator<Item = f64> {
+        self.todos
+            .values()
+            .filter(|row| {
+                row.todo.list_id == todo.list_id
+                    && row.todo.id != todo.id
+                    && row.todo.parent_id == todo.parent_id
+                    && row.todo.deleted_at.is_none()
+            })
+            .map(|row| row.position)
+    }
+
+    /// Finds the positions a todo has to go between to end up right after
+    /// `after` and right before `before`. Only its siblings count, since
+    /// subtasks are ordered under their parent.
+    fn position_bounds(
+        &self,
+        todo: &Todo,
+        before: Option<i64>,
+        after: Option<i64>,
+    ) -> Result<(Option<f64>, Option<f64>)> {
+        let neighbour_position = |neighbour_id: i64| {
+            self.todos
+                .get(&neighbour_id)
+                .filter(|row| {
+                    row.todo.list_id == todo.list_id
+                        && row.todo.id != todo.id
+                        && row.todo.parent_id == todo.parent_id
+                        && row.todo.deleted_at.is_none()
+                })
+                .map(|row| row.position)
+                .ok_or_else(|| {
+                    TodoError::Validation(
+                        "todos can only be moved next to others under the \
+                        same parent in the same list"
+                            .to_string(),
+                    )
+                })
+        };
+        let lower = after.map(neighbour_position).transpose()?;
+        let upper = before.map(neighbour_position).transpose()?;
+        match (lower, upper) {
+            (None, None) => Err(TodoError::Validation(
+                "say which todo to move it next to".to_string(),
+            )),
+            (Some(lower), Some(upper)) if lower >= upper => {
+                Err(TodoError::Validation(
+                    "the todo to move it after has to come first".to_string(),
+                ))
+            }
+            // only one side was given, so the other is whatever is next to it
+            (Some(lower), None) => {
+                let upper = self
+                    .sibling_positions(todo)
+                    .filter(|position| *position > lower)
+                    .reduce(f64::min);
+                Ok((Some(lower), upper))
+            }
+            (None, Some(upper)) => {
+                let lower = self
+                    .sibling_positions(todo)
+                    .filter(|position| *position < upper)
+                    .reduce(f64::max);
+                Ok((lower, Some(upper)))
+            }
+            bounds => Ok(bounds),
+        }
+    }
+
+    /// Spreads a list's positions back out to 1, 2, 3, ... keeping the order.
+    fn renumber_positions(&mut self, list_id: i64) {
+        let mut rows = self
+            .todos
+            .values_mut()
+            .filter(|row| row.todo.list_id == list_id)
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| compare(TodoOrder::Manual, a, b));
+        for (i, row) in rows.into_iter().enumerate() {
+            row.position = (i + 1) as f64;
+        }
+    }
+}
+
+impl Default for TodoMemoryDao {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TodoMemoryDao {
+    /// An empty store, with just the list a fresh database starts out with.
+    pub fn new() -> Self {
+        TodoMemoryDao {
+            tables: Arc::new(RwLock::new(Tables::default())),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The time to stamp anything written now with, in millis.
+    fn now(&self) -> i64 {
+        self.clock.now_millis()
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, Tables>> {
+        self.tables.read().map_err(poisoned)
+    }
+
+    /// Locks the tables for a change. Every change checks everything it can
+    /// fail on before it touches anything, so that holding the lock is as
+    /// good as a transaction.
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Tables>> {
+        self.tables.write().map_err(poisoned)
+    }
+}
+
+impl TodoDao for TodoMemoryDao {
+    async fn get_lists(&self, user_id: i64) -> Result<Vec<List>> {
+        Ok(self
+            .read()?
+            .lists
+            .values()
+            .filter(|row| row.user_id == Some(user_id))
+            .map(|row| row.list.clone())
+            .collect())
+    }
+
+    async fn get_list(&self, user_id: i64, id: i64) -> Result<List> {
+        let tables = self.read()?;
+        match tables.lists.get(&id) {
+            Some(row) if row.user_id == Some(user_id) => Ok(row.list.clone()),
+            _ => Err(TodoError::NotFound),
+        }
+    }
+
+    async fn create_list(&self, user_id: i64, name: String) -> Result<List> {
+        check_not_blank("name", &name)?;
+        let mut tables = self.write()?;
+        let list = List {
+            id: next_id(&tables.lists),
+            name,
+        };
+        tables.lists.insert(
+            list.id,
+            ListRow {
+                list: list.clone(),
+                user_id: Some(user_id),
+            },
+        );
+        Ok(list)
+    }
+
+    async fn rename_list(
+        &self,
+        user_id: i64,
+        id: i64,
+        name: String,
+    ) -> Result<List> {
+        let mut tables = self.write()?;
+        let list = tables.owned_list_mut(user_id, id)?;
+        check_not_blank("name", &name)?;
+        list.name = name;
+        Ok(list.clone())
+    }
+
+    async fn delete_list(&self, user_id: i64, id: i64) -> Result<()> {
+        let mut tables = self.write()?;
+        tables.owned_list_mut(user_id, id)?;
+        // the home page always shows a list, so there has to be one left
+        if !tables
+            .lists
+            .values()
+            .any(|row| row.user_id == Some(user_id) && row.list.id != id)
+        {
+            return Err(TodoError::Conflict(
+                "can't delete the last list".to_string(),
+            ));
+        }
+        tables.lists.remove(&id);
+        let ids = tables
+            .todos
+            .values()
+            .filter(|row| row.todo.list_id == id)
+            .map(|row| row.todo.id)
+            .collect::<Vec<_>>();
+        tables.purge(&ids);
+        Ok(())
+    }
+
+    async fn get_todos(
+        &self,
+        user_id: i64,
+        todo_query: TodoQuery,
+    ) -> Result<Vec<Todo>> {
+        let tables = self.read()?;
+        if !tables.owns_list(user_id, todo_query.list_id) {
+            return Ok(vec![]);
+        }
+        let mut rows = tables
+            .todos
+            .values()
+            .filter(|row| {
+                let todo = &row.todo;
+                todo.list_id == todo_query.list_id
+                    && todo.parent_id.is_none()
+                    && todo.deleted_at.is_none()
+                    && todo_query
+                        .tag
+                        .as_ref()
+                        .is_none_or(|tag| todo.tags.contains(tag))
+                    && passes(todo_query.filter, todo)
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| compare(todo_query.order, a, b));
+        Ok(rows
+            .into_iter()
+            .map(|row| tables.with_details(row.todo.clone()))
+            .collect())
+    }
+
+    async fn get_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let tables = self.read()?;
+        let todo = tables.owned(user_id, id)?.clone();
+        Ok(tables.with_details(todo))
+    }
+
+    async fn add_todo(&self, user_id: i64, new_todo: NewTodo) -> Result<Todo> {
+        let mut tables = self.write()?;
+
+        // only add it if the list exists and belongs to the user
+        if !tables.owns_list(user_id, new_todo.list_id) {
+            return Err(TodoError::NotFound);
+        }
+        check_not_blank("description", &new_todo.description)?;
+        let mut tags = new_todo.tags;
+        tags.sort();
+        tags.dedup();
+        for tag in tags.iter() {
+            check_not_blank("tag", tag)?;
+        }
+
+        let now = self.now();
+        let todo = Todo {
+            id: next_id(&tables.todos),
+            list_id: new_todo.list_id,
+            description: new_todo.description,
+            completed_at: None,
+            created_at: now,
+            updated_at: now,
+            due_at: new_todo.due_at,
+            priority: new_todo.priority,
+            tags,
+            parent_id: None,
+            recurrence: new_todo.recurrence,
+            deleted_at: None,
+            subtasks: vec![],
+        };
+        tables.insert_todo(todo.clone());
+        tables.record_event(
+            user_id,
+            todo.id,
+            TodoEventKind::Created,
+            Some(&todo.description),
+            now,
+        );
+        Ok(todo)
+    }
+
+    async fn update_description(
+        &self,
+        user_id: i64,
+        id: i64,
+        description: String,
+    ) -> Result<Todo> {
+        let mut tables = self.write()?;
+        tables.owned(user_id, id)?;
+        check_not_blank("description", &description)?;
+        tables.update(
+            user_id,
+            id,
+            self.now(),
+            TodoEventKind::Renamed,
+            Some(&description),
+            |todo| todo.description = description.clone(),
+        )
+    }
+
+    async fn set_priority(
+        &self,
+        user_id: i64,
+        id: i64,
+        priority: Priority,
+    ) -> Result<Todo> {
+        self.write()?.update(
+            user_id,
+            id,
+            self.now(),
+            TodoEventKind::Prioritized,
+            Some(priority.as_str()),
+            |todo| todo.priority = priority,
+        )
+    }
+
+    async fn set_recurrence(
+        &self,
+        user_id: i64,
+        id: i64,
+        recurrence: Option<Recurrence>,
+    ) -> Result<Todo> {
+        self.write()?.update(
+            user_id,
+            id,
+            self.now(),
+            TodoEventKind::Repeated,
+            recurrence.as_ref().map(Recurrence::to_string).as_deref(),
+            |todo| todo.recurrence = recurrence.clone(),
+        )
+    }
+
+    async fn toggle_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tables = self.write()?;
+        let now = self.now();
+        if tables.owned(user_id, id)?.is_completed() {
+            tables.reopen(user_id, id, now)?;
+        } else {
+            tables.complete(user_id, id, now)?;
+        }
+        let todo = tables.owned(user_id, id)?.clone();
+        Ok(tables.with_details(todo))
+    }
+
+    async fn get_tags(&self, user_id: i64) -> Result<Vec<String>> {
+        let tables = self.read()?;
+        let mut tags = tables
+            .todos
+            .values()
+            .filter(|row| {
+                row.todo.deleted_at.is_none()
+                    && tables.owns_list(user_id, row.todo.list_id)
+            })
+            .flat_map(|row| row.todo.tags.iter().cloned())
+            .collect::<Vec<_>>();
+        tags.sort();
+        tags.dedup();
+        Ok(tags)
+    }
+
+    async fn tag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> Result<Todo> {
+        let mut tables = self.write()?;
+        let now = self.now();
+        let todo = tables.owned_mut(user_id, id)?;
+        check_not_blank("tag", &tag)?;
+        if let Err(index) = todo.tags.binary_search(&tag) {
+            todo.tags.insert(index, tag.clone());
+            todo.updated_at = now;
+            tables.record_event(
+                user_id,
+                id,
+                TodoEventKind::Tagged,
+                Some(&tag),
+                now,
+            );
+        }
+        let todo = tables.owned(user_id, id)?.clone();
+        Ok(tables.with_details(todo))
+    }
+
+    async fn untag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> Result<Todo> {
+        let mut tables = self.write()?;
+        let now = self.now();
+        let todo = tables.owned_mut(user_id, id)?;
+        if let Ok(index) = todo.tags.binary_search(&tag) {
+            todo.tags.remove(index);
+            todo.updated_at = now;
+            tables.record_event(
+                user_id,
+                id,
+                TodoEventKind::Untagged,
+                Some(&tag),
+                now,
+            );
+        }
+        let todo = tables.owned(user_id, id)?.clone();
+        Ok(tables.with_details(todo))
+    }
+
+    async fn add_subtask(
+        &self,
+        user_id: i64,
+        parent_id: i64,
+        description: String,
+    ) -> Result<Todo> {
+        let mut tables = self.write()?;
+        let list_id = tables.owned(user_id, parent_id)?.list_id;
+        check_not_blank("description", &description)?;
+
+        let now = self.now();
+        let todo = Todo {
+            list_id,
+            parent_id: Some(parent_id),
+            created_at: now,
+            updated_at: now,
+            ..Todo::new(next_id(&tables.todos), description)
+        };
+        tables.insert_todo(todo.clone());
+        tables.record_event(
+            user_id,
+            todo.id,
+            TodoEventKind::Created,
+            Some(&todo.description),
+            now,
+        );
+        // a completed todo can't have anything left to do underneath it
+        tables.reopen(user_id, parent_id, now)?;
+        Ok(todo)
+    }
+
+    async fn get_subtasks(&self, user_id: i64, id: i64) -> Result<Vec<Todo>> {
+        Ok(self.get_todo(user_id, id).await?.subtasks)
+    }
+
+    async fn move_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        before: Option<i64>,
+        after: Option<i64>,
+    ) -> Result<Todo> {
+        let mut tables = self.write()?;
+        let todo = tables.owned(user_id, id)?.clone();
+
+        let (lower, upper) = tables.position_bounds(&todo, before, after)?;
+        let position = match position_between(lower, upper) {
+            Some(position) => position,
+            None => {
+                // out of room between the two, so make some and try again
+                tables.renumber_positions(todo.list_id);
+                let (lower, upper) =
+                    tables.position_bounds(&todo, before, after)?;
+                position_between(lower, upper).ok_or_else(|| {
+                    TodoError::Storage("ran out of room to move todo".into())
+                })?
+            }
+        };
+        tables.row_mut(id)?.position = position;
+        tables.record_event(
+            user_id,
+            id,
+            TodoEventKind::Moved,
+            None,
+            self.now(),
+        );
+        Ok(tables.with_details(todo))
+    }
+
+    async fn delete_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tables = self.write()?;
+        tables.owned(user_id, id)?;
+        tables.trash(user_id, id, self.now())?;
+        let todo = tables.owned_any(user_id, id)?.clone();
+        Ok(tables.with_details(todo))
+    }
+
+    async fn complete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.write()?.bulk_update_list(
+            user_id,
+            list_id,
+            TodoFilter::Active,
+            BulkAction::Complete,
+            self.now(),
+        )
+    }
+
+    async fn uncomplete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.write()?.bulk_update_list(
+            user_id,
+            list_id,
+            TodoFilter::Completed,
+            BulkAction::Uncomplete,
+            self.now(),
+        )
+    }
+
+    async fn delete_completed(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.write()?.bulk_update_list(
+            user_id,
+            list_id,
+            TodoFilter::Completed,
+            BulkAction::Delete,
+            self.now(),
+        )
+    }
+
+    async fn bulk_update(
+        &self,
+        user_id: i64,
+        ids: Vec<i64>,
+        action: BulkAction,
+    ) -> Result<Vec<Todo>> {
+        let mut tables = self.write()?;
+        let ids = tables.apply_bulk(user_id, &ids, action, self.now())?;
+        Ok(tables.todos_by_id(&ids))
+    }
+
+    async fn import(
+        &self,
+        user_id: i64,
+        list_id: i64,
+        todos: Vec<ImportedTodo>,
+    ) -> Result<Vec<Todo>> {
+        let mut tables = self.write()?;
+        if !tables.owns_list(user_id, list_id) {
+            return Err(TodoError::NotFound);
+        }
+        // check everything first, so a bad todo leaves nothing behind
+        for (i, todo) in todos.iter().enumerate() {
+            match (todo.parent, &todo.list) {
+                (Some(parent), _) if parent >= i => {
+                    return Err(TodoError::Validation(
+                        "subtasks have to come after the todo they're under"
+                            .to_string(),
+                    ));
+                }
+                (None, Some(name)) => check_not_blank("name", name)?,
+                _ => {}
+            }
+            check_not_blank("description", &todo.description)?;
+            for tag in todo.tags.iter() {
+                check_not_blank("tag", tag)?;
+            }
+        }
+
+        let now = self.now();
+        let mut imported: Vec<Todo> = vec![];
+        for todo in todos {
+            let (list_id, parent_id) = match (todo.parent, &todo.list) {
+                (Some(parent), _) => {
+                    (imported[parent].list_id, Some(imported[parent].id))
+                }
+                (None, Some(name)) => (tables.list_named(user_id, name), None),
+                (None, None) => (list_id, None),
+            };
+            let (completed_at, created_at, updated_at) = todo.timestamps(now);
+            let mut tags = todo.tags;
+            tags.sort();
+            tags.dedup();
+            let new_todo = Todo {
+                id: next_id(&tables.todos),
+                list_id,
+                description: todo.description,
+                completed_at,
+                created_at,
+                updated_at,
+                due_at: todo.due_at,
+                priority: todo.priority,
+                tags,
+                parent_id,
+                recurrence: todo.recurrence,
+                deleted_at: None,
+                subtasks: vec![],
+            };
+            tables.insert_todo(new_todo.clone());
+            tables.record_event(
+                user_id,
+                new_todo.id,
+                TodoEventKind::Created,
+                Some(&new_todo.description),
+                now,
+            );
+            imported.push(new_todo);
+        }
+        Ok(imported)
+    }
+
+    async fn get_trash(&self, user_id: i64) -> Result<Vec<Todo>> {
+        let tables = self.read()?;
+        let mut todos = tables
+            .todos
+            .values()
+            .map(|row| &row.todo)
+            .filter(|todo| {
+                todo.deleted_at.is_some()
+                    && tables.owns_list(user_id, todo.list_id)
+                    && todo
+                        .parent_id
+                        .and_then(|parent_id| tables.todos.get(&parent_id))
+                        .is_none_or(|parent| parent.todo.deleted_at.is_none())
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        todos.sort_by(|a, b| {
+            b.deleted_at.cmp(&a.deleted_at).then(a.id.cmp(&b.id))
+        });
+        Ok(todos)
+    }
+
+    async fn restore_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tables = self.write()?;
+
+        let todo = tables.owned_any(user_id, id)?;
+        let (Some(deleted_at), parent_id) = (todo.deleted_at, todo.parent_id)
+        else {
+            return Err(TodoError::NotFound);
+        };
+        if let Some(parent_id) = parent_id {
+            let parent_trashed = tables
+                .todos
+                .get(&parent_id)
+                .is_some_and(|parent| parent.todo.deleted_at.is_some());
+            if parent_trashed {
+                return Err(TodoError::Conflict(
+                    "restore the todo this is a subtask of first".to_string(),
+                ));
+            }
+        }
+        let now = self.now();
+        for id in tables.subtree(id) {
+            let todo = &mut tables.row_mut(id)?.todo;
+            if todo.deleted_at == Some(deleted_at) {
+                todo.deleted_at = None;
+                tables.record_event(
+                    user_id,
+                    id,
+                    TodoEventKind::Restored,
+                    None,
+                    now,
+                );
+            }
+        }
+
+        let todo = tables.owned(user_id, id)?.clone();
+        Ok(tables.with_details(todo))
+    }
+
+    async fn purge_todo(&self, user_id: i64, id: i64) -> Result<()> {
+        let mut tables = self.write()?;
+        if tables.owned_any(user_id, id)?.deleted_at.is_none() {
+            return Err(TodoError::NotFound);
+        }
+        tables.purge(&[id]);
+        Ok(())
+    }
+
+    async fn empty_trash(&self, user_id: i64) -> Result<u64> {
+        let mut tables = self.write()?;
+        let ids = tables
+            .todos
+            .values()
+            .filter(|row| {
+                row.todo.deleted_at.is_some()
+                    && tables.owns_list(user_id, row.todo.list_id)
+            })
+            .map(|row| row.todo.id)
+            .collect::<Vec<_>>();
+        tables.purge(&ids);
+        Ok(ids.len() as u64)
+    }
+
+    async fn purge_expired_trash(&self, retention: Duration) -> Result<u64> {
+        let cutoff = self.now() - retention.as_millis() as i64;
+        let mut tables = self.write()?;
+        let ids = tables
+            .todos
+            .values()
+            .filter(|row| row.todo.deleted_at.is_some_and(|at| at < cutoff))
+            .map(|row| row.todo.id)
+            .collect::<Vec<_>>();
+        tables.purge(&ids);
+        Ok(ids.len() as u64)
+    }
+
+    async fn history(&self, user_id: i64, id: i64) -> Result<Vec<TodoEvent>> {
+        let tables = self.read()?;
+        // make sure the todo is theirs first, so that someone else's looks
+        // missing rather than empty
+        tables.owned_any(user_id, id)?;
+        let mut events = tables
+            .events
+            .iter()
+            .filter(|(_, event)| event.todo_id == id)
+            .map(|(&event_id, event)| TodoEvent {
+                id: event_id,
+                todo_id: event.todo_id,
+                username: tables
+                    .users
+                    .get(&event.user_id)
+                    .map(|user| user.username.clone()),
+                kind: event.kind,
+                detail: event.detail.clone(),
+                created_at: event.created_at,
+            })
+            .collect::<Vec<_>>();
+        events.sort_by_key(|event| (event.created_at, event.id));
+        Ok(events)
+    }
+
+    async fn search(
+        &self,
+        user_id: i64,
+        text: String,
+    ) -> Result<Vec<SearchHit>> {
+        let phrases = search_phrases(&text);
+        if phrases.is_empty() {
+            return Ok(vec![]);
+        }
+        let tables = self.read()?;
+
+        // like SQLite's full-text index, the statistics that go into ranking
+        // cover every todo there is
+        let documents = tables
+            .todos
+            .values()
+            .map(|row| Document::new(&row.todo, &phrases))
+            .collect::<Vec<_>>();
+        let rows = documents.len() as f64;
+        let average_length = documents
+            .iter()
+            .map(|document| document.words.len())
+            .sum::<usize>() as f64
+            / rows;
+        let idfs = (0..phrases.len())
+            .map(|phrase| {
+                let hits = documents
+                    .iter()
+                    .filter(|document| !document.matches[phrase].is_empty())
+                    .count() as f64;
+                let idf = ((rows - hits + 0.5) / (hits + 0.5)).ln();
+                if idf > 0.0 { idf } else { 1e-6 }
+            })
+            .collect::<Vec<_>>();
+
+        let mut hits = documents
+            .iter()
+            .filter(|document| {
+                document.matches.iter().all(|matches| !matches.is_empty())
+                    && document.todo.deleted_at.is_none()
+                    && tables.owns_list(user_id, document.todo.list_id)
+            })
+            .map(|document| (document.score(&idfs, average_length), document))
+            .collect::<Vec<_>>();
+        hits.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.total_cmp(a_score).then(a.todo.id.cmp(&b.todo.id))
+        });
+        hits.truncate(SEARCH_LIMIT as usize);
+
+        Ok(hits
+            .into_iter()
+            .map(|(_, document)| SearchHit {
+                todo: tables.with_details(document.todo.clone()),
+                list_name: tables
+                    .lists
+                    .get(&document.todo.list_id)
+                    .map(|row| row.list.name.clone())
+                    .unwrap_or_default(),
+                snippet: parse_snippet(&document.snippet()),
+            })
+            .collect())
+    }
+}
+
+impl UserDao for TodoMemoryDao {
+    async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> Result<User> {
+        let mut tables = self.write()?;
+
+        // usernames don't care about case, same as on SQLite
+        if tables
+            .users
+            .values()
+            .any(|user| user.username.eq_ignore_ascii_case(&username))
+        {
+            return Err(TodoError::Conflict(
+                "that username is already taken".to_string(),
+            ));
+        }
+        let id = next_id(&tables.users);
+        tables.users.insert(
+            id,
+            Credentials {
+                id,
+                username: username.clone(),
+                password_hash,
+            },
+        );
+
+        let list_id = next_id(&tables.lists);
+        tables.lists.insert(
+            list_id,
+            ListRow {
+                list: List::new(list_id, "Todos"),
+                user_id: Some(id),
+            },
+        );
+
+        Ok(User { id, username })
+    }
+
+    async fn get_credentials(&self, username: String) -> Result<Credentials> {
+        self.read()?
+            .users
+            .values()
+            .find(|user| user.username.eq_ignore_ascii_case(&username))
+            .cloned()
+            .ok_or(TodoError::NotFound)
+    }
+
+    async fn create_session(&self, token: String, user_id: i64) -> Result<()> {
+        let expires_at = self.now() + SESSION_TTL.as_millis() as i64;
+        let mut tables = self.write()?;
+        if tables.sessions.contains_key(&token) {
+            return Err(TodoError::Conflict(
+                "that session already exists".to_string(),
+            ));
+        }
+        tables.sessions.insert(
+            token,
+            Session {
+                user_id,
+                expires_at,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_session_user(&self, token: String) -> Result<User> {
+        let tables = self.read()?;
+        tables
+            .sessions
+            .get(&token)
+            .filter(|session| session.expires_at > self.now())
+            .and_then(|session| tables.users.get(&session.user_id))
+            .map(|credentials| User::from(credentials.clone()))
+            .ok_or(TodoError::NotFound)
+    }
+
+    async fn delete_session(&self, token: String) -> Result<()> {
+        self.write()?.sessions.remove(&token);
+        Ok(())
+    }
+
+    async fn claim_unowned_lists(&self, user_id: i64) -> Result<u64> {
+        let mut claimed = 0;
+        for row in self.write()?.lists.values_mut() {
+            if row.user_id.is_none() {
+                row.user_id = Some(user_id);
+                claimed += 1;
+            }
+        }
+        Ok(claimed)
+    }
+}
+
+/// How many words a search snippet shows, like the `12` SQLite's `snippet`
+/// is given.
+const SNIPPET_WORDS: usize = 12;
+
+/// Splits text into words the way FTS5's default tokenizer does: runs of
+/// letters and numbers, with anything else in between. Returns where in the
+/// text each word is.
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = vec![];
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        let in_word = c.is_alphanumeric() || is_combining_mark(c);
+        match start {
+            None if in_word => start = Some(i),
+            Some(word_start) if !in_word => {
+                words.push(word_start..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        words.push(word_start..text.len());
+    }
+    words
+}
+
+/// Folds a word down to what search compares: lowercase, without accents.
+fn fold(word: &str) -> String {
+    word.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Reads whatever the user typed the way SQLite reads the query `fts_query`
+/// makes from it: each word is a phrase whose last word only has to match as
+/// a prefix. Words that are all punctuation can't match anything, so SQLite
+/// ignores them, and so does this.
+fn search_phrases(text: &str) -> Vec<Vec<String>> {
+    text.split_whitespace()
+        .map(|word| {
+            words(word)
+                .into_iter()
+                .map(|range| fold(&word[range]))
+                .collect::<Vec<_>>()
+        })
+        .filter(|phrase| !phrase.is_empty())
+        .collect()
+}
+
+/// A todo's description split up for searching, along with where each of
+/// the phrases being searched for turns up in it, as ranges of words.
+struct Document<'a> {
+    todo: &'a Todo,
+    words: Vec<Range<usize>>,
+    matches: Vec<Vec<Range<usize>>>,
+}
+
+impl<'a> Document<'a> {
+    fn new(todo: &'a Todo, phrases: &[Vec<String>]) -> Self {
+        let words = words(&todo.description);
+        let folded = words
+            .iter()
+            .map(|range| fold(&todo.description[range.clone()]))
+            .collect::<Vec<_>>();
+        let matches = phrases
+            .iter()
+            .map(|phrase| {
+                let Some((last, rest)) = phrase.split_last() else {
+                    return vec![];
+                };
+                let starts = 0..(folded.len() + 1).saturating_sub(phrase.len());
+                starts
+                    .filter(|&i| {
+                        folded[i..].iter().zip(rest).all(|(a, b)| a == b)
+                            && folded[i + rest.len()].starts_with(last.as_str())
+                    })
+                    .map(|i| i..i + phrase.len())
+                    .collect()
+            })
+            .collect();
+        Self {
+            todo,
+            words,
+            matches,
+        }
+    }
+
+    /// How well the description matches, worked out the way SQLite's `bm25`
+    /// does it except that higher is better.
+    fn score(&self, idfs: &[f64], average_length: f64) -> f64 {
+        const K1: f64 = 1.2;
+        const B: f64 = 0.75;
+        let length = self.words.len() as f64;
+        self.matches
+            .iter()
+            .zip(idfs)
+            .map(|(matches, idf)| {
+                let frequency = matches.len() as f64;
+                idf * ((frequency * (K1 + 1.0))
+                    / (frequency
+                        + K1 * (1.0 - B + B * length / average_length)))
+            })
+            .sum()
+    }
+
+    /// The part of the description that best shows why it matched, with the
+    /// matches marked, picked the way SQLite's `snippet` does it: the dozen
+    /// words showing the most different phrases, preferring the start of a
+    /// sentence.
+    fn snippet(&self) -> String {
+        let description = &self.todo.description;
+        let mut matches = self
+            .matches
+            .iter()
+            .enumerate()
+            .flat_map(|(phrase, matches)| {
+                matches.iter().map(move |words| (phrase, words.clone()))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(phrase, words)| (words.start, *phrase));
+
+        // scores the window of words starting at `start`, and says where the
+        // window should start instead to centre what it found
+        let window = |start: usize| {
+            let mut seen = HashSet::new();
+            let mut score = 0;
+            let mut found: Option<(usize, usize)> = None;
+            for (phrase, words) in matches.iter() {
+                if (start..start + SNIPPET_WORDS).contains(&words.start) {
+                    score += if seen.insert(phrase) { 1000 } else { 1 };
+                    let first = found.map_or(words.start, |(first, _)| first);
+                    found = Some((first, words.end));
+                }
+            }
+            let (first, last) = found.unwrap_or_default();
+            let centred = first as i64
+                - (SNIPPET_WORDS as i64 - (last - first) as i64) / 2;
+            let centred = centred
+                .min(self.words.len() as i64 - SNIPPET_WORDS as i64)
+                .max(0);
+            (score, centred as usize)
+        };
+        let sentences = self.sentence_starts();
+        let mut best = (0, 0);
+        for (_, words) in matches.iter() {
+            let (score, start) = window(words.start);
+            if score > best.0 {
+                best = (score, start);
+            }
+            if self.words.len() > SNIPPET_WORDS {
+                let sentence = sentences
+                    .iter()
+                    .copied()
+                    .take_while(|&sentence| sentence <= words.start)
+                    .last()
+                    .unwrap_or(0);
+                if sentence < words.start {
+                    let bonus = if sentence == 0 { 120 } else { 100 };
+                    let score = window(sentence).0 + bonus;
+                    if score > best.0 {
+                        best = (score, sentence);
+                    }
+                }
+            }
+        }
+        let start = best.1;
+        let end = (start + SNIPPET_WORDS).min(self.words.len());
+
+        // matches that overlap are highlighted as one, but ones that are
+        // only next to each other aren't
+        let mut highlights: Vec<Range<usize>> = vec![];
+        for (_, words) in matches.iter() {
+            if !(start..end).contains(&words.start) {
+                continue;
+            }
+            let words = words.start..words.end.min(end);
+            match highlights.last_mut() {
+                Some(last) if words.start < last.end => {
+                    last.end = last.end.max(words.end)
+                }
+                _ => highlights.push(words),
+            }
+        }
+
+        let mut snippet = String::new();
+        let mut offset = 0;
+        if start > 0 {
+            snippet.push('…');
+            offset = self.words[start].start;
+        }
+        for words in highlights {
+            let from = self.words[words.start].start;
+            let to = self.words[words.end - 1].end;
+            snippet.push_str(&description[offset..from]);
+            snippet.push(HIGHLIGHT_START);
+            snippet.push_str(&description[from..to]);
+            snippet.push(HIGHLIGHT_END);
+            offset = to;
+        }
+        if end == self.words.len() {
+            snippet.push_str(&description[offset..]);
+        } else {
+            snippet.push_str(&description[offset..self.words[end - 1].end]);
+            snippet.push('…');
+        }
+        snippet
+    }
+
+    /// The words that start a sentence: the first, and any that come after
+    /// a `.` or `:` and some space.
+    fn sentence_starts(&self) -> Vec<usize> {
+        let description = &self.todo.description;
+        (0..self.words.len())
+            .filter(|&i| {
+                let before = &description[..self.words[i].start];
+                let trimmed = before.trim_end_matches([' ', '\t', '\n', '\r']);
+                i == 0
+                    || (trimmed.len() < before.len()
+                        && (trimmed.ends_with('.') || trimmed.ends_with(':')))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::FakeClock,
+        dao_tests::{TestDao, dao_tests},
+    };
+
+    fn snippet(description: &str, text: &str) -> String {
+        let todo = Todo::new(1, description);
+        Document::new(&todo, &search_phrases(text)).snippet()
+    }
+
+    #[test]
+    fn test_search_phrases() {
+        assert_eq!(
+            search_phrases(" Buy  crème-BRÛLÉE \"- "),
+            vec![vec!["buy"], vec!["creme", "brulee"]]
+        );
+        assert!(search_phrases("  ").is_empty());
+    }
+
+    #[test]
+    fn test_snippet() {
+        assert_eq!(
+            snippet("  Buy milk AND milk-man", "milk-man and"),
+            "  Buy milk \u{2}AND\u{3} \u{2}milk-man\u{3}"
+        );
+        assert_eq!(
+            snippet("Buy milk AND eggs", "buy milk"),
+            "\u{2}Buy\u{3} \u{2}milk\u{3} AND eggs"
+        );
+    }
+
+    #[test]
+    fn test_snippet_of_long_description() {
+        let description =
+            "a. b c d e f g h i j k l m: n o p q r s t u v w x y z";
+
+        // the same snippets SQLite makes, starting at a sentence if it can
+        assert_eq!(
+            snippet(description, "c"),
+            "…b \u{2}c\u{3} d e f g h i j k l m…"
+        );
+        assert_eq!(
+            snippet(description, "p"),
+            "…n o \u{2}p\u{3} q r s t u v w x y…"
+        );
+        assert_eq!(
+            snippet(description, "z"),
+            "…o p q r s t u v w x y \u{2}z\u{3}"
+        );
+    }
+
+    impl TestDao for TodoMemoryDao {
+        async fn empty(clock: FakeClock) -> Self {
+            TodoMemoryDao::new().with_clock(clock)
+        }
+
+        async fn add_unowned_list(&self, name: &str) -> i64 {
+            let mut tables = self.write().expect("failed to add list");
+            let id = next_id(&tables.lists);
+            tables.lists.insert(
+                id,
+                ListRow {
+                    list: List::new(id, name),
+                    user_id: None,
+                },
+            );
+            id
+        }
+    }
+
+    dao_tests!(TodoMemoryDao);
+}
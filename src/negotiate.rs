@@ -0,0 +1,230 @@
+use crate::{
+    errors::ErrorMessage,
+    views::{Render, RenderResponse},
+};
+use axum::{
+    Json,
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{HeaderMap, header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response, Result as AxumResult},
+};
+use axum_extra::extract::Form;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{convert::Infallible, fmt::Debug};
+
+/// The representation a client asked for via its `Accept` header.
+///
+/// Browsers and htmx never ask for JSON explicitly, so HTML is the default.
+/// JSON is only picked when the client gives it a higher `q` than HTML, and
+/// `q=0` refuses a type outright.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Html,
+    Json,
+}
+
+impl Format {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut json = 0.0;
+        let mut html = 0.0;
+        let media_ranges = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for media_range in media_ranges {
+            let mut parts = media_range.split(';');
+            let media_type = parts.next().unwrap_or_default().trim();
+            let best = if media_type.eq_ignore_ascii_case("application/json") {
+                &mut json
+            } else if media_type.eq_ignore_ascii_case("text/html") {
+                &mut html
+            } else {
+                continue;
+            };
+            *best = f32::max(*best, quality(parts));
+        }
+        if json > html {
+            Format::Json
+        } else {
+            Format::Html
+        }
+    }
+}
+
+/// The `q` among a media range's parameters, which is 1 if it's missing.
+fn quality<'a>(params: impl Iterator<Item = &'a str>) -> f32 {
+    params
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(1.0)
+}
+
+impl<S> FromRequestParts<S> for Format
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Ok(Format::from_headers(&parts.headers))
+    }
+}
+
+/// A view that is rendered as HTML or serialized as JSON depending on the
+/// requested [`Format`].
+#[derive(Debug)]
+pub struct Negotiated<T: Render + Serialize + Debug>(pub Format, pub T);
+
+impl<T: Render + Serialize + Debug> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        match self.0 {
+            Format::Html => RenderResponse(self.1).into_response(),
+            Format::Json => Json(self.1).into_response(),
+        }
+    }
+}
+
+pub type Result<T> = AxumResult<Negotiated<T>>;
+
+/// Request body extractor that accepts either `application/json` or a
+/// url-encoded form, based on the `Content-Type` header. Forms may repeat a
+/// field to fill in a `Vec`, the way checkboxes that share a name do.
+#[derive(Debug)]
+pub struct JsonOrForm<T>(pub T);
+
+impl<S, T> FromRequest<S> for JsonOrForm<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = Response;
+
+    async fn from_request(
+        req: Request,
+        state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let is_json = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        if is_json {
+            let Json(value) = Json::<T>::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            Ok(Self(value))
+        } else {
+            let Form(value) = Form::<T>::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            Ok(Self(value))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Middleware that swaps the rendered error fragment for a JSON body when the
+/// client asked for JSON.
+pub async fn json_errors(request: Request, next: Next) -> Response {
+    let format = Format::from_headers(request.headers());
+    let response = next.run(request).await;
+    if format != Format::Json {
+        return response;
+    }
+    match response.extensions().get::<ErrorMessage>() {
+        Some(ErrorMessage(message)) => (
+            response.status(),
+            Json(ErrorBody {
+                error: message.clone(),
+            }),
+        )
+            .into_response(),
+        None => response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers_with_accept(accept: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static(accept));
+        headers
+    }
+
+    #[test]
+    fn test_format_defaults_to_html() {
+        assert_eq!(Format::from_headers(&HeaderMap::new()), Format::Html);
+        assert_eq!(
+            Format::from_headers(&headers_with_accept("*/*")),
+            Format::Html
+        );
+        assert_eq!(
+            Format::from_headers(&headers_with_accept("text/html")),
+            Format::Html
+        );
+    }
+
+    #[test]
+    fn test_format_json() {
+        assert_eq!(
+            Format::from_headers(&headers_with_accept("application/json")),
+            Format::Json
+        );
+        assert_eq!(
+            Format::from_headers(&headers_with_accept(
+                "text/plain, application/json; q=0.9"
+            )),
+            Format::Json
+        );
+    }
+
+    #[test]
+    fn test_format_json_refused() {
+        assert_eq!(
+            Format::from_headers(&headers_with_accept(
+                "text/html, application/json;q=0"
+            )),
+            Format::Html
+        );
+        assert_eq!(
+            Format::from_headers(&headers_with_accept("application/json;q=0")),
+            Format::Html
+        );
+    }
+
+    #[test]
+    fn test_format_prefers_highest_quality() {
+        assert_eq!(
+            Format::from_headers(&headers_with_accept(
+                "application/json;q=0.5, text/html;q=0.8"
+            )),
+            Format::Html
+        );
+        assert_eq!(
+            Format::from_headers(&headers_with_accept(
+                "text/html;q=0.5, application/json;q=0.8"
+            )),
+            Format::Json
+        );
+        // a tie goes to HTML, like everything else
+        assert_eq!(
+            Format::from_headers(&headers_with_accept(
+                "text/html, application/json"
+            )),
+            Format::Html
+        );
+    }
+}
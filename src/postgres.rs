@@ -0,0 +1,1618 @@
+use crate::{
+    clock::{Clock, SystemClock},
+    errors::{Result, TodoError},
+    recurrence::Recurrence,
+    todos::{
+        BulkAction, HIGHLIGHT_END, HIGHLIGHT_START, ImportedTodo, List,
+        NewTodo, Priority, SEARCH_LIMIT, SearchHit, SearchRow, Todo, TodoDao,
+        TodoEvent, TodoEventKind, TodoQuery, attach_subtasks,
+        next_occurrence_parent, parse_snippet, position_between,
+    },
+    users::{Credentials, SESSION_TTL, User, UserDao},
+};
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use sqlx::{PgConnection, PgPool, query, query_as, query_scalar};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// Stores everything in Postgres. It behaves exactly like `TodoSqliteDao`;
+/// only the SQL differs.
+#[derive(Clone, Debug)]
+pub struct TodoPostgresDao {
+    pool: PgPool,
+    clock: Arc<dyn Clock>,
+}
+
+/// Turns whatever the user typed into a `tsquery` matching every word as a
+/// prefix. Quoting the words stops Postgres from treating things like `&` or
+/// `!` as syntax.
+fn ts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            format!("'{}':*", word.replace('\\', "\\\\").replace('\'', "''"))
+        })
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// Options for `ts_headline` that come as close as Postgres gets to the
+/// snippets SQLite makes: about a dozen words, highlighted the same way.
+fn headline_options() -> String {
+    format!(
+        "StartSel={}, StopSel={}, MaxWords=12, MinWords=11",
+        HIGHLIGHT_START, HIGHLIGHT_END
+    )
+}
+
+/// `ts_headline` doesn't say when it's cut a description short, so mark
+/// those ends with an ellipsis like SQLite does.
+fn mark_truncation(headline: &str, description: &str) -> String {
+    let text = headline
+        .chars()
+        .filter(|c| *c != HIGHLIGHT_START && *c != HIGHLIGHT_END)
+        .collect::<String>();
+    let mut snippet = String::new();
+    if !description.starts_with(&text) {
+        snippet.push('…');
+    }
+    snippet.push_str(headline);
+    if !description.ends_with(&text) {
+        snippet.push('…');
+    }
+    snippet
+}
+
+impl TodoPostgresDao {
+    pub fn new(pool: PgPool) -> Self {
+        TodoPostgresDao {
+            pool,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The time to stamp anything written now with, in millis.
+    fn now(&self) -> i64 {
+        self.clock.now_millis()
+    }
+
+    /// Fills in the tags of todos fetched from the `todos` table.
+    async fn load_tags(&self, todos: &mut [Todo]) -> Result<()> {
+        if todos.is_empty() {
+            return Ok(());
+        }
+        let ids = todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
+        let tags: Vec<(i64, String)> = query_as(
+            "SELECT todo_tags.todo_id, tags.name FROM todo_tags
+            JOIN tags ON tags.id = todo_tags.tag_id
+            WHERE todo_tags.todo_id = ANY($1)
+            ORDER BY tags.name",
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        for todo in todos.iter_mut() {
+            todo.tags = tags
+                .iter()
+                .filter(|(todo_id, _)| *todo_id == todo.id)
+                .map(|(_, name)| name.clone())
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Fills in the subtasks of todos, and theirs, all the way down.
+    async fn load_subtasks(&self, todos: &mut [Todo]) -> Result<()> {
+        if todos.is_empty() {
+            return Ok(());
+        }
+        let ids = todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
+        let mut descendants = query_as::<_, Todo>(
+            "WITH RECURSIVE descendants (id) AS (
+                SELECT id FROM todos
+                WHERE parent_id = ANY($1) AND deleted_at IS NULL
+                UNION
+                SELECT todos.id FROM todos
+                JOIN descendants ON todos.parent_id = descendants.id
+                WHERE todos.deleted_at IS NULL
+            )
+            SELECT todos.* FROM todos
+            JOIN descendants ON descendants.id = todos.id
+            ORDER BY todos.position, todos.id",
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        self.load_tags(&mut descendants).await?;
+        attach_subtasks(todos, &descendants);
+        Ok(())
+    }
+
+    /// Fills in everything about todos that doesn't live in the `todos` row.
+    async fn load_details(&self, todos: &mut [Todo]) -> Result<()> {
+        self.load_tags(todos).await?;
+        self.load_subtasks(todos).await
+    }
+
+    async fn with_details(&self, mut todo: Todo) -> Result<Todo> {
+        self.load_details(std::slice::from_mut(&mut todo)).await?;
+        Ok(todo)
+    }
+
+    /// Fetches a todo the user owns, inside a transaction. Trashed todos
+    /// don't count.
+    async fn get_owned_todo(
+        conn: &mut PgConnection,
+        user_id: i64,
+        id: i64,
+    ) -> Result<Todo> {
+        let todo = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = $1 AND lists.user_id = $2
+            AND todos.deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+        Ok(todo)
+    }
+
+    /// Finds the positions a todo has to go between to end up right after
+    /// `after` and right before `before`. Only its siblings count, since
+    /// subtasks are ordered under their parent.
+    async fn position_bounds(
+        conn: &mut PgConnection,
+        todo: &Todo,
+        before: Option<i64>,
+        after: Option<i64>,
+    ) -> Result<(Option<f64>, Option<f64>)> {
+        let lower = match after {
+            Some(after) => {
+                Some(Self::neighbour_position(conn, todo, after).await?)
+            }
+            None => None,
+        };
+        let upper = match before {
+            Some(before) => {
+                Some(Self::neighbour_position(conn, todo, before).await?)
+            }
+            None => None,
+        };
+        match (lower, upper) {
+            (None, None) => Err(TodoError::Validation(
+                "say which todo to move it next to".to_string(),
+            )),
+            (Some(lower), Some(upper)) if lower >= upper => {
+                Err(TodoError::Validation(
+                    "the todo to move it after has to come first".to_string(),
+                ))
+            }
+            // only one side was given, so the other is whatever is next to it
+            (Some(lower), None) => {
+                let upper = query_scalar(
+                    "SELECT MIN(position) FROM todos
+                    WHERE list_id = $1 AND id != $2 AND position > $3
+                    AND parent_id IS NOT DISTINCT FROM $4
+                    AND deleted_at IS NULL",
+                )
+                .bind(todo.list_id)
+                .bind(todo.id)
+                .bind(lower)
+                .bind(todo.parent_id)
+                .fetch_one(&mut *conn)
+                .await?;
+                Ok((Some(lower), upper))
+            }
+            (None, Some(upper)) => {
+                let lower = query_scalar(
+                    "SELECT MAX(position) FROM todos
+                    WHERE list_id = $1 AND id != $2 AND position < $3
+                    AND parent_id IS NOT DISTINCT FROM $4
+                    AND deleted_at IS NULL",
+                )
+                .bind(todo.list_id)
+                .bind(todo.id)
+                .bind(upper)
+                .bind(todo.parent_id)
+                .fetch_one(&mut *conn)
+                .await?;
+                Ok((lower, Some(upper)))
+            }
+            bounds => Ok(bounds),
+        }
+    }
+
+    async fn neighbour_position(
+        conn: &mut PgConnection,
+        todo: &Todo,
+        neighbour_id: i64,
+    ) -> Result<f64> {
+        query_scalar(
+            "SELECT position FROM todos
+            WHERE id = $1 AND list_id = $2 AND id != $3
+            AND parent_id IS NOT DISTINCT FROM $4 AND deleted_at IS NULL",
+        )
+        .bind(neighbour_id)
+        .bind(todo.list_id)
+        .bind(todo.id)
+        .bind(todo.parent_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| {
+            TodoError::Validation(
+                "todos can only be moved next to others under the same parent \
+                in the same list"
+                    .to_string(),
+            )
+        })
+    }
+
+    /// Adds an entry to a todo's history. Call it inside the transaction that
+    /// makes the change, so that the two can't disagree.
+    async fn record_event(
+        &self,
+        conn: &mut PgConnection,
+        user_id: i64,
+        todo_id: i64,
+        kind: TodoEventKind,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        query(
+            "INSERT INTO todo_events (todo_id, user_id, kind, detail, created_at)
+            VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(kind)
+        .bind(detail)
+        .bind(self.now())
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Completes a todo along with whatever is still open underneath it and
+    /// not in the trash, and schedules the next occurrence of each of those
+    /// that repeats.
+    async fn complete(
+        &self,
+        conn: &mut PgConnection,
+        user_id: i64,
+        todo: &mut Todo,
+    ) -> Result<()> {
+        let now = self.now();
+        let mut completed = query_as::<_, Todo>(
+            "UPDATE todos SET completed_at = $1, updated_at = $1
+            WHERE completed_at IS NULL AND deleted_at IS NULL AND id IN (
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT $2::BIGINT
+                    UNION
+                    SELECT todos.id FROM todos
+                    JOIN subtree ON todos.parent_id = subtree.id
+                    WHERE todos.deleted_at IS NULL
+                )
+                SELECT id FROM subtree
+            )
+            RETURNING *",
+        )
+        .bind(now)
+        .bind(todo.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        // subtasks always come after what they're under, so this puts each
+        // todo's next occurrence in place before its subtasks need it
+        completed.sort_by_key(|done| done.id);
+        for done in &completed {
+            self.record_event(
+                conn,
+                user_id,
+                done.id,
+                TodoEventKind::Completed,
+                None,
+            )
+            .await?;
+        }
+
+        let today = DateTime::from_timestamp_millis(now)
+            .unwrap_or_default()
+            .date_naive();
+        let mut occurrences = HashMap::new();
+        for done in &completed {
+            let Some(recurrence) = &done.recurrence else {
+                continue;
+            };
+            let due = recurrence.next_due(done.due_date(), today);
+            let parent_id =
+                next_occurrence_parent(done, todo, &completed, &occurrences);
+            let Some(next_id) =
+                Self::schedule_next(conn, done, parent_id, due, now).await?
+            else {
+                continue;
+            };
+            occurrences.insert(done.id, next_id);
+            self.record_event(
+                conn,
+                user_id,
+                next_id,
+                TodoEventKind::Created,
+                Some(&done.description),
+            )
+            .await?;
+        }
+        todo.completed_at = Some(now);
+        todo.updated_at = now;
+        Ok(())
+    }
+
+    /// Reopens a todo, along with everything it's a subtask of.
+    async fn uncomplete(
+        &self,
+        conn: &mut PgConnection,
+        user_id: i64,
+        todo: &mut Todo,
+    ) -> Result<()> {
+        let now = self.now();
+        for reopened in Self::reopen(conn, todo.id, now).await? {
+            self.record_event(
+                conn,
+                user_id,
+                reopened,
+                TodoEventKind::Reopened,
+                None,
+            )
+            .await?;
+        }
+        todo.completed_at = None;
+        todo.updated_at = now;
+        Ok(())
+    }
+
+    /// Moves a todo and its subtasks to the trash.
+    async fn trash(
+        &self,
+        conn: &mut PgConnection,
+        user_id: i64,
+        todo: &mut Todo,
+    ) -> Result<()> {
+        // subtasks go in the trash with it, marked with the same time so
+        // that they come back out together too
+        let deleted_at = self.now();
+        let trashed: Vec<i64> = query_scalar(
+            "UPDATE todos SET deleted_at = $1
+            WHERE deleted_at IS NULL AND id IN (
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT $2::BIGINT
+                    UNION
+                    SELECT todos.id FROM todos
+                    JOIN subtree ON todos.parent_id = subtree.id
+                )
+                SELECT id FROM subtree
+            )
+            RETURNING id",
+        )
+        .bind(deleted_at)
+        .bind(todo.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        for trashed in trashed {
+            self.record_event(
+                conn,
+                user_id,
+                trashed,
+                TodoEventKind::Trashed,
+                None,
+            )
+            .await?;
+        }
+        todo.deleted_at = Some(deleted_at);
+        Ok(())
+    }
+
+    /// Applies a bulk action to each of the todos, which must all belong to
+    /// the user, and returns their ids in the order they were given.
+    async fn apply_bulk(
+        &self,
+        conn: &mut PgConnection,
+        user_id: i64,
+        ids: &[i64],
+        action: BulkAction,
+    ) -> Result<Vec<i64>> {
+        // everything has to be there before anything changes
+        for &id in ids {
+            Self::get_owned_todo(conn, user_id, id).await?;
+        }
+        let mut applied: Vec<i64> = Vec::with_capacity(ids.len());
+        for &id in ids {
+            if applied.contains(&id) {
+                continue;
+            }
+            // fetched again one at a time, since doing one can change another
+            // further down, e.g. completing a todo completes its subtasks
+            let mut todo =
+                query_as::<_, Todo>("SELECT * FROM todos WHERE id = $1")
+                    .bind(id)
+                    .fetch_one(&mut *conn)
+                    .await?;
+            if todo.deleted_at.is_none() {
+                match action {
+                    BulkAction::Complete if !todo.is_completed() => {
+                        self.complete(conn, user_id, &mut todo).await?
+                    }
+                    BulkAction::Uncomplete if todo.is_completed() => {
+                        self.uncomplete(conn, user_id, &mut todo).await?
+                    }
+                    BulkAction::Delete => {
+                        self.trash(conn, user_id, &mut todo).await?
+                    }
+                    _ => {}
+                }
+            }
+            applied.push(id);
+        }
+        Ok(applied)
+    }
+
+    /// Fetches todos by id with all their details, in the order given,
+    /// whether they're in the trash or not.
+    async fn get_todos_by_id(&self, ids: &[i64]) -> Result<Vec<Todo>> {
+        let mut todos = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN unnest($1::BIGINT[]) WITH ORDINALITY AS ids (id, n)
+                ON ids.id = todos.id
+            ORDER BY ids.n",
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        self.load_details(&mut todos).await?;
+        Ok(todos)
+    }
+
+    /// Runs a bulk action on the top-level todos of a list that match the
+    /// condition.
+    async fn bulk_update_list(
+        &self,
+        user_id: i64,
+        list_id: i64,
+        condition: &str,
+        action: BulkAction,
+    ) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        query("SELECT id FROM lists WHERE id = $1 AND user_id = $2")
+            .bind(list_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let ids: Vec<i64> = query_scalar(&format!(
+            "SELECT id FROM todos
+            WHERE list_id = $1 AND parent_id IS NULL
+            AND deleted_at IS NULL AND {}
+            ORDER BY position, id",
+            condition
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let ids = self.apply_bulk(&mut tx, user_id, &ids, action).await?;
+        tx.commit().await?;
+        self.get_todos_by_id(&ids).await
+    }
+
+    /// Adds a copy of a recurring todo under `parent_id` that's due next on
+    /// `due`, returning its id, unless the todo still has an open one from
+    /// the last time it was completed.
+    async fn schedule_next(
+        conn: &mut PgConnection,
+        todo: &Todo,
+        parent_id: Option<i64>,
+        due: NaiveDate,
+        now: i64,
+    ) -> Result<Option<i64>> {
+        let Some(next_id) = query_scalar(
+            "INSERT INTO todos
+            (list_id, parent_id, description, due_at, priority, recurrence,
+                position, created_at, updated_at, previous_id)
+            SELECT list_id, $4, description, $2, priority, recurrence, (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                WHERE list_id = previous.list_id
+            ), $3, $3, id
+            FROM todos AS previous WHERE id = $1 AND NOT EXISTS (
+                SELECT 1 FROM todos WHERE previous_id = $1
+                AND completed_at IS NULL AND deleted_at IS NULL
+            )
+            RETURNING id",
+        )
+        .bind(todo.id)
+        .bind(due.and_time(NaiveTime::MIN).and_utc().timestamp_millis())
+        .bind(now)
+        .bind(parent_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        else {
+            return Ok(None);
+        };
+        query(
+            "INSERT INTO todo_tags (todo_id, tag_id)
+            SELECT $1, tag_id FROM todo_tags WHERE todo_id = $2",
+        )
+        .bind(next_id)
+        .bind(todo.id)
+        .execute(&mut *conn)
+        .await?;
+        Ok(Some(next_id))
+    }
+
+    /// Marks a todo as not done, along with everything it's a subtask of,
+    /// returning the ids of the todos that were done.
+    async fn reopen(
+        conn: &mut PgConnection,
+        id: i64,
+        now: i64,
+    ) -> Result<Vec<i64>> {
+        let reopened = query_scalar(
+            "UPDATE todos SET completed_at = NULL, updated_at = $2
+            WHERE completed_at IS NOT NULL AND id IN (
+                WITH RECURSIVE ancestors (id) AS (
+                    SELECT $1::BIGINT
+                    UNION
+                    SELECT todos.parent_id FROM todos
+                    JOIN ancestors ON ancestors.id = todos.id
+                    WHERE todos.parent_id IS NOT NULL
+                )
+                SELECT id FROM ancestors
+            )
+            RETURNING id",
+        )
+        .bind(id)
+        .bind(now)
+        .fetch_all(conn)
+        .await?;
+        Ok(reopened)
+    }
+
+    /// Marks a todo as changed just now, for changes that live outside its
+    /// row.
+    async fn touch(conn: &mut PgConnection, id: i64, now: i64) -> Result<()> {
+        query("UPDATE todos SET updated_at = $1 WHERE id = $2")
+            .bind(now)
+            .bind(id)
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Spreads a list's positions back out to 1, 2, 3, ... keeping the order.
+    async fn renumber_positions(
+        conn: &mut PgConnection,
+        list_id: i64,
+    ) -> Result<()> {
+        query(
+            "UPDATE todos SET position = ranked.position
+            FROM (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS position
+                FROM todos WHERE list_id = $1
+            ) AS ranked
+            WHERE todos.id = ranked.id",
+        )
+        .bind(list_id)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Attaches a tag to a todo, creating the tag if the user hasn't used it
+    /// before. Returns whether the todo didn't already have it.
+    async fn insert_tag(
+        conn: &mut PgConnection,
+        user_id: i64,
+        todo_id: i64,
+        tag: &str,
+    ) -> Result<bool> {
+        query(
+            "INSERT INTO tags (user_id, name) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(tag)
+        .execute(&mut *conn)
+        .await?;
+        let inserted = query(
+            "INSERT INTO todo_tags (todo_id, tag_id)
+            SELECT $1, id FROM tags WHERE user_id = $2 AND name = $3
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(tag)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+        Ok(inserted > 0)
+    }
+
+    /// The user's first list with the name, created if they don't have one.
+    async fn list_named(
+        conn: &mut PgConnection,
+        user_id: i64,
+        name: &str,
+    ) -> Result<i64> {
+        let existing = query_scalar(
+            "SELECT id FROM lists WHERE user_id = $1 AND name = $2
+            ORDER BY id LIMIT 1",
+        )
+        .bind(user_id)
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+        let id = query_scalar(
+            "INSERT INTO lists (user_id, name) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(name)
+        .fetch_one(&mut *conn)
+        .await?;
+        Ok(id)
+    }
+}
+
+impl TodoDao for TodoPostgresDao {
+    async fn get_lists(&self, user_id: i64) -> Result<Vec<List>> {
+        let lists = query_as::<_, List>(
+            "SELECT * FROM lists WHERE user_id = $1 ORDER BY id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(lists)
+    }
+
+    async fn get_list(&self, user_id: i64, id: i64) -> Result<List> {
+        let list = query_as::<_, List>(
+            "SELECT * FROM lists WHERE id = $1 AND user_id = $2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(list)
+    }
+
+    async fn create_list(&self, user_id: i64, name: String) -> Result<List> {
+        let id = query_scalar(
+            "INSERT INTO lists (user_id, name) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(&name)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(List { id, name })
+    }
+
+    async fn rename_list(
+        &self,
+        user_id: i64,
+        id: i64,
+        name: String,
+    ) -> Result<List> {
+        let list = query_as::<_, List>(
+            "UPDATE lists SET name = $1 WHERE id = $2 AND user_id = $3
+            RETURNING *",
+        )
+        .bind(name)
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(list)
+    }
+
+    async fn delete_list(&self, user_id: i64, id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let remaining: i64 = query_scalar(
+            "SELECT COUNT(*) FROM lists WHERE id != $1 AND user_id = $2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let rows_affected =
+            query("DELETE FROM lists WHERE id = $1 AND user_id = $2")
+                .bind(id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        if rows_affected == 0 {
+            return Err(TodoError::NotFound);
+        }
+        // the home page always shows a list, so there has to be one left
+        if remaining == 0 {
+            return Err(TodoError::Conflict(
+                "can't delete the last list".to_string(),
+            ));
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_todos(
+        &self,
+        user_id: i64,
+        todo_query: TodoQuery,
+    ) -> Result<Vec<Todo>> {
+        // the filter and order are fixed strings, so they're safe to splice in
+        let sql = format!(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.list_id = $1 AND lists.user_id = $2
+            AND todos.parent_id IS NULL AND todos.deleted_at IS NULL
+            AND ($3::TEXT IS NULL OR todos.id IN (
+                SELECT todo_tags.todo_id FROM todo_tags
+                JOIN tags ON tags.id = todo_tags.tag_id
+                WHERE tags.user_id = $2 AND tags.name = $3
+            ))
+            AND {}
+            ORDER BY {}",
+            todo_query.filter.condition(),
+            todo_query.order.order_by()
+        );
+        let mut todos = query_as::<_, Todo>(&sql)
+            .bind(todo_query.list_id)
+            .bind(user_id)
+            .bind(todo_query.tag)
+            .fetch_all(&self.pool)
+            .await?;
+        self.load_details(&mut todos).await?;
+        Ok(todos)
+    }
+
+    async fn get_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let todo = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = $1 AND lists.user_id = $2
+            AND todos.deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        self.with_details(todo).await
+    }
+
+    async fn add_todo(&self, user_id: i64, new_todo: NewTodo) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+
+        // only insert if the list exists and belongs to the user
+        let now = self.now();
+        let id = query_scalar(
+            "INSERT INTO todos
+            (list_id, description, due_at, priority, recurrence, position,
+                created_at, updated_at)
+            SELECT id, $2, $3, $4, $5, (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                WHERE list_id = $1
+            ), $7, $7 FROM lists
+            WHERE id = $1 AND user_id = $6
+            RETURNING id",
+        )
+        .bind(new_todo.list_id)
+        .bind(&new_todo.description)
+        .bind(new_todo.due_at)
+        .bind(new_todo.priority)
+        .bind(&new_todo.recurrence)
+        .bind(user_id)
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(TodoError::NotFound)?;
+        let mut tags = new_todo.tags;
+        tags.sort();
+        tags.dedup();
+        for tag in tags.iter() {
+            Self::insert_tag(&mut tx, user_id, id, tag).await?;
+        }
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Created,
+            Some(&new_todo.description),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Todo {
+            id,
+            list_id: new_todo.list_id,
+            description: new_todo.description,
+            completed_at: None,
+            created_at: now,
+            updated_at: now,
+            due_at: new_todo.due_at,
+            priority: new_todo.priority,
+            tags,
+            parent_id: None,
+            recurrence: new_todo.recurrence,
+            deleted_at: None,
+            subtasks: vec![],
+        })
+    }
+
+    async fn update_description(
+        &self,
+        user_id: i64,
+        id: i64,
+        description: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = query_as::<_, Todo>(
+            "UPDATE todos SET description = $1, updated_at = $4
+            WHERE id = $2 AND deleted_at IS NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = $3)
+            RETURNING *",
+        )
+        .bind(&description)
+        .bind(id)
+        .bind(user_id)
+        .bind(self.now())
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Renamed,
+            Some(&todo.description),
+        )
+        .await?;
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn set_priority(
+        &self,
+        user_id: i64,
+        id: i64,
+        priority: Priority,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = query_as::<_, Todo>(
+            "UPDATE todos SET priority = $1, updated_at = $4
+            WHERE id = $2 AND deleted_at IS NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = $3)
+            RETURNING *",
+        )
+        .bind(priority)
+        .bind(id)
+        .bind(user_id)
+        .bind(self.now())
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Prioritized,
+            Some(priority.as_str()),
+        )
+        .await?;
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn set_recurrence(
+        &self,
+        user_id: i64,
+        id: i64,
+        recurrence: Option<Recurrence>,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = query_as::<_, Todo>(
+            "UPDATE todos SET recurrence = $1, updated_at = $4
+            WHERE id = $2 AND deleted_at IS NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = $3)
+            RETURNING *",
+        )
+        .bind(&recurrence)
+        .bind(id)
+        .bind(user_id)
+        .bind(self.now())
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Repeated,
+            recurrence.as_ref().map(Recurrence::to_string).as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn toggle_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let mut todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+
+        if todo.is_completed() {
+            self.uncomplete(&mut tx, user_id, &mut todo).await?;
+        } else {
+            self.complete(&mut tx, user_id, &mut todo).await?;
+        }
+
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn get_tags(&self, user_id: i64) -> Result<Vec<String>> {
+        let tags = query_scalar(
+            "SELECT DISTINCT tags.name FROM tags
+            JOIN todo_tags ON todo_tags.tag_id = tags.id
+            JOIN todos ON todos.id = todo_tags.todo_id
+            WHERE tags.user_id = $1 AND todos.deleted_at IS NULL
+            ORDER BY tags.name",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tags)
+    }
+
+    async fn tag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let mut todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+        if Self::insert_tag(&mut tx, user_id, id, &tag).await? {
+            todo.updated_at = self.now();
+            Self::touch(&mut tx, id, todo.updated_at).await?;
+            self.record_event(
+                &mut tx,
+                user_id,
+                id,
+                TodoEventKind::Tagged,
+                Some(&tag),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn untag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let mut todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+        let removed = query(
+            "DELETE FROM todo_tags WHERE todo_id = $1
+            AND tag_id IN (SELECT id FROM tags WHERE user_id = $2 AND name = $3)",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(&tag)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if removed > 0 {
+            todo.updated_at = self.now();
+            Self::touch(&mut tx, id, todo.updated_at).await?;
+            self.record_event(
+                &mut tx,
+                user_id,
+                id,
+                TodoEventKind::Untagged,
+                Some(&tag),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn add_subtask(
+        &self,
+        user_id: i64,
+        parent_id: i64,
+        description: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let parent = Self::get_owned_todo(&mut tx, user_id, parent_id).await?;
+
+        let now = self.now();
+        let todo = query_as::<_, Todo>(
+            "INSERT INTO todos
+            (list_id, parent_id, description, position, created_at, updated_at)
+            VALUES ($1, $2, $3, (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                WHERE list_id = $1
+            ), $4, $4)
+            RETURNING *",
+        )
+        .bind(parent.list_id)
+        .bind(parent.id)
+        .bind(description)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            todo.id,
+            TodoEventKind::Created,
+            Some(&todo.description),
+        )
+        .await?;
+        // a completed todo can't have anything left to do underneath it
+        for reopened in Self::reopen(&mut tx, parent.id, now).await? {
+            self.record_event(
+                &mut tx,
+                user_id,
+                reopened,
+                TodoEventKind::Reopened,
+                None,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(todo)
+    }
+
+    async fn get_subtasks(&self, user_id: i64, id: i64) -> Result<Vec<Todo>> {
+        Ok(self.get_todo(user_id, id).await?.subtasks)
+    }
+
+    async fn move_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        before: Option<i64>,
+        after: Option<i64>,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+
+        let (lower, upper) =
+            Self::position_bounds(&mut tx, &todo, before, after).await?;
+        let position = match position_between(lower, upper) {
+            Some(position) => position,
+            None => {
+                // out of room between the two, so make some and try again
+                Self::renumber_positions(&mut tx, todo.list_id).await?;
+                let (lower, upper) =
+                    Self::position_bounds(&mut tx, &todo, before, after)
+                        .await?;
+                position_between(lower, upper).ok_or_else(|| {
+                    TodoError::Storage("ran out of room to move todo".into())
+                })?
+            }
+        };
+        query("UPDATE todos SET position = $1 WHERE id = $2")
+            .bind(position)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        self.record_event(&mut tx, user_id, id, TodoEventKind::Moved, None)
+            .await?;
+
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn delete_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let mut todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+
+        self.trash(&mut tx, user_id, &mut todo).await?;
+
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn complete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.bulk_update_list(
+            user_id,
+            list_id,
+            "completed_at IS NULL",
+            BulkAction::Complete,
+        )
+        .await
+    }
+
+    async fn uncomplete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.bulk_update_list(
+            user_id,
+            list_id,
+            "completed_at IS NOT NULL",
+            BulkAction::Uncomplete,
+        )
+        .await
+    }
+
+    async fn delete_completed(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.bulk_update_list(
+            user_id,
+            list_id,
+            "completed_at IS NOT NULL",
+            BulkAction::Delete,
+        )
+        .await
+    }
+
+    async fn bulk_update(
+        &self,
+        user_id: i64,
+        ids: Vec<i64>,
+        action: BulkAction,
+    ) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        let ids = self.apply_bulk(&mut tx, user_id, &ids, action).await?;
+        tx.commit().await?;
+        self.get_todos_by_id(&ids).await
+    }
+
+    async fn import(
+        &self,
+        user_id: i64,
+        list_id: i64,
+        todos: Vec<ImportedTodo>,
+    ) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        query("SELECT id FROM lists WHERE id = $1 AND user_id = $2")
+            .bind(list_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let now = self.now();
+        let mut imported: Vec<Todo> = vec![];
+        for todo in todos {
+            let (list_id, parent_id) = match (todo.parent, &todo.list) {
+                (Some(parent), _) => {
+                    let parent = imported.get(parent).ok_or_else(|| {
+                        TodoError::Validation(
+                            "subtasks have to come after the todo they're \
+                            under"
+                                .to_string(),
+                        )
+                    })?;
+                    (parent.list_id, Some(parent.id))
+                }
+                (None, Some(name)) => {
+                    (Self::list_named(&mut tx, user_id, name).await?, None)
+                }
+                (None, None) => (list_id, None),
+            };
+            let (completed_at, created_at, updated_at) = todo.timestamps(now);
+            let mut new_todo = query_as::<_, Todo>(
+                "INSERT INTO todos
+                (list_id, parent_id, description, completed_at, created_at,
+                    updated_at, due_at, priority, recurrence, position)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, (
+                    SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                    WHERE list_id = $1
+                ))
+                RETURNING *",
+            )
+            .bind(list_id)
+            .bind(parent_id)
+            .bind(&todo.description)
+            .bind(completed_at)
+            .bind(created_at)
+            .bind(updated_at)
+            .bind(todo.due_at)
+            .bind(todo.priority)
+            .bind(&todo.recurrence)
+            .fetch_one(&mut *tx)
+            .await?;
+            let mut tags = todo.tags;
+            tags.sort();
+            tags.dedup();
+            for tag in tags.iter() {
+                Self::insert_tag(&mut tx, user_id, new_todo.id, tag).await?;
+            }
+            new_todo.tags = tags;
+            self.record_event(
+                &mut tx,
+                user_id,
+                new_todo.id,
+                TodoEventKind::Created,
+                Some(&new_todo.description),
+            )
+            .await?;
+            imported.push(new_todo);
+        }
+
+        tx.commit().await?;
+        Ok(imported)
+    }
+
+    async fn get_trash(&self, user_id: i64) -> Result<Vec<Todo>> {
+        let mut todos = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            LEFT JOIN todos AS parent ON parent.id = todos.parent_id
+            WHERE lists.user_id = $1 AND todos.deleted_at IS NOT NULL
+            AND parent.deleted_at IS NULL
+            ORDER BY todos.deleted_at DESC, todos.id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        self.load_tags(&mut todos).await?;
+        Ok(todos)
+    }
+
+    async fn restore_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+
+        let todo = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = $1 AND lists.user_id = $2
+            AND todos.deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(parent_id) = todo.parent_id {
+            let parent_trashed: bool = query_scalar(
+                "SELECT deleted_at IS NOT NULL FROM todos WHERE id = $1",
+            )
+            .bind(parent_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if parent_trashed {
+                return Err(TodoError::Conflict(
+                    "restore the todo this is a subtask of first".to_string(),
+                ));
+            }
+        }
+        let restored: Vec<i64> = query_scalar(
+            "UPDATE todos SET deleted_at = NULL
+            WHERE deleted_at = $1 AND id IN (
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT $2::BIGINT
+                    UNION
+                    SELECT todos.id FROM todos
+                    JOIN subtree ON todos.parent_id = subtree.id
+                )
+                SELECT id FROM subtree
+            )
+            RETURNING id",
+        )
+        .bind(todo.deleted_at)
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for restored in restored {
+            self.record_event(
+                &mut tx,
+                user_id,
+                restored,
+                TodoEventKind::Restored,
+                None,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        self.get_todo(user_id, id).await
+    }
+
+    async fn purge_todo(&self, user_id: i64, id: i64) -> Result<()> {
+        // subtasks are deleted along with it by the foreign key
+        let rows_affected = query(
+            "DELETE FROM todos
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = $2)",
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if rows_affected == 0 {
+            return Err(TodoError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn empty_trash(&self, user_id: i64) -> Result<u64> {
+        const TRASHED: &str = "FROM todos
+            WHERE deleted_at IS NOT NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = $1)";
+        // count first, since rows removed by the cascade from a trashed
+        // parent don't show up in `rows_affected`
+        let mut tx = self.pool.begin().await?;
+        let purged: i64 = query_scalar(&format!("SELECT COUNT(*) {}", TRASHED))
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        query(&format!("DELETE {}", TRASHED))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(purged as u64)
+    }
+
+    async fn purge_expired_trash(&self, retention: Duration) -> Result<u64> {
+        let cutoff = self.now() - retention.as_millis() as i64;
+        let mut tx = self.pool.begin().await?;
+        let purged: i64 =
+            query_scalar("SELECT COUNT(*) FROM todos WHERE deleted_at < $1")
+                .bind(cutoff)
+                .fetch_one(&mut *tx)
+                .await?;
+        query("DELETE FROM todos WHERE deleted_at < $1")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(purged as u64)
+    }
+
+    async fn history(&self, user_id: i64, id: i64) -> Result<Vec<TodoEvent>> {
+        // make sure the todo is theirs first, so that someone else's looks
+        // missing rather than empty
+        query(
+            "SELECT todos.id FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = $1 AND lists.user_id = $2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        let events = query_as::<_, TodoEvent>(
+            "SELECT todo_events.id, todo_events.todo_id, users.username,
+                todo_events.kind, todo_events.detail, todo_events.created_at
+            FROM todo_events
+            LEFT JOIN users ON users.id = todo_events.user_id
+            WHERE todo_events.todo_id = $1
+            ORDER BY todo_events.created_at, todo_events.id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events)
+    }
+
+    async fn search(
+        &self,
+        user_id: i64,
+        text: String,
+    ) -> Result<Vec<SearchHit>> {
+        let ts_query = ts_query(&text);
+        if ts_query.is_empty() {
+            return Ok(vec![]);
+        }
+        // ranks are normalized by length so that, like bm25, shorter
+        // descriptions win when they match as well
+        let rows = query_as::<_, SearchRow>(
+            "SELECT todos.*, lists.name AS list_name,
+            ts_headline('todo_search', todos.description, search.query, $4)
+                AS snippet
+            FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            CROSS JOIN to_tsquery('todo_search', $1) AS search (query)
+            WHERE to_tsvector('todo_search', todos.description) @@ search.query
+            AND lists.user_id = $2 AND todos.deleted_at IS NULL
+            ORDER BY ts_rank(
+                to_tsvector('todo_search', todos.description), search.query, 1
+            ) DESC, todos.id
+            LIMIT $3",
+        )
+        .bind(ts_query)
+        .bind(user_id)
+        .bind(SEARCH_LIMIT)
+        .bind(headline_options())
+        .fetch_all(&self.pool)
+        .await?;
+        let mut todos =
+            rows.iter().map(|row| row.todo.clone()).collect::<Vec<_>>();
+        self.load_details(&mut todos).await?;
+        Ok(rows
+            .into_iter()
+            .zip(todos)
+            .map(|(row, todo)| SearchHit {
+                snippet: parse_snippet(&mark_truncation(
+                    &row.snippet,
+                    &todo.description,
+                )),
+                todo,
+                list_name: row.list_name,
+            })
+            .collect())
+    }
+}
+
+impl UserDao for TodoPostgresDao {
+    async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> Result<User> {
+        let mut tx = self.pool.begin().await?;
+
+        let id = query_scalar(
+            "INSERT INTO users (username, password_hash) VALUES ($1, $2)
+            RETURNING id",
+        )
+        .bind(&username)
+        .bind(&password_hash)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match TodoError::from(e) {
+            TodoError::Conflict(_) => TodoError::Conflict(
+                "that username is already taken".to_string(),
+            ),
+            e => e,
+        })?;
+
+        query("INSERT INTO lists (user_id, name) VALUES ($1, 'Todos')")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(User { id, username })
+    }
+
+    async fn get_credentials(&self, username: String) -> Result<Credentials> {
+        // usernames don't care about case, same as on SQLite
+        let credentials = query_as::<_, Credentials>(
+            "SELECT id, username, password_hash FROM users
+            WHERE lower(username) = lower($1)",
+        )
+        .bind(username)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(credentials)
+    }
+
+    async fn create_session(&self, token: String, user_id: i64) -> Result<()> {
+        let expires_at = self.now() + SESSION_TTL.as_millis() as i64;
+        query("INSERT INTO sessions (id, user_id, expires_at) VALUES ($1, $2, $3)")
+            .bind(token)
+            .bind(user_id)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_session_user(&self, token: String) -> Result<User> {
+        let user = query_as::<_, User>(
+            "SELECT users.id, users.username FROM sessions
+            JOIN users ON users.id = sessions.user_id
+            WHERE sessions.id = $1 AND sessions.expires_at > $2",
+        )
+        .bind(token)
+        .bind(self.now())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn delete_session(&self, token: String) -> Result<()> {
+        query("DELETE FROM sessions WHERE id = $1")
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn claim_unowned_lists(&self, user_id: i64) -> Result<u64> {
+        let claimed =
+            query("UPDATE lists SET user_id = $1 WHERE user_id IS NULL")
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        Ok(claimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::FakeClock,
+        dao_tests::{TestDao, dao_tests},
+        db::create_postgres_pool,
+    };
+
+    #[test]
+    fn test_ts_query() {
+        assert_eq!(ts_query(" buy  mi "), "'buy':* & 'mi':*");
+        assert_eq!(ts_query("it's a\\b"), "'it''s':* & 'a\\\\b':*");
+        assert_eq!(ts_query("  "), "");
+    }
+
+    #[test]
+    fn test_mark_truncation() {
+        assert_eq!(
+            mark_truncation("Buy \u{2}milk\u{3}", "Buy milk"),
+            "Buy \u{2}milk\u{3}"
+        );
+        assert_eq!(
+            mark_truncation("the \u{2}milk\u{3} run", "Do the milk run today"),
+            "…the \u{2}milk\u{3} run…"
+        );
+    }
+
+    impl TestDao for TodoPostgresDao {
+        async fn empty(clock: FakeClock) -> Self {
+            let url = std::env::var("TEST_POSTGRES_URL")
+                .expect("TEST_POSTGRES_URL should point at a database");
+            // every test gets a schema of its own, so that it starts from
+            // scratch and can run alongside the others
+            let schema = format!("test_{:016x}", rand::random::<u64>());
+            let admin = PgPool::connect(&url)
+                .await
+                .expect("failed to connect to Postgres");
+            query(&format!("CREATE SCHEMA {}", schema))
+                .execute(&admin)
+                .await
+                .expect("failed to create schema");
+            admin.close().await;
+            let separator = if url.contains('?') { '&' } else { '?' };
+            let pool = create_postgres_pool(&format!(
+                "{}{}options[search_path]={}",
+                url, separator, schema
+            ))
+            .await
+            .expect("failed to create pool");
+            TodoPostgresDao::new(pool).with_clock(clock)
+        }
+
+        async fn add_unowned_list(&self, name: &str) -> i64 {
+            query_scalar("INSERT INTO lists (name) VALUES ($1) RETURNING id")
+                .bind(name)
+                .fetch_one(&self.pool)
+                .await
+                .expect("failed to add list")
+        }
+    }
+
+    dao_tests!(
+        TodoPostgresDao,
+        #[ignore = "needs a Postgres database at TEST_POSTGRES_URL"]
+    );
+}
@@ -0,0 +1,424 @@
+use crate::errors::{Result, TodoError};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Decode, Encode, Postgres, Sqlite, Type,
+    encode::IsNull,
+    error::BoxDynError,
+    postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef},
+    sqlite::{SqliteArgumentValue, SqliteTypeInfo, SqliteValueRef},
+};
+use std::{fmt, str::FromStr};
+
+/// Longest gap we'll accept between occurrences, in whatever unit the
+/// frequency counts in.
+pub const MAX_INTERVAL: u32 = 365;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// How often a todo comes back once it's done. This is the subset of
+/// iCalendar RRULEs we understand, e.g. `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`,
+/// and it is stored and sent over the API in that form.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(into = "String", try_from = "String")]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Every how many days, weeks or months.
+    pub interval: u32,
+    /// The days a weekly todo falls on, Monday first; empty means the same
+    /// day of the week it was last due.
+    pub weekdays: Vec<Weekday>,
+}
+
+impl Recurrence {
+    pub fn new(frequency: Frequency) -> Self {
+        Self {
+            frequency,
+            interval: 1,
+            weekdays: vec![],
+        }
+    }
+
+    /// The first day the todo is due again after `date`.
+    pub fn next_after(&self, date: NaiveDate) -> NaiveDate {
+        let interval = self.interval.max(1);
+        match self.frequency {
+            Frequency::Daily => date + Days::new(interval.into()),
+            // months that are too short for the day land on their last day
+            Frequency::Monthly => date
+                .checked_add_months(Months::new(interval))
+                .unwrap_or(NaiveDate::MAX),
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                date + Days::new(7 * u64::from(interval))
+            }
+            Frequency::Weekly => {
+                let today = date.weekday().num_days_from_monday();
+                // a later day this week, or else the first day of the next
+                // week it recurs in
+                match self
+                    .weekdays
+                    .iter()
+                    .map(|day| day.num_days_from_monday())
+                    .find(|day| *day > today)
+                {
+                    Some(day) => date + Days::new((day - today).into()),
+                    None => {
+                        let first = self.weekdays[0].num_days_from_monday();
+                        let week_start = date - Days::new(today.into());
+                        week_start
+                            + Days::new(
+                                7 * u64::from(interval) + u64::from(first),
+                            )
+                    }
+                }
+            }
+        }
+    }
+
+    /// When the todo is next due after one due on `due` was done on `today`;
+    /// todos without a due date count from the day they were done.
+    /// Occurrences that would already be in the past are skipped, so a chore
+    /// that's done late doesn't come back overdue.
+    pub fn next_due(
+        &self,
+        due: Option<NaiveDate>,
+        today: NaiveDate,
+    ) -> NaiveDate {
+        let mut next = self.next_after(due.unwrap_or(today));
+        while next <= today {
+            next = self.next_after(next);
+        }
+        next
+    }
+
+    /// A short description for people, e.g. "every 2 weeks on Mon, Thu".
+    pub fn describe(&self) -> String {
+        let every = match (self.frequency, self.interval) {
+            (Frequency::Daily, 1) => "daily".to_string(),
+            (Frequency::Weekly, 1) => "weekly".to_string(),
+            (Frequency::Monthly, 1) => "monthly".to_string(),
+            (Frequency::Daily, n) => format!("every {} days", n),
+            (Frequency::Weekly, n) => format!("every {} weeks", n),
+            (Frequency::Monthly, n) => format!("every {} months", n),
+        };
+        if self.weekdays.is_empty() {
+            return every;
+        }
+        let days = self
+            .weekdays
+            .iter()
+            .map(|day| day.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{} on {}", every, days)
+    }
+}
+
+const WEEKDAY_CODES: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "MO"),
+    (Weekday::Tue, "TU"),
+    (Weekday::Wed, "WE"),
+    (Weekday::Thu, "TH"),
+    (Weekday::Fri, "FR"),
+    (Weekday::Sat, "SA"),
+    (Weekday::Sun, "SU"),
+];
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    WEEKDAY_CODES[weekday.num_days_from_monday() as usize].1
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.weekdays.is_empty() {
+            let days = self
+                .weekdays
+                .iter()
+                .map(|day| weekday_code(*day))
+                .collect::<Vec<_>>()
+                .join(",");
+            write!(f, ";BYDAY={}", days)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: &str) -> TodoError {
+    TodoError::Validation(format!("recurrence {}", message))
+}
+
+impl FromStr for Recurrence {
+    type Err = TodoError;
+
+    fn from_str(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut weekdays = vec![];
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid("must look like FREQ=WEEKLY"))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency =
+                        Some(match value.to_ascii_uppercase().as_str() {
+                            "DAILY" => Frequency::Daily,
+                            "WEEKLY" => Frequency::Weekly,
+                            "MONTHLY" => Frequency::Monthly,
+                            _ => {
+                                return Err(invalid(
+                                    "FREQ must be DAILY, WEEKLY or MONTHLY",
+                                ));
+                            }
+                        })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (1..=MAX_INTERVAL).contains(n))
+                        .ok_or_else(|| {
+                            TodoError::Validation(format!(
+                                "recurrence INTERVAL must be between 1 and {}",
+                                MAX_INTERVAL
+                            ))
+                        })?;
+                }
+                "BYDAY" => {
+                    for code in value.split(',') {
+                        let day = WEEKDAY_CODES
+                            .iter()
+                            .find(|(_, c)| c.eq_ignore_ascii_case(code.trim()))
+                            .map(|(day, _)| *day)
+                            .ok_or_else(|| {
+                                invalid("BYDAY must be days like MO,WE,FR")
+                            })?;
+                        weekdays.push(day);
+                    }
+                }
+                _ => {
+                    return Err(TodoError::Validation(format!(
+                        "recurrence rule part {} isn't supported",
+                        name
+                    )));
+                }
+            }
+        }
+        let frequency = frequency.ok_or_else(|| invalid("needs a FREQ"))?;
+        if !weekdays.is_empty() && frequency != Frequency::Weekly {
+            return Err(invalid("BYDAY only works with FREQ=WEEKLY"));
+        }
+        weekdays.sort_by_key(|day| day.num_days_from_monday());
+        weekdays.dedup();
+        Ok(Self {
+            frequency,
+            interval,
+            weekdays,
+        })
+    }
+}
+
+/// Parses a recurrence rule as sent by a form; blank means it doesn't recur.
+pub fn parse_recurrence(rule: &str) -> Result<Option<Recurrence>> {
+    if rule.trim().is_empty() {
+        return Ok(None);
+    }
+    rule.parse().map(Some)
+}
+
+impl From<Recurrence> for String {
+    fn from(recurrence: Recurrence) -> Self {
+        recurrence.to_string()
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = TodoError;
+
+    fn try_from(rule: String) -> Result<Self> {
+        rule.parse()
+    }
+}
+
+// stored as the rule's text
+impl Type<Sqlite> for Recurrence {
+    fn type_info() -> SqliteTypeInfo {
+        <String as Type<Sqlite>>::type_info()
+    }
+
+    fn compatible(ty: &SqliteTypeInfo) -> bool {
+        <String as Type<Sqlite>>::compatible(ty)
+    }
+}
+
+impl<'q> Encode<'q, Sqlite> for Recurrence {
+    fn encode_by_ref(
+        &self,
+        buf: &mut Vec<SqliteArgumentValue<'q>>,
+    ) -> std::result::Result<IsNull, BoxDynError> {
+        <String as Encode<'q, Sqlite>>::encode(self.to_string(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Sqlite> for Recurrence {
+    fn decode(
+        value: SqliteValueRef<'r>,
+    ) -> std::result::Result<Self, BoxDynError> {
+        let rule = <&str as Decode<'r, Sqlite>>::decode(value)?;
+        Ok(rule.parse()?)
+    }
+}
+
+impl Type<Postgres> for Recurrence {
+    fn type_info() -> PgTypeInfo {
+        <String as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <String as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, Postgres> for Recurrence {
+    fn encode_by_ref(
+        &self,
+        buf: &mut PgArgumentBuffer,
+    ) -> std::result::Result<IsNull, BoxDynError> {
+        <String as Encode<'_, Postgres>>::encode(self.to_string(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Recurrence {
+    fn decode(value: PgValueRef<'r>) -> std::result::Result<Self, BoxDynError> {
+        let rule = <&str as Decode<'r, Postgres>>::decode(value)?;
+        Ok(rule.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_recurrence() {
+        let rule: Recurrence = "RRULE:freq=weekly;INTERVAL=2;BYDAY=TH,MO,TH"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            rule,
+            Recurrence {
+                frequency: Frequency::Weekly,
+                interval: 2,
+                weekdays: vec![Weekday::Mon, Weekday::Thu],
+            }
+        );
+        assert_eq!(rule.to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH");
+        assert_eq!(
+            "FREQ=DAILY".parse::<Recurrence>().unwrap(),
+            Recurrence::new(Frequency::Daily)
+        );
+    }
+
+    #[test]
+    fn test_parse_recurrence_invalid() {
+        for rule in [
+            "",
+            "WEEKLY",
+            "FREQ=YEARLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=lots",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=MONTHLY;BYDAY=MO",
+            "FREQ=DAILY;COUNT=3",
+        ] {
+            assert!(
+                matches!(
+                    rule.parse::<Recurrence>(),
+                    Err(TodoError::Validation(_))
+                ),
+                "{}",
+                rule
+            );
+        }
+        assert_eq!(parse_recurrence("  ").unwrap(), None);
+    }
+
+    #[test]
+    fn test_next_after() {
+        let daily = Recurrence {
+            interval: 3,
+            ..Recurrence::new(Frequency::Daily)
+        };
+        assert_eq!(daily.next_after(date("2026-01-30")), date("2026-02-02"));
+
+        let weekly = Recurrence::new(Frequency::Weekly);
+        assert_eq!(weekly.next_after(date("2026-01-30")), date("2026-02-06"));
+
+        // 2026-01-28 is a Wednesday
+        let weekly_on = Recurrence {
+            interval: 2,
+            weekdays: vec![Weekday::Mon, Weekday::Thu],
+            ..Recurrence::new(Frequency::Weekly)
+        };
+        assert_eq!(
+            weekly_on.next_after(date("2026-01-28")),
+            date("2026-01-29")
+        );
+        assert_eq!(
+            weekly_on.next_after(date("2026-01-29")),
+            date("2026-02-09")
+        );
+
+        let monthly = Recurrence::new(Frequency::Monthly);
+        assert_eq!(monthly.next_after(date("2026-01-31")), date("2026-02-28"));
+    }
+
+    #[test]
+    fn test_next_due_skips_the_past() {
+        let daily = Recurrence::new(Frequency::Daily);
+        let today = date("2026-03-10");
+
+        assert_eq!(daily.next_due(None, today), date("2026-03-11"));
+        assert_eq!(
+            daily.next_due(Some(date("2026-03-01")), today),
+            date("2026-03-11")
+        );
+        assert_eq!(
+            daily.next_due(Some(date("2026-03-20")), today),
+            date("2026-03-21")
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(Recurrence::new(Frequency::Daily).describe(), "daily");
+        assert_eq!(
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH"
+                .parse::<Recurrence>()
+                .unwrap()
+                .describe(),
+            "every 2 weeks on Mon, Thu"
+        );
+    }
+}
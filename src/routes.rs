@@ -1,7 +1,9 @@
-use crate::{handlers, state::AppState, todos::TodoSqliteDao};
+use crate::{
+    auth, handlers, negotiate, state::AppState, todos::TodoDao, users::UserDao,
+};
 use axum::{
-    Router,
-    routing::{get, post, put},
+    Router, middleware,
+    routing::{delete, get, patch, post, put},
 };
 use tower_http::{
     services::ServeDir,
@@ -9,17 +11,107 @@ use tower_http::{
 };
 use tracing::Level;
 
-pub fn create_router(state: AppState<TodoSqliteDao>) -> Router {
+/// Builds the app on top of whatever storage `T` is, whether that's one of
+/// the backends `main` picks from the database url or one supplied by
+/// whoever's embedding the app.
+pub fn create_router<T>(state: AppState<T>) -> Router
+where
+    T: TodoDao + UserDao + Clone + Send + Sync + 'static,
+{
+    // everything in here needs a logged in user
+    let protected = Router::new()
+        .route("/", get(handlers::home::<T>))
+        .route("/lists/{id}", get(handlers::show_list::<T>))
+        .route("/trash", get(handlers::trash_page::<T>))
+        .route(
+            "/api/v1/lists",
+            get(handlers::list_lists::<T>).post(handlers::create_list::<T>),
+        )
+        .route(
+            "/api/v1/lists/{id}",
+            patch(handlers::rename_list::<T>)
+                .delete(handlers::delete_list::<T>),
+        )
+        .route(
+            "/api/v1/lists/{id}/complete-all",
+            post(handlers::complete_all::<T>),
+        )
+        .route(
+            "/api/v1/lists/{id}/uncomplete-all",
+            post(handlers::uncomplete_all::<T>),
+        )
+        .route(
+            "/api/v1/lists/{id}/completed",
+            delete(handlers::delete_completed::<T>),
+        )
+        .route(
+            "/api/v1/todos",
+            get(handlers::list_todos::<T>).post(handlers::add_todo::<T>),
+        )
+        .route("/api/v1/todos/{id}/toggle", put(handlers::toggle_todo::<T>))
+        .route("/api/v1/todos/bulk", post(handlers::bulk_update::<T>))
+        .route("/api/v1/todos/search", get(handlers::search_todos::<T>))
+        .route("/api/v1/todos/export", get(handlers::export_todos::<T>))
+        .route("/api/v1/todos/import", post(handlers::import_todos::<T>))
+        .route("/api/v1/tags", get(handlers::list_tags::<T>))
+        .route("/api/v1/todos/{id}/tags", post(handlers::tag_todo::<T>))
+        .route(
+            "/api/v1/todos/{id}/tags/{tag}",
+            delete(handlers::untag_todo::<T>),
+        )
+        .route(
+            "/api/v1/todos/{id}/subtasks",
+            get(handlers::list_subtasks::<T>).post(handlers::add_subtask::<T>),
+        )
+        .route("/api/v1/todos/{id}/position", put(handlers::move_todo::<T>))
+        .route(
+            "/api/v1/todos/{id}/recurrence",
+            put(handlers::set_recurrence::<T>),
+        )
+        .route(
+            "/api/v1/todos/{id}/priority",
+            put(handlers::set_priority::<T>),
+        )
+        .route(
+            "/api/v1/todos/{id}",
+            get(handlers::get_todo::<T>)
+                .patch(handlers::update_todo::<T>)
+                .delete(handlers::delete_todo::<T>),
+        )
+        .route(
+            "/api/v1/trash",
+            get(handlers::list_trash::<T>).delete(handlers::empty_trash::<T>),
+        )
+        .route("/api/v1/trash/{id}", delete(handlers::purge_todo::<T>))
+        .route(
+            "/api/v1/trash/{id}/restore",
+            post(handlers::restore_todo::<T>),
+        )
+        .route(
+            "/api/v1/todos/{id}/history",
+            get(handlers::todo_history::<T>),
+        )
+        .route("/api/v1/todos/{id}/edit", get(handlers::edit_todo::<T>))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_user::<T>,
+        ));
+
     // NOTE: state needs to be added _last_ to convert Router<AppState> -> Router<()>
     // see this page for details: https://docs.rs/axum/0.8.3/axum/routing/struct.Router.html#method.with_state
     Router::new()
         .nest_service("/public", ServeDir::new("public"))
-        .route("/", get(handlers::home::<TodoSqliteDao>))
-        .route("/api/v1/todos", post(handlers::add_todo::<TodoSqliteDao>))
         .route(
-            "/api/v1/todos/{id}/toggle",
-            put(handlers::toggle_todo::<TodoSqliteDao>),
+            "/signup",
+            get(handlers::signup_page).post(handlers::signup::<T>),
+        )
+        .route(
+            "/login",
+            get(handlers::login_page).post(handlers::login::<T>),
         )
+        .route("/logout", post(handlers::logout::<T>))
+        .merge(protected)
+        .layer(middleware::from_fn(negotiate::json_errors))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(
@@ -1,20 +1,62 @@
 use axum::extract::FromRef;
 
-use crate::todos::{TodoDao, TodoSqliteDao};
+use crate::todos::TodoDao;
 
 #[derive(Clone, Debug)]
 pub struct AppState<T: TodoDao> {
     pub dao: T,
+    pub settings: Settings,
 }
 
 impl<T: TodoDao> AppState<T> {
     pub fn new(dao: T) -> Self {
-        Self { dao }
+        Self {
+            dao,
+            settings: Settings::default(),
+        }
+    }
+
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+/// Runtime knobs that handlers need but the DAO doesn't care about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub max_description_length: usize,
+    /// Days a deleted todo stays in the trash before it's purged.
+    pub trash_retention_days: u64,
+    /// Whether the session cookie is kept to https. Only worth turning off
+    /// to try the app out over plain http.
+    pub secure_cookies: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_description_length: 500,
+            trash_retention_days: 30,
+            secure_cookies: true,
+        }
+    }
+}
+
+impl<T: TodoDao> FromRef<AppState<T>> for Settings {
+    fn from_ref(app_state: &AppState<T>) -> Self {
+        app_state.settings.clone()
     }
 }
 
-impl FromRef<AppState<TodoSqliteDao>> for TodoSqliteDao {
-    fn from_ref(app_state: &AppState<TodoSqliteDao>) -> Self {
-        app_state.dao.clone()
+/// The DAO as handlers take it out of the state. Wrapping it lets one blanket
+/// `FromRef` impl cover any backend, which the orphan rules don't allow for
+/// the bare type.
+#[derive(Clone, Debug)]
+pub struct Dao<T>(pub T);
+
+impl<T: TodoDao + Clone> FromRef<AppState<T>> for Dao<T> {
+    fn from_ref(app_state: &AppState<T>) -> Self {
+        Dao(app_state.dao.clone())
     }
 }
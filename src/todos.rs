@@ -1,15 +1,65 @@
-use anyhow::Result;
-use sqlx::{SqlitePool, query, query_as};
-use std::time::{SystemTime, UNIX_EPOCH};
+use crate::{
+    clock::{Clock, SystemClock},
+    errors::{Result, TodoError},
+    recurrence::Recurrence,
+};
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool, query, query_as, query_scalar};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 #[cfg(test)]
 use mockall::automock;
 
-#[derive(sqlx::FromRow, PartialEq, Eq, Clone, Debug)]
+/// The list created by the migration that introduced lists; any todos that
+/// predate lists live here. A fresh database drops it while it's still empty,
+/// so the first user's list ends up with this id instead.
+pub const DEFAULT_LIST_ID: i64 = 1;
+
+#[derive(sqlx::FromRow, Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct List {
+    pub id: i64,
+    pub name: String,
+}
+
+impl List {
+    pub fn new<S>(id: i64, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id,
+            name: name.into(),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize, PartialEq, Eq, Clone, Debug)]
 pub struct Todo {
     pub id: i64,
+    pub list_id: i64,
     pub description: String,
     pub completed_at: Option<i64>,
+    /// When the todo was added, in millis.
+    pub created_at: i64,
+    /// When the todo itself last changed, in millis. Moving it around or in
+    /// and out of the trash doesn't count.
+    pub updated_at: i64,
+    /// Midnight UTC on the day the todo is due, in millis.
+    pub due_at: Option<i64>,
+    pub priority: Priority,
+    /// Filled in separately from the `todo_tags` table, sorted by name.
+    #[sqlx(skip)]
+    pub tags: Vec<String>,
+    /// The todo this is a subtask of, if any.
+    pub parent_id: Option<i64>,
+    /// How often the todo comes back after it's done, if at all.
+    pub recurrence: Option<Recurrence>,
+    /// When the todo was moved to the trash, in millis.
+    pub deleted_at: Option<i64>,
+    /// Filled in separately, in the order they were dragged into.
+    #[sqlx(skip)]
+    pub subtasks: Vec<Todo>,
 }
 
 impl Todo {
@@ -19,168 +69,2336 @@ impl Todo {
     {
         Self {
             id,
+            list_id: DEFAULT_LIST_ID,
             description: description.into(),
             completed_at: None,
+            created_at: 0,
+            updated_at: 0,
+            due_at: None,
+            priority: Priority::default(),
+            tags: vec![],
+            parent_id: None,
+            recurrence: None,
+            deleted_at: None,
+            subtasks: vec![],
         }
     }
 
     pub fn is_completed(&self) -> bool {
         self.completed_at.is_some()
     }
+
+    pub fn due_date(&self) -> Option<NaiveDate> {
+        self.due_at
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|due_at| due_at.date_naive())
+    }
+
+    /// How many of the todo's own subtasks are done, out of how many, or
+    /// `None` if it doesn't have any.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        if self.subtasks.is_empty() {
+            return None;
+        }
+        let done = self.subtasks.iter().filter(|t| t.is_completed()).count();
+        Some((done, self.subtasks.len()))
+    }
+
+    /// Whether the todo is still open after the day it was due.
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.is_completed() && self.due_date().is_some_and(|due| due < today)
+    }
+}
+
+/// How urgently a todo needs doing. Stored as a number so that the database
+/// can sort by it.
+#[derive(
+    sqlx::Type,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+)]
+#[repr(i64)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low = 0,
+    #[default]
+    Normal = 1,
+    High = 2,
+    Urgent = 3,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 4] = [
+        Priority::Low,
+        Priority::Normal,
+        Priority::High,
+        Priority::Urgent,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+}
+
+/// Everything needed to add a todo to a list.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct NewTodo {
+    pub list_id: i64,
+    pub description: String,
+    pub due_at: Option<i64>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+}
+
+impl NewTodo {
+    pub fn new<S>(list_id: i64, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            list_id,
+            description: description.into(),
+            due_at: None,
+            priority: Priority::default(),
+            tags: vec![],
+            recurrence: None,
+        }
+    }
+}
+
+/// A todo read from a file, to be added along with the rest of the file.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ImportedTodo {
+    /// The name of the list it goes in, if the file said. Subtasks always go
+    /// in their parent's list.
+    pub list: Option<String>,
+    /// Where its parent is among the todos being imported, if it's a
+    /// subtask. Parents have to come before their subtasks.
+    pub parent: Option<usize>,
+    pub description: String,
+    pub completed: bool,
+    /// When it was completed, if the file said. Ignored unless `completed`.
+    pub completed_at: Option<i64>,
+    pub created_at: Option<i64>,
+    pub updated_at: Option<i64>,
+    pub due_at: Option<i64>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+}
+
+impl ImportedTodo {
+    pub fn new<S>(description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            list: None,
+            parent: None,
+            description: description.into(),
+            completed: false,
+            completed_at: None,
+            created_at: None,
+            updated_at: None,
+            due_at: None,
+            priority: Priority::default(),
+            tags: vec![],
+            recurrence: None,
+        }
+    }
+
+    /// When the todo was completed, added and last changed, filling in
+    /// whatever the file didn't say from `now` and the times it did.
+    pub(crate) fn timestamps(&self, now: i64) -> (Option<i64>, i64, i64) {
+        let completed_at =
+            self.completed.then(|| self.completed_at.unwrap_or(now));
+        let created_at = self.created_at.unwrap_or(now);
+        let updated_at = self.updated_at.unwrap_or_else(|| {
+            completed_at.unwrap_or(created_at).max(created_at)
+        });
+        (completed_at, created_at, updated_at)
+    }
+}
+
+/// How a list's todos are sorted.
+#[derive(
+    Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug, Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoOrder {
+    /// The order they were dragged into; new todos go at the bottom.
+    #[default]
+    Manual,
+    /// The order they were added in.
+    Added,
+    /// Most recently changed first.
+    Updated,
+    /// Soonest due first; todos without a due date go last.
+    DueDate,
+    /// Most urgent first.
+    Priority,
+}
+
+impl TodoOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TodoOrder::Manual => "manual",
+            TodoOrder::Added => "added",
+            TodoOrder::Updated => "updated",
+            TodoOrder::DueDate => "due_date",
+            TodoOrder::Priority => "priority",
+        }
+    }
+
+    pub(crate) fn order_by(self) -> &'static str {
+        match self {
+            TodoOrder::Manual => "todos.position, todos.id",
+            TodoOrder::Added => "todos.created_at, todos.id",
+            TodoOrder::Updated => "todos.updated_at DESC, todos.id",
+            TodoOrder::DueDate => {
+                "todos.due_at IS NULL, todos.due_at, todos.id"
+            }
+            TodoOrder::Priority => "todos.priority DESC, todos.id",
+        }
+    }
+}
+
+/// Which todos to show based on whether they're done.
+#[derive(
+    Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug, Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoFilter {
+    #[default]
+    All,
+    /// Still to do.
+    Active,
+    Completed,
+}
+
+impl TodoFilter {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TodoFilter::All => "all",
+            TodoFilter::Active => "active",
+            TodoFilter::Completed => "completed",
+        }
+    }
+
+    pub(crate) fn condition(self) -> &'static str {
+        match self {
+            TodoFilter::All => "TRUE",
+            TodoFilter::Active => "todos.completed_at IS NULL",
+            TodoFilter::Completed => "todos.completed_at IS NOT NULL",
+        }
+    }
+}
+
+/// Which of a list's todos to fetch, and in what order.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TodoQuery {
+    pub list_id: i64,
+    pub filter: TodoFilter,
+    pub order: TodoOrder,
+    /// Only todos with this tag.
+    pub tag: Option<String>,
+}
+
+impl TodoQuery {
+    /// Every todo in the list, in the order the user put them in.
+    pub fn new(list_id: i64) -> Self {
+        Self {
+            list_id,
+            filter: TodoFilter::default(),
+            order: TodoOrder::default(),
+            tag: None,
+        }
+    }
+}
+
+/// Something to do to several todos at once.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    Complete,
+    Uncomplete,
+    /// Move to the trash.
+    Delete,
+}
+
+/// The kinds of change a todo's history records.
+#[derive(sqlx::Type, Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[sqlx(type_name = "todo_event_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TodoEventKind {
+    Created,
+    Renamed,
+    Completed,
+    Reopened,
+    Prioritized,
+    Repeated,
+    Tagged,
+    Untagged,
+    Moved,
+    Trashed,
+    Restored,
+}
+
+/// One change in a todo's history.
+#[derive(sqlx::FromRow, Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct TodoEvent {
+    pub id: i64,
+    pub todo_id: i64,
+    /// Who made the change, if their account is still around.
+    pub username: Option<String>,
+    pub kind: TodoEventKind,
+    /// The description, priority, recurrence or tag the todo was given, for
+    /// the kinds of change that have one.
+    pub detail: Option<String>,
+    /// In millis.
+    pub created_at: i64,
+}
+
+/// Hangs each todo's subtasks off it, recursively, picking them out of a flat
+/// list of everything underneath.
+pub(crate) fn attach_subtasks(todos: &mut [Todo], descendants: &[Todo]) {
+    for todo in todos.iter_mut() {
+        todo.subtasks = descendants
+            .iter()
+            .filter(|d| d.parent_id == Some(todo.id))
+            .cloned()
+            .collect();
+        attach_subtasks(&mut todo.subtasks, descendants);
+    }
+}
+
+/// Which todo the next occurrence of a recurring `todo`, completed along with
+/// `root`, goes under: the next occurrence of the nearest todo it was a
+/// subtask of, if that repeats too, or else wherever `root` is, so nothing
+/// open ends up underneath a completed todo. `completed` is everything that
+/// got completed with `root`, and `occurrences` maps the ones rescheduled so
+/// far to their next occurrence.
+pub(crate) fn next_occurrence_parent(
+    todo: &Todo,
+    root: &Todo,
+    completed: &[Todo],
+    occurrences: &HashMap<i64, i64>,
+) -> Option<i64> {
+    let mut current = todo;
+    while current.id != root.id {
+        let Some(parent) = current
+            .parent_id
+            .and_then(|id| completed.iter().find(|t| t.id == id))
+        else {
+            break;
+        };
+        if let Some(&next_id) = occurrences.get(&parent.id) {
+            return Some(next_id);
+        }
+        current = parent;
+    }
+    root.parent_id
+}
+
+/// Picks a position strictly between two others, where `None` means there's
+/// nothing on that side. Returns `None` when the two are too close together
+/// to fit anything in between, at which point the list needs renumbering.
+pub(crate) fn position_between(
+    lower: Option<f64>,
+    upper: Option<f64>,
+) -> Option<f64> {
+    match (lower, upper) {
+        (Some(lower), Some(upper)) => {
+            let middle = lower + (upper - lower) / 2.0;
+            (lower < middle && middle < upper).then_some(middle)
+        }
+        (Some(lower), None) => Some(lower + 1.0),
+        (None, Some(upper)) => Some(upper - 1.0),
+        (None, None) => Some(1.0),
+    }
+}
+
+/// Normalizes a user-supplied description, returning the trimmed text if it
+/// is acceptable to store.
+pub fn validate_description(
+    description: &str,
+    max_length: usize,
+) -> Result<String> {
+    validate_text("description", description, max_length)
+}
+
+/// Most results a search returns; it's for finding things, not browsing.
+pub const SEARCH_LIMIT: i64 = 20;
+
+// Marks the matched words in search snippets. Descriptions can't contain
+// control characters, so these never clash with what users typed.
+pub(crate) const HIGHLIGHT_START: char = '\u{2}';
+pub(crate) const HIGHLIGHT_END: char = '\u{3}';
+
+/// A run of text in a search snippet, highlighted if it matched the search.
+#[derive(Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+impl SnippetPart {
+    pub fn new<S>(text: S, highlighted: bool) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            text: text.into(),
+            highlighted,
+        }
+    }
+}
+
+/// A todo that matched a search, with the matching part of its description.
+#[derive(Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct SearchHit {
+    pub todo: Todo,
+    pub list_name: String,
+    pub snippet: Vec<SnippetPart>,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct SearchRow {
+    #[sqlx(flatten)]
+    pub todo: Todo,
+    pub list_name: String,
+    pub snippet: String,
+}
+
+pub(crate) fn parse_snippet(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut highlighted = false;
+    for c in snippet.chars() {
+        if c == HIGHLIGHT_START || c == HIGHLIGHT_END {
+            if !text.is_empty() {
+                parts.push(SnippetPart::new(
+                    std::mem::take(&mut text),
+                    highlighted,
+                ));
+            }
+            highlighted = c == HIGHLIGHT_START;
+        } else {
+            text.push(c);
+        }
+    }
+    if !text.is_empty() {
+        parts.push(SnippetPart::new(text, highlighted));
+    }
+    parts
+}
+
+/// Turns whatever the user typed into an FTS5 query matching every word as a
+/// prefix. Quoting the words stops FTS5 from treating things like `AND` or `*`
+/// as syntax.
+fn fts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses a due date as sent by a date input (`YYYY-MM-DD`) into millis at
+/// midnight UTC. A blank date means the todo isn't due at all.
+pub fn parse_due_date(due_date: &str) -> Result<Option<i64>> {
+    let due_date = due_date.trim();
+    if due_date.is_empty() {
+        return Ok(None);
+    }
+    let date =
+        NaiveDate::parse_from_str(due_date, "%Y-%m-%d").map_err(|_| {
+            TodoError::Validation(
+                "due date must be a date like 2025-12-31".to_string(),
+            )
+        })?;
+    Ok(Some(
+        date.and_time(NaiveTime::MIN).and_utc().timestamp_millis(),
+    ))
+}
+
+/// Longest tag we'll accept, not counting the `#`.
+pub const MAX_TAG_LENGTH: usize = 32;
+
+/// Normalizes a tag to how it's stored: lowercase, without the leading `#`.
+pub fn validate_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix('#').unwrap_or(tag).to_lowercase();
+    if tag.is_empty() || tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(TodoError::Validation(format!(
+            "tags must be between 1 and {} characters",
+            MAX_TAG_LENGTH
+        )));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(TodoError::Validation(
+            "tags may only contain letters, numbers, '_' and '-'".to_string(),
+        ));
+    }
+    Ok(tag)
+}
+
+/// Pulls `#tag` words out of a description, returning what's left of the
+/// description along with the tags it mentioned.
+pub fn extract_tags(description: &str) -> (String, Vec<String>) {
+    let mut words = vec![];
+    let mut tags: Vec<String> = vec![];
+    for word in description.split_whitespace() {
+        match word.strip_prefix('#').map(validate_tag) {
+            Some(Ok(tag)) => {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            _ => words.push(word),
+        }
+    }
+    if tags.is_empty() {
+        // leave descriptions without tags exactly as they were
+        return (description.to_string(), tags);
+    }
+    (words.join(" "), tags)
+}
+
+/// Longest name a list can have; names show up in tabs so keep them short.
+pub const MAX_LIST_NAME_LENGTH: usize = 100;
+
+/// Normalizes a user-supplied list name, returning the trimmed text if it is
+/// acceptable to store.
+pub fn validate_list_name(name: &str) -> Result<String> {
+    validate_text("name", name, MAX_LIST_NAME_LENGTH)
+}
+
+fn validate_text(field: &str, text: &str, max_length: usize) -> Result<String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(TodoError::Validation(format!(
+            "{} must not be empty",
+            field
+        )));
+    }
+    if text.chars().count() > max_length {
+        return Err(TodoError::Validation(format!(
+            "{} must be at most {} characters",
+            field, max_length
+        )));
+    }
+    if text.chars().any(char::is_control) {
+        return Err(TodoError::Validation(format!(
+            "{} must not contain control characters",
+            field
+        )));
+    }
+    Ok(text.to_string())
 }
 
+/// Storage for lists and todos. Every method is scoped to the user making the
+/// request; anything they don't own behaves as if it doesn't exist.
 #[cfg_attr(test, automock)]
 pub trait TodoDao {
-    fn get_all_todos(&self) -> impl Future<Output = Result<Vec<Todo>>>;
+    fn get_lists(
+        &self,
+        user_id: i64,
+    ) -> impl Future<Output = Result<Vec<List>>> + Send;
+    fn get_list(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<List>> + Send;
+    fn create_list(
+        &self,
+        user_id: i64,
+        name: String,
+    ) -> impl Future<Output = Result<List>> + Send;
+    fn rename_list(
+        &self,
+        user_id: i64,
+        id: i64,
+        name: String,
+    ) -> impl Future<Output = Result<List>> + Send;
+    fn delete_list(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// The list's top level todos, with their subtasks nested inside.
+    fn get_todos(
+        &self,
+        user_id: i64,
+        query: TodoQuery,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    fn get_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<Todo>> + Send;
     fn add_todo(
         &self,
+        user_id: i64,
+        new_todo: NewTodo,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    fn update_description(
+        &self,
+        user_id: i64,
+        id: i64,
         description: String,
-    ) -> impl Future<Output = Result<Todo>>;
-    fn toggle_todo(&self, id: i64) -> impl Future<Output = Result<Todo>>;
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    fn set_priority(
+        &self,
+        user_id: i64,
+        id: i64,
+        priority: Priority,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    fn set_recurrence(
+        &self,
+        user_id: i64,
+        id: i64,
+        recurrence: Option<Recurrence>,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    /// Every tag the user has on at least one todo.
+    fn get_tags(
+        &self,
+        user_id: i64,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+    fn tag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    fn untag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    /// Adds a subtask to a todo, in the same list and at the bottom of its
+    /// other subtasks.
+    fn add_subtask(
+        &self,
+        user_id: i64,
+        parent_id: i64,
+        description: String,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    /// The todo's direct subtasks, each with their own subtasks filled in.
+    fn get_subtasks(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    /// Completing a todo completes all of its subtasks too, and reopening one
+    /// reopens everything it's a subtask of, so a completed todo never has
+    /// anything left to do underneath it.
+    ///
+    /// Completing a recurring todo also adds its next occurrence, with the
+    /// same details and tags but none of the subtasks, unless the one from
+    /// last time is still open. Reopening it leaves that occurrence alone.
+    /// Recurring subtasks completed along with it come back too, under the
+    /// next occurrence of what they were under, or else alongside it.
+    fn toggle_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    /// Moves a todo so it sits right after `after` and right before
+    /// `before`, both of which must be in the same list under the same
+    /// parent. Either can be left out when the todo is moved to the top or
+    /// bottom.
+    fn move_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        before: Option<i64>,
+        after: Option<i64>,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    /// Moves a todo and its subtasks to the trash, returning it as trashed.
+    fn delete_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    /// Completes every open todo at the top of the list, and with them
+    /// everything underneath. Returns the todos it completed.
+    fn complete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    /// Reopens every completed todo at the top of the list. Like reopening
+    /// them one at a time, their subtasks are left as they are.
+    fn uncomplete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    /// Moves every completed todo at the top of the list to the trash, along
+    /// with its subtasks. Returns the todos it trashed.
+    fn delete_completed(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    /// Does the same thing to each of the todos in one go, or to none of
+    /// them if any can't be found. Todos the action has already been done to
+    /// are left alone, but still returned with the rest.
+    fn bulk_update(
+        &self,
+        user_id: i64,
+        ids: Vec<i64>,
+        action: BulkAction,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    /// Adds todos read from a file, all of them or none. Each goes at the
+    /// bottom of the user's first list with the name it gives, which is
+    /// created if they don't have one, or of `list_id` if it doesn't give
+    /// one. Returns the new todos in the order they were given, without
+    /// their subtasks nested.
+    fn import(
+        &self,
+        user_id: i64,
+        list_id: i64,
+        todos: Vec<ImportedTodo>,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    /// Trashed todos, most recently deleted first. Subtasks that are in the
+    /// trash because their parent is aren't listed separately.
+    fn get_trash(
+        &self,
+        user_id: i64,
+    ) -> impl Future<Output = Result<Vec<Todo>>> + Send;
+    /// Takes a todo back out of the trash, along with the subtasks that went
+    /// in with it.
+    fn restore_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<Todo>> + Send;
+    /// Deletes a trashed todo for good.
+    fn purge_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// Deletes everything in the user's trash for good, returning how many
+    /// todos went.
+    fn empty_trash(
+        &self,
+        user_id: i64,
+    ) -> impl Future<Output = Result<u64>> + Send;
+    /// Deletes everything that's been in the trash longer than `retention`,
+    /// going by the DAO's clock, for every user. This is for the background
+    /// clean up, not for handlers.
+    fn purge_expired_trash(
+        &self,
+        retention: Duration,
+    ) -> impl Future<Output = Result<u64>> + Send;
+    /// Every change made to a todo, oldest first. Trashed todos keep their
+    /// history until they're purged.
+    fn history(
+        &self,
+        user_id: i64,
+        id: i64,
+    ) -> impl Future<Output = Result<Vec<TodoEvent>>> + Send;
+    /// Finds todos across all the user's lists whose descriptions contain
+    /// every word in the query, best matches first.
+    fn search(
+        &self,
+        user_id: i64,
+        query: String,
+    ) -> impl Future<Output = Result<Vec<SearchHit>>> + Send;
 }
 
 #[derive(Clone, Debug)]
 pub struct TodoSqliteDao {
     pool: SqlitePool,
+    clock: Arc<dyn Clock>,
 }
 
 impl TodoSqliteDao {
     pub fn new(pool: SqlitePool) -> Self {
-        TodoSqliteDao { pool }
+        TodoSqliteDao {
+            pool,
+            clock: Arc::new(SystemClock),
+        }
     }
-}
 
-impl TodoDao for TodoSqliteDao {
-    async fn get_all_todos(&self) -> anyhow::Result<Vec<Todo>> {
-        let todos = query_as::<_, Todo>("SELECT * FROM todos ORDER BY id")
-            .fetch_all(&self.pool)
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub(crate) fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// The time to stamp anything written now with, in millis.
+    pub(crate) fn now(&self) -> i64 {
+        self.clock.now_millis()
+    }
+
+    /// Fills in the tags of todos fetched from the `todos` table.
+    async fn load_tags(&self, todos: &mut [Todo]) -> Result<()> {
+        if todos.is_empty() {
+            return Ok(());
+        }
+        let ids = todos
+            .iter()
+            .map(|todo| todo.id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let tags: Vec<(i64, String)> = query_as(
+            "SELECT todo_tags.todo_id, tags.name FROM todo_tags
+            JOIN tags ON tags.id = todo_tags.tag_id
+            WHERE todo_tags.todo_id IN (SELECT value FROM json_each(?1))
+            ORDER BY tags.name",
+        )
+        .bind(format!("[{}]", ids))
+        .fetch_all(&self.pool)
+        .await?;
+        for todo in todos.iter_mut() {
+            todo.tags = tags
+                .iter()
+                .filter(|(todo_id, _)| *todo_id == todo.id)
+                .map(|(_, name)| name.clone())
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Fills in the subtasks of todos, and theirs, all the way down.
+    async fn load_subtasks(&self, todos: &mut [Todo]) -> Result<()> {
+        if todos.is_empty() {
+            return Ok(());
+        }
+        let ids = todos
+            .iter()
+            .map(|todo| todo.id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut descendants = query_as::<_, Todo>(
+            "WITH RECURSIVE descendants (id) AS (
+                SELECT id FROM todos
+                WHERE parent_id IN (SELECT value FROM json_each(?1))
+                AND deleted_at IS NULL
+                UNION
+                SELECT todos.id FROM todos
+                JOIN descendants ON todos.parent_id = descendants.id
+                WHERE todos.deleted_at IS NULL
+            )
+            SELECT todos.* FROM todos
+            JOIN descendants ON descendants.id = todos.id
+            ORDER BY todos.position, todos.id",
+        )
+        .bind(format!("[{}]", ids))
+        .fetch_all(&self.pool)
+        .await?;
+        self.load_tags(&mut descendants).await?;
+        attach_subtasks(todos, &descendants);
+        Ok(())
+    }
+
+    /// Fills in everything about todos that doesn't live in the `todos` row.
+    async fn load_details(&self, todos: &mut [Todo]) -> Result<()> {
+        self.load_tags(todos).await?;
+        self.load_subtasks(todos).await
+    }
+
+    async fn with_details(&self, mut todo: Todo) -> Result<Todo> {
+        self.load_details(std::slice::from_mut(&mut todo)).await?;
+        Ok(todo)
+    }
+
+    /// Fetches a todo the user owns, inside a transaction. Trashed todos
+    /// don't count.
+    async fn get_owned_todo(
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        id: i64,
+    ) -> Result<Todo> {
+        let todo = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = (?1) AND lists.user_id = (?2)
+            AND todos.deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+        Ok(todo)
+    }
+
+    /// Finds the positions a todo has to go between to end up right after
+    /// `after` and right before `before`. Only its siblings count, since
+    /// subtasks are ordered under their parent.
+    async fn position_bounds(
+        conn: &mut SqliteConnection,
+        todo: &Todo,
+        before: Option<i64>,
+        after: Option<i64>,
+    ) -> Result<(Option<f64>, Option<f64>)> {
+        let lower = match after {
+            Some(after) => {
+                Some(Self::neighbour_position(conn, todo, after).await?)
+            }
+            None => None,
+        };
+        let upper = match before {
+            Some(before) => {
+                Some(Self::neighbour_position(conn, todo, before).await?)
+            }
+            None => None,
+        };
+        match (lower, upper) {
+            (None, None) => Err(TodoError::Validation(
+                "say which todo to move it next to".to_string(),
+            )),
+            (Some(lower), Some(upper)) if lower >= upper => {
+                Err(TodoError::Validation(
+                    "the todo to move it after has to come first".to_string(),
+                ))
+            }
+            // only one side was given, so the other is whatever is next to it
+            (Some(lower), None) => {
+                let upper = query_scalar(
+                    "SELECT MIN(position) FROM todos
+                    WHERE list_id = (?1) AND id != (?2) AND position > (?3)
+                    AND parent_id IS (?4) AND deleted_at IS NULL",
+                )
+                .bind(todo.list_id)
+                .bind(todo.id)
+                .bind(lower)
+                .bind(todo.parent_id)
+                .fetch_one(&mut *conn)
+                .await?;
+                Ok((Some(lower), upper))
+            }
+            (None, Some(upper)) => {
+                let lower = query_scalar(
+                    "SELECT MAX(position) FROM todos
+                    WHERE list_id = (?1) AND id != (?2) AND position < (?3)
+                    AND parent_id IS (?4) AND deleted_at IS NULL",
+                )
+                .bind(todo.list_id)
+                .bind(todo.id)
+                .bind(upper)
+                .bind(todo.parent_id)
+                .fetch_one(&mut *conn)
+                .await?;
+                Ok((lower, Some(upper)))
+            }
+            bounds => Ok(bounds),
+        }
+    }
+
+    async fn neighbour_position(
+        conn: &mut SqliteConnection,
+        todo: &Todo,
+        neighbour_id: i64,
+    ) -> Result<f64> {
+        query_scalar(
+            "SELECT position FROM todos
+            WHERE id = (?1) AND list_id = (?2) AND id != (?3)
+            AND parent_id IS (?4) AND deleted_at IS NULL",
+        )
+        .bind(neighbour_id)
+        .bind(todo.list_id)
+        .bind(todo.id)
+        .bind(todo.parent_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(|| {
+            TodoError::Validation(
+                "todos can only be moved next to others under the same parent \
+                in the same list"
+                    .to_string(),
+            )
+        })
+    }
+
+    /// Adds an entry to a todo's history. Call it inside the transaction that
+    /// makes the change, so that the two can't disagree.
+    async fn record_event(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        todo_id: i64,
+        kind: TodoEventKind,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        query(
+            "INSERT INTO todo_events (todo_id, user_id, kind, detail, created_at)
+            VALUES ((?1), (?2), (?3), (?4), (?5))",
+        )
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(kind)
+        .bind(detail)
+        .bind(self.now())
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Completes a todo along with whatever is still open underneath it and
+    /// not in the trash, and schedules the next occurrence of each of those
+    /// that repeats.
+    async fn complete(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        todo: &mut Todo,
+    ) -> Result<()> {
+        let now = self.now();
+        let mut completed = query_as::<_, Todo>(
+            "UPDATE todos SET completed_at = (?1), updated_at = (?1)
+            WHERE completed_at IS NULL AND deleted_at IS NULL AND id IN (
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT (?2)
+                    UNION
+                    SELECT todos.id FROM todos
+                    JOIN subtree ON todos.parent_id = subtree.id
+                    WHERE todos.deleted_at IS NULL
+                )
+                SELECT id FROM subtree
+            )
+            RETURNING *",
+        )
+        .bind(now)
+        .bind(todo.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        // subtasks always come after what they're under, so this puts each
+        // todo's next occurrence in place before its subtasks need it
+        completed.sort_by_key(|done| done.id);
+        for done in &completed {
+            self.record_event(
+                conn,
+                user_id,
+                done.id,
+                TodoEventKind::Completed,
+                None,
+            )
             .await?;
+        }
+
+        let today = DateTime::from_timestamp_millis(now)
+            .unwrap_or_default()
+            .date_naive();
+        let mut occurrences = HashMap::new();
+        for done in &completed {
+            let Some(recurrence) = &done.recurrence else {
+                continue;
+            };
+            let due = recurrence.next_due(done.due_date(), today);
+            let parent_id =
+                next_occurrence_parent(done, todo, &completed, &occurrences);
+            let Some(next_id) =
+                Self::schedule_next(conn, done, parent_id, due, now).await?
+            else {
+                continue;
+            };
+            occurrences.insert(done.id, next_id);
+            self.record_event(
+                conn,
+                user_id,
+                next_id,
+                TodoEventKind::Created,
+                Some(&done.description),
+            )
+            .await?;
+        }
+        todo.completed_at = Some(now);
+        todo.updated_at = now;
+        Ok(())
+    }
+
+    /// Reopens a todo, along with everything it's a subtask of.
+    async fn uncomplete(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        todo: &mut Todo,
+    ) -> Result<()> {
+        let now = self.now();
+        for reopened in Self::reopen(conn, todo.id, now).await? {
+            self.record_event(
+                conn,
+                user_id,
+                reopened,
+                TodoEventKind::Reopened,
+                None,
+            )
+            .await?;
+        }
+        todo.completed_at = None;
+        todo.updated_at = now;
+        Ok(())
+    }
+
+    /// Moves a todo and its subtasks to the trash.
+    async fn trash(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        todo: &mut Todo,
+    ) -> Result<()> {
+        // subtasks go in the trash with it, marked with the same time so
+        // that they come back out together too
+        let deleted_at = self.now();
+        let trashed: Vec<i64> = query_scalar(
+            "UPDATE todos SET deleted_at = (?1)
+            WHERE deleted_at IS NULL AND id IN (
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT (?2)
+                    UNION
+                    SELECT todos.id FROM todos
+                    JOIN subtree ON todos.parent_id = subtree.id
+                )
+                SELECT id FROM subtree
+            )
+            RETURNING id",
+        )
+        .bind(deleted_at)
+        .bind(todo.id)
+        .fetch_all(&mut *conn)
+        .await?;
+        for trashed in trashed {
+            self.record_event(
+                conn,
+                user_id,
+                trashed,
+                TodoEventKind::Trashed,
+                None,
+            )
+            .await?;
+        }
+        todo.deleted_at = Some(deleted_at);
+        Ok(())
+    }
+
+    /// Applies a bulk action to each of the todos, which must all belong to
+    /// the user, and returns their ids in the order they were given.
+    async fn apply_bulk(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        ids: &[i64],
+        action: BulkAction,
+    ) -> Result<Vec<i64>> {
+        // everything has to be there before anything changes
+        for &id in ids {
+            Self::get_owned_todo(conn, user_id, id).await?;
+        }
+        let mut applied: Vec<i64> = Vec::with_capacity(ids.len());
+        for &id in ids {
+            if applied.contains(&id) {
+                continue;
+            }
+            // fetched again one at a time, since doing one can change another
+            // further down, e.g. completing a todo completes its subtasks
+            let mut todo =
+                query_as::<_, Todo>("SELECT * FROM todos WHERE id = (?1)")
+                    .bind(id)
+                    .fetch_one(&mut *conn)
+                    .await?;
+            if todo.deleted_at.is_none() {
+                match action {
+                    BulkAction::Complete if !todo.is_completed() => {
+                        self.complete(conn, user_id, &mut todo).await?
+                    }
+                    BulkAction::Uncomplete if todo.is_completed() => {
+                        self.uncomplete(conn, user_id, &mut todo).await?
+                    }
+                    BulkAction::Delete => {
+                        self.trash(conn, user_id, &mut todo).await?
+                    }
+                    _ => {}
+                }
+            }
+            applied.push(id);
+        }
+        Ok(applied)
+    }
+
+    /// Fetches todos by id with all their details, in the order given,
+    /// whether they're in the trash or not.
+    async fn get_todos_by_id(&self, ids: &[i64]) -> Result<Vec<Todo>> {
+        let mut todos = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN json_each(?1) AS ids ON ids.value = todos.id
+            ORDER BY ids.key",
+        )
+        .bind(format!(
+            "[{}]",
+            ids.iter().map(i64::to_string).collect::<Vec<_>>().join(",")
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        self.load_details(&mut todos).await?;
         Ok(todos)
     }
 
-    async fn add_todo(&self, description: String) -> anyhow::Result<Todo> {
-        let id = query("INSERT INTO todos (description) VALUES (?1)")
-            .bind(&description)
+    /// Runs a bulk action on the top-level todos of a list that match the
+    /// condition.
+    async fn bulk_update_list(
+        &self,
+        user_id: i64,
+        list_id: i64,
+        condition: &str,
+        action: BulkAction,
+    ) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        query("SELECT id FROM lists WHERE id = (?1) AND user_id = (?2)")
+            .bind(list_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let ids: Vec<i64> = query_scalar(&format!(
+            "SELECT id FROM todos
+            WHERE list_id = (?1) AND parent_id IS NULL
+            AND deleted_at IS NULL AND {}
+            ORDER BY position, id",
+            condition
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let ids = self.apply_bulk(&mut tx, user_id, &ids, action).await?;
+        tx.commit().await?;
+        self.get_todos_by_id(&ids).await
+    }
+
+    /// Adds a copy of a recurring todo under `parent_id` that's due next on
+    /// `due`, returning its id, unless the todo still has an open one from
+    /// the last time it was completed.
+    async fn schedule_next(
+        conn: &mut SqliteConnection,
+        todo: &Todo,
+        parent_id: Option<i64>,
+        due: NaiveDate,
+        now: i64,
+    ) -> Result<Option<i64>> {
+        let Some(next_id) = query_scalar(
+            "INSERT INTO todos
+            (list_id, parent_id, description, due_at, priority, recurrence,
+                position, created_at, updated_at, previous_id)
+            SELECT list_id, (?4), description, (?2), priority, recurrence, (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                WHERE list_id = previous.list_id
+            ), (?3), (?3), id
+            FROM todos AS previous WHERE id = (?1) AND NOT EXISTS (
+                SELECT 1 FROM todos WHERE previous_id = (?1)
+                AND completed_at IS NULL AND deleted_at IS NULL
+            )
+            RETURNING id",
+        )
+        .bind(todo.id)
+        .bind(due.and_time(NaiveTime::MIN).and_utc().timestamp_millis())
+        .bind(now)
+        .bind(parent_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        else {
+            return Ok(None);
+        };
+        query(
+            "INSERT INTO todo_tags (todo_id, tag_id)
+            SELECT (?1), tag_id FROM todo_tags WHERE todo_id = (?2)",
+        )
+        .bind(next_id)
+        .bind(todo.id)
+        .execute(&mut *conn)
+        .await?;
+        Ok(Some(next_id))
+    }
+
+    /// Marks a todo as not done, along with everything it's a subtask of,
+    /// returning the ids of the todos that were done.
+    async fn reopen(
+        conn: &mut SqliteConnection,
+        id: i64,
+        now: i64,
+    ) -> Result<Vec<i64>> {
+        let reopened = query_scalar(
+            "UPDATE todos SET completed_at = NULL, updated_at = (?2)
+            WHERE completed_at IS NOT NULL AND id IN (
+                WITH RECURSIVE ancestors (id) AS (
+                    SELECT (?1)
+                    UNION
+                    SELECT todos.parent_id FROM todos
+                    JOIN ancestors ON ancestors.id = todos.id
+                    WHERE todos.parent_id IS NOT NULL
+                )
+                SELECT id FROM ancestors
+            )
+            RETURNING id",
+        )
+        .bind(id)
+        .bind(now)
+        .fetch_all(conn)
+        .await?;
+        Ok(reopened)
+    }
+
+    /// Marks a todo as changed just now, for changes that live outside its
+    /// row.
+    async fn touch(
+        conn: &mut SqliteConnection,
+        id: i64,
+        now: i64,
+    ) -> Result<()> {
+        query("UPDATE todos SET updated_at = (?1) WHERE id = (?2)")
+            .bind(now)
+            .bind(id)
+            .execute(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Spreads a list's positions back out to 1, 2, 3, ... keeping the order.
+    async fn renumber_positions(
+        conn: &mut SqliteConnection,
+        list_id: i64,
+    ) -> Result<()> {
+        query(
+            "UPDATE todos SET position = ranked.position
+            FROM (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS position
+                FROM todos WHERE list_id = (?1)
+            ) AS ranked
+            WHERE todos.id = ranked.id",
+        )
+        .bind(list_id)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Attaches a tag to a todo, creating the tag if the user hasn't used it
+    /// before. Returns whether the todo didn't already have it.
+    async fn insert_tag(
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        todo_id: i64,
+        tag: &str,
+    ) -> Result<bool> {
+        query("INSERT OR IGNORE INTO tags (user_id, name) VALUES (?1, ?2)")
+            .bind(user_id)
+            .bind(tag)
+            .execute(&mut *conn)
+            .await?;
+        let inserted = query(
+            "INSERT OR IGNORE INTO todo_tags (todo_id, tag_id)
+            SELECT (?1), id FROM tags WHERE user_id = (?2) AND name = (?3)",
+        )
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(tag)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+        Ok(inserted > 0)
+    }
+
+    /// The user's first list with the name, created if they don't have one.
+    async fn list_named(
+        conn: &mut SqliteConnection,
+        user_id: i64,
+        name: &str,
+    ) -> Result<i64> {
+        let existing = query_scalar(
+            "SELECT id FROM lists WHERE user_id = (?1) AND name = (?2)
+            ORDER BY id LIMIT 1",
+        )
+        .bind(user_id)
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+        let id = query("INSERT INTO lists (user_id, name) VALUES (?1, ?2)")
+            .bind(user_id)
+            .bind(name)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid();
+        Ok(id)
+    }
+}
+
+impl TodoDao for TodoSqliteDao {
+    async fn get_lists(&self, user_id: i64) -> Result<Vec<List>> {
+        let lists = query_as::<_, List>(
+            "SELECT * FROM lists WHERE user_id = (?1) ORDER BY id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(lists)
+    }
+
+    async fn get_list(&self, user_id: i64, id: i64) -> Result<List> {
+        let list = query_as::<_, List>(
+            "SELECT * FROM lists WHERE id = (?1) AND user_id = (?2)",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(list)
+    }
+
+    async fn create_list(&self, user_id: i64, name: String) -> Result<List> {
+        let id = query("INSERT INTO lists (user_id, name) VALUES (?1, ?2)")
+            .bind(user_id)
+            .bind(&name)
             .execute(&self.pool)
             .await?
             .last_insert_rowid();
+        Ok(List { id, name })
+    }
+
+    async fn rename_list(
+        &self,
+        user_id: i64,
+        id: i64,
+        name: String,
+    ) -> Result<List> {
+        let list = query_as::<_, List>(
+            "UPDATE lists SET name = (?1) WHERE id = (?2) AND user_id = (?3)
+            RETURNING *",
+        )
+        .bind(name)
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(list)
+    }
+
+    async fn delete_list(&self, user_id: i64, id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let remaining: i64 = query_scalar(
+            "SELECT COUNT(*) FROM lists WHERE id != (?1) AND user_id = (?2)",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let rows_affected =
+            query("DELETE FROM lists WHERE id = (?1) AND user_id = (?2)")
+                .bind(id)
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        if rows_affected == 0 {
+            return Err(TodoError::NotFound);
+        }
+        // the home page always shows a list, so there has to be one left
+        if remaining == 0 {
+            return Err(TodoError::Conflict(
+                "can't delete the last list".to_string(),
+            ));
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_todos(
+        &self,
+        user_id: i64,
+        todo_query: TodoQuery,
+    ) -> Result<Vec<Todo>> {
+        // the filter and order are fixed strings, so they're safe to splice in
+        let sql = format!(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.list_id = (?1) AND lists.user_id = (?2)
+            AND todos.parent_id IS NULL AND todos.deleted_at IS NULL
+            AND ((?3) IS NULL OR todos.id IN (
+                SELECT todo_tags.todo_id FROM todo_tags
+                JOIN tags ON tags.id = todo_tags.tag_id
+                WHERE tags.user_id = (?2) AND tags.name = (?3)
+            ))
+            AND {}
+            ORDER BY {}",
+            todo_query.filter.condition(),
+            todo_query.order.order_by()
+        );
+        let mut todos = query_as::<_, Todo>(&sql)
+            .bind(todo_query.list_id)
+            .bind(user_id)
+            .bind(todo_query.tag)
+            .fetch_all(&self.pool)
+            .await?;
+        self.load_details(&mut todos).await?;
+        Ok(todos)
+    }
+
+    async fn get_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let todo = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = (?1) AND lists.user_id = (?2)
+            AND todos.deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        self.with_details(todo).await
+    }
+
+    async fn add_todo(&self, user_id: i64, new_todo: NewTodo) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+
+        // only insert if the list exists and belongs to the user
+        let now = self.now();
+        let result = query(
+            "INSERT INTO todos
+            (list_id, description, due_at, priority, recurrence, position,
+                created_at, updated_at)
+            SELECT id, (?2), (?3), (?4), (?5), (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                WHERE list_id = (?1)
+            ), (?7), (?7) FROM lists
+            WHERE id = (?1) AND user_id = (?6)",
+        )
+        .bind(new_todo.list_id)
+        .bind(&new_todo.description)
+        .bind(new_todo.due_at)
+        .bind(new_todo.priority)
+        .bind(&new_todo.recurrence)
+        .bind(user_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(TodoError::NotFound);
+        }
+        let id = result.last_insert_rowid();
+        let mut tags = new_todo.tags;
+        tags.sort();
+        tags.dedup();
+        for tag in tags.iter() {
+            Self::insert_tag(&mut tx, user_id, id, tag).await?;
+        }
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Created,
+            Some(&new_todo.description),
+        )
+        .await?;
+
+        tx.commit().await?;
+
         Ok(Todo {
             id,
-            description,
+            list_id: new_todo.list_id,
+            description: new_todo.description,
             completed_at: None,
+            created_at: now,
+            updated_at: now,
+            due_at: new_todo.due_at,
+            priority: new_todo.priority,
+            tags,
+            parent_id: None,
+            recurrence: new_todo.recurrence,
+            deleted_at: None,
+            subtasks: vec![],
         })
     }
 
-    async fn toggle_todo(&self, id: i64) -> anyhow::Result<Todo> {
+    async fn update_description(
+        &self,
+        user_id: i64,
+        id: i64,
+        description: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = query_as::<_, Todo>(
+            "UPDATE todos SET description = (?1), updated_at = (?4)
+            WHERE id = (?2) AND deleted_at IS NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = (?3))
+            RETURNING *",
+        )
+        .bind(&description)
+        .bind(id)
+        .bind(user_id)
+        .bind(self.now())
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Renamed,
+            Some(&todo.description),
+        )
+        .await?;
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn set_priority(
+        &self,
+        user_id: i64,
+        id: i64,
+        priority: Priority,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = query_as::<_, Todo>(
+            "UPDATE todos SET priority = (?1), updated_at = (?4)
+            WHERE id = (?2) AND deleted_at IS NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = (?3))
+            RETURNING *",
+        )
+        .bind(priority)
+        .bind(id)
+        .bind(user_id)
+        .bind(self.now())
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Prioritized,
+            Some(priority.as_str()),
+        )
+        .await?;
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn set_recurrence(
+        &self,
+        user_id: i64,
+        id: i64,
+        recurrence: Option<Recurrence>,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = query_as::<_, Todo>(
+            "UPDATE todos SET recurrence = (?1), updated_at = (?4)
+            WHERE id = (?2) AND deleted_at IS NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = (?3))
+            RETURNING *",
+        )
+        .bind(&recurrence)
+        .bind(id)
+        .bind(user_id)
+        .bind(self.now())
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            id,
+            TodoEventKind::Repeated,
+            recurrence.as_ref().map(Recurrence::to_string).as_deref(),
+        )
+        .await?;
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn toggle_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
         // open a new transaction
         let mut tx = self.pool.begin().await?;
 
         // fetch existing todo
-        let mut todo: Todo = query_as("SELECT * FROM todos WHERE id = (?1)")
-            .bind(id)
-            .fetch_one(&mut *tx)
-            .await?;
+        let mut todo: Todo = query_as(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = (?1) AND lists.user_id = (?2)
+            AND todos.deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
 
         if todo.is_completed() {
-            // uncomplete the todo
-            query("UPDATE todos SET completed_at = NULL WHERE id = (?1)")
-                .bind(id)
-                .execute(&mut *tx)
-                .await?;
-            todo.completed_at = None;
+            self.uncomplete(&mut tx, user_id, &mut todo).await?;
         } else {
-            let completed_at = SystemTime::now()
-                .duration_since(UNIX_EPOCH)?
-                .as_millis() as i64;
-
-            // update the database row
-            query("UPDATE todos SET completed_at = (?1) WHERE id = (?2)")
-                .bind(completed_at)
-                .bind(id)
-                .execute(&mut *tx)
-                .await?;
-            todo.completed_at = Some(completed_at);
+            self.complete(&mut tx, user_id, &mut todo).await?;
         }
 
         // close the transaction (important!)
         tx.commit().await?;
 
+        self.with_details(todo).await
+    }
+
+    async fn get_tags(&self, user_id: i64) -> Result<Vec<String>> {
+        let tags = query_scalar(
+            "SELECT DISTINCT tags.name FROM tags
+            JOIN todo_tags ON todo_tags.tag_id = tags.id
+            JOIN todos ON todos.id = todo_tags.todo_id
+            WHERE tags.user_id = (?1) AND todos.deleted_at IS NULL
+            ORDER BY tags.name",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tags)
+    }
+
+    async fn tag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let mut todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+        if Self::insert_tag(&mut tx, user_id, id, &tag).await? {
+            todo.updated_at = self.now();
+            Self::touch(&mut tx, id, todo.updated_at).await?;
+            self.record_event(
+                &mut tx,
+                user_id,
+                id,
+                TodoEventKind::Tagged,
+                Some(&tag),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn untag_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        tag: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let mut todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+        let removed = query(
+            "DELETE FROM todo_tags WHERE todo_id = (?1)
+            AND tag_id IN (SELECT id FROM tags WHERE user_id = (?2) AND name = (?3))",
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(&tag)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if removed > 0 {
+            todo.updated_at = self.now();
+            Self::touch(&mut tx, id, todo.updated_at).await?;
+            self.record_event(
+                &mut tx,
+                user_id,
+                id,
+                TodoEventKind::Untagged,
+                Some(&tag),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn add_subtask(
+        &self,
+        user_id: i64,
+        parent_id: i64,
+        description: String,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let parent = Self::get_owned_todo(&mut tx, user_id, parent_id).await?;
+
+        let now = self.now();
+        let todo = query_as::<_, Todo>(
+            "INSERT INTO todos
+            (list_id, parent_id, description, position, created_at, updated_at)
+            VALUES ((?1), (?2), (?3), (
+                SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                WHERE list_id = (?1)
+            ), (?4), (?4))
+            RETURNING *",
+        )
+        .bind(parent.list_id)
+        .bind(parent.id)
+        .bind(description)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        self.record_event(
+            &mut tx,
+            user_id,
+            todo.id,
+            TodoEventKind::Created,
+            Some(&todo.description),
+        )
+        .await?;
+        // a completed todo can't have anything left to do underneath it
+        for reopened in Self::reopen(&mut tx, parent.id, now).await? {
+            self.record_event(
+                &mut tx,
+                user_id,
+                reopened,
+                TodoEventKind::Reopened,
+                None,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
         Ok(todo)
     }
+
+    async fn get_subtasks(&self, user_id: i64, id: i64) -> Result<Vec<Todo>> {
+        Ok(self.get_todo(user_id, id).await?.subtasks)
+    }
+
+    async fn move_todo(
+        &self,
+        user_id: i64,
+        id: i64,
+        before: Option<i64>,
+        after: Option<i64>,
+    ) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+
+        let (lower, upper) =
+            Self::position_bounds(&mut tx, &todo, before, after).await?;
+        let position = match position_between(lower, upper) {
+            Some(position) => position,
+            None => {
+                // out of room between the two, so make some and try again
+                Self::renumber_positions(&mut tx, todo.list_id).await?;
+                let (lower, upper) =
+                    Self::position_bounds(&mut tx, &todo, before, after)
+                        .await?;
+                position_between(lower, upper).ok_or_else(|| {
+                    TodoError::Storage("ran out of room to move todo".into())
+                })?
+            }
+        };
+        query("UPDATE todos SET position = (?1) WHERE id = (?2)")
+            .bind(position)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        self.record_event(&mut tx, user_id, id, TodoEventKind::Moved, None)
+            .await?;
+
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn delete_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+        let mut todo = Self::get_owned_todo(&mut tx, user_id, id).await?;
+
+        self.trash(&mut tx, user_id, &mut todo).await?;
+
+        tx.commit().await?;
+        self.with_details(todo).await
+    }
+
+    async fn complete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.bulk_update_list(
+            user_id,
+            list_id,
+            "completed_at IS NULL",
+            BulkAction::Complete,
+        )
+        .await
+    }
+
+    async fn uncomplete_all(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.bulk_update_list(
+            user_id,
+            list_id,
+            "completed_at IS NOT NULL",
+            BulkAction::Uncomplete,
+        )
+        .await
+    }
+
+    async fn delete_completed(
+        &self,
+        user_id: i64,
+        list_id: i64,
+    ) -> Result<Vec<Todo>> {
+        self.bulk_update_list(
+            user_id,
+            list_id,
+            "completed_at IS NOT NULL",
+            BulkAction::Delete,
+        )
+        .await
+    }
+
+    async fn bulk_update(
+        &self,
+        user_id: i64,
+        ids: Vec<i64>,
+        action: BulkAction,
+    ) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        let ids = self.apply_bulk(&mut tx, user_id, &ids, action).await?;
+        tx.commit().await?;
+        self.get_todos_by_id(&ids).await
+    }
+
+    async fn import(
+        &self,
+        user_id: i64,
+        list_id: i64,
+        todos: Vec<ImportedTodo>,
+    ) -> Result<Vec<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        query("SELECT id FROM lists WHERE id = (?1) AND user_id = (?2)")
+            .bind(list_id)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let now = self.now();
+        let mut imported: Vec<Todo> = vec![];
+        for todo in todos {
+            let (list_id, parent_id) = match (todo.parent, &todo.list) {
+                (Some(parent), _) => {
+                    let parent = imported.get(parent).ok_or_else(|| {
+                        TodoError::Validation(
+                            "subtasks have to come after the todo they're \
+                            under"
+                                .to_string(),
+                        )
+                    })?;
+                    (parent.list_id, Some(parent.id))
+                }
+                (None, Some(name)) => {
+                    (Self::list_named(&mut tx, user_id, name).await?, None)
+                }
+                (None, None) => (list_id, None),
+            };
+            let (completed_at, created_at, updated_at) = todo.timestamps(now);
+            let mut new_todo = query_as::<_, Todo>(
+                "INSERT INTO todos
+                (list_id, parent_id, description, completed_at, created_at,
+                    updated_at, due_at, priority, recurrence, position)
+                VALUES ((?1), (?2), (?3), (?4), (?5), (?6), (?7), (?8), (?9), (
+                    SELECT COALESCE(MAX(position), 0) + 1 FROM todos
+                    WHERE list_id = (?1)
+                ))
+                RETURNING *",
+            )
+            .bind(list_id)
+            .bind(parent_id)
+            .bind(&todo.description)
+            .bind(completed_at)
+            .bind(created_at)
+            .bind(updated_at)
+            .bind(todo.due_at)
+            .bind(todo.priority)
+            .bind(&todo.recurrence)
+            .fetch_one(&mut *tx)
+            .await?;
+            let mut tags = todo.tags;
+            tags.sort();
+            tags.dedup();
+            for tag in tags.iter() {
+                Self::insert_tag(&mut tx, user_id, new_todo.id, tag).await?;
+            }
+            new_todo.tags = tags;
+            self.record_event(
+                &mut tx,
+                user_id,
+                new_todo.id,
+                TodoEventKind::Created,
+                Some(&new_todo.description),
+            )
+            .await?;
+            imported.push(new_todo);
+        }
+
+        tx.commit().await?;
+        Ok(imported)
+    }
+
+    async fn get_trash(&self, user_id: i64) -> Result<Vec<Todo>> {
+        let mut todos = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            LEFT JOIN todos AS parent ON parent.id = todos.parent_id
+            WHERE lists.user_id = (?1) AND todos.deleted_at IS NOT NULL
+            AND parent.deleted_at IS NULL
+            ORDER BY todos.deleted_at DESC, todos.id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        self.load_tags(&mut todos).await?;
+        Ok(todos)
+    }
+
+    async fn restore_todo(&self, user_id: i64, id: i64) -> Result<Todo> {
+        let mut tx = self.pool.begin().await?;
+
+        let todo = query_as::<_, Todo>(
+            "SELECT todos.* FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = (?1) AND lists.user_id = (?2)
+            AND todos.deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if let Some(parent_id) = todo.parent_id {
+            let parent_trashed: bool = query_scalar(
+                "SELECT deleted_at IS NOT NULL FROM todos WHERE id = (?1)",
+            )
+            .bind(parent_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            if parent_trashed {
+                return Err(TodoError::Conflict(
+                    "restore the todo this is a subtask of first".to_string(),
+                ));
+            }
+        }
+        let restored: Vec<i64> = query_scalar(
+            "UPDATE todos SET deleted_at = NULL
+            WHERE deleted_at = (?1) AND id IN (
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT (?2)
+                    UNION
+                    SELECT todos.id FROM todos
+                    JOIN subtree ON todos.parent_id = subtree.id
+                )
+                SELECT id FROM subtree
+            )
+            RETURNING id",
+        )
+        .bind(todo.deleted_at)
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for restored in restored {
+            self.record_event(
+                &mut tx,
+                user_id,
+                restored,
+                TodoEventKind::Restored,
+                None,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        self.get_todo(user_id, id).await
+    }
+
+    async fn purge_todo(&self, user_id: i64, id: i64) -> Result<()> {
+        // subtasks are deleted along with it by the foreign key
+        let rows_affected = query(
+            "DELETE FROM todos
+            WHERE id = (?1) AND deleted_at IS NOT NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = (?2))",
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if rows_affected == 0 {
+            return Err(TodoError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn empty_trash(&self, user_id: i64) -> Result<u64> {
+        const TRASHED: &str = "FROM todos
+            WHERE deleted_at IS NOT NULL
+            AND list_id IN (SELECT id FROM lists WHERE user_id = (?1))";
+        // count first, since rows removed by the cascade from a trashed
+        // parent don't show up in `rows_affected`
+        let mut tx = self.pool.begin().await?;
+        let purged: i64 = query_scalar(&format!("SELECT COUNT(*) {}", TRASHED))
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        query(&format!("DELETE {}", TRASHED))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(purged as u64)
+    }
+
+    async fn purge_expired_trash(&self, retention: Duration) -> Result<u64> {
+        let cutoff = self.now() - retention.as_millis() as i64;
+        let mut tx = self.pool.begin().await?;
+        let purged: i64 =
+            query_scalar("SELECT COUNT(*) FROM todos WHERE deleted_at < (?1)")
+                .bind(cutoff)
+                .fetch_one(&mut *tx)
+                .await?;
+        query("DELETE FROM todos WHERE deleted_at < (?1)")
+            .bind(cutoff)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(purged as u64)
+    }
+
+    async fn history(&self, user_id: i64, id: i64) -> Result<Vec<TodoEvent>> {
+        // make sure the todo is theirs first, so that someone else's looks
+        // missing rather than empty
+        query(
+            "SELECT todos.id FROM todos
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos.id = (?1) AND lists.user_id = (?2)",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        let events = query_as::<_, TodoEvent>(
+            "SELECT todo_events.id, todo_events.todo_id, users.username,
+                todo_events.kind, todo_events.detail, todo_events.created_at
+            FROM todo_events
+            LEFT JOIN users ON users.id = todo_events.user_id
+            WHERE todo_events.todo_id = (?1)
+            ORDER BY todo_events.created_at, todo_events.id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events)
+    }
+
+    async fn search(
+        &self,
+        user_id: i64,
+        text: String,
+    ) -> Result<Vec<SearchHit>> {
+        let fts_query = fts_query(&text);
+        if fts_query.is_empty() {
+            return Ok(vec![]);
+        }
+        let rows = query_as::<_, SearchRow>(
+            "SELECT todos.*, lists.name AS list_name,
+            snippet(todos_fts, 0, char(2), char(3), '…', 12) AS snippet
+            FROM todos_fts
+            JOIN todos ON todos.id = todos_fts.rowid
+            JOIN lists ON lists.id = todos.list_id
+            WHERE todos_fts MATCH (?1) AND lists.user_id = (?2)
+            AND todos.deleted_at IS NULL
+            ORDER BY bm25(todos_fts), todos.id
+            LIMIT (?3)",
+        )
+        .bind(fts_query)
+        .bind(user_id)
+        .bind(SEARCH_LIMIT)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut todos =
+            rows.iter().map(|row| row.todo.clone()).collect::<Vec<_>>();
+        self.load_details(&mut todos).await?;
+        Ok(rows
+            .into_iter()
+            .zip(todos)
+            .map(|(row, todo)| SearchHit {
+                todo,
+                list_name: row.list_name,
+                snippet: parse_snippet(&row.snippet),
+            })
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::create_pool;
-    use std::collections::HashSet;
+    use crate::{
+        clock::FakeClock,
+        dao_tests::{TestDao, dao_tests},
+        db::create_pool,
+    };
 
-    async fn get_dao() -> TodoSqliteDao {
-        let pool = create_pool("sqlite::memory:")
-            .await
-            .expect("failed to create pool");
-        TodoSqliteDao::new(pool)
+    #[test]
+    fn test_validate_description_trims() {
+        let description = validate_description("  Buy milk \n", 10).unwrap();
+
+        assert_eq!(description, "Buy milk");
+    }
+
+    #[test]
+    fn test_validate_description_empty() {
+        let result = validate_description(" \t ", 10);
+
+        assert!(matches!(result, Err(TodoError::Validation(_))));
     }
 
-    #[tokio::test]
-    async fn test_get_all_todos_empty() {
-        let dao = get_dao().await;
+    #[test]
+    fn test_validate_description_too_long() {
+        assert!(validate_description("ééééé", 5).is_ok());
 
-        let todos = dao.get_all_todos().await.unwrap();
+        let result = validate_description("Buy milk", 5);
 
-        assert!(todos.is_empty());
+        assert!(matches!(result, Err(TodoError::Validation(_))));
     }
 
-    #[tokio::test]
-    async fn test_get_all_todos() {
-        let dao = get_dao().await;
+    #[test]
+    fn test_validate_description_control_characters() {
+        let result = validate_description("Buy\u{7}milk", 10);
 
-        dao.add_todo("Buy milk".to_string()).await.unwrap();
-        dao.add_todo("Buy eggs".to_string()).await.unwrap();
-        dao.add_todo("Make breakfast".to_string()).await.unwrap();
-        let todos = dao.get_all_todos().await.unwrap();
+        assert!(matches!(result, Err(TodoError::Validation(_))));
+    }
 
-        assert_eq!(3, todos.len());
-        let descriptions = todos
-            .into_iter()
-            .map(|t| t.description)
-            .collect::<HashSet<_>>();
-        assert_eq!(
-            descriptions,
-            HashSet::from([
-                "Buy milk".to_string(),
-                "Buy eggs".to_string(),
-                "Make breakfast".to_string()
-            ])
-        );
+    #[test]
+    fn test_parse_due_date() {
+        let due_at = parse_due_date("2025-12-31").unwrap();
+
+        assert_eq!(due_at, Some(1_767_139_200_000));
+        assert_eq!(parse_due_date(" ").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_due_date_invalid() {
+        let result = parse_due_date("31/12/2025");
+
+        assert!(matches!(result, Err(TodoError::Validation(_))));
+    }
+
+    #[test]
+    fn test_is_overdue() {
+        let today = NaiveDate::from_ymd_opt(2025, 12, 31).unwrap();
+        let mut todo = Todo::new(1, "Buy milk");
+
+        assert!(!todo.is_overdue(today));
+        todo.due_at = parse_due_date("2025-12-31").unwrap();
+        assert!(!todo.is_overdue(today));
+        todo.due_at = parse_due_date("2025-12-30").unwrap();
+        assert!(todo.is_overdue(today));
+        todo.completed_at = Some(0);
+        assert!(!todo.is_overdue(today));
     }
 
-    #[tokio::test]
-    async fn test_add_todo() {
-        let pool = get_dao().await;
+    #[test]
+    fn test_validate_tag() {
+        assert_eq!(validate_tag(" #Work ").unwrap(), "work");
+        assert_eq!(validate_tag("side-project_2").unwrap(), "side-project_2");
+        assert!(matches!(validate_tag("#"), Err(TodoError::Validation(_))));
+        assert!(matches!(
+            validate_tag("two words"),
+            Err(TodoError::Validation(_))
+        ));
+    }
 
-        let todo = pool.add_todo("Buy milk".to_string()).await.unwrap();
+    #[test]
+    fn test_extract_tags() {
+        let (description, tags) =
+            extract_tags("Buy milk #errands #Errands #home");
 
-        assert_eq!(todo.description, "Buy milk");
-        assert!(todo.completed_at.is_none());
+        assert_eq!(description, "Buy milk");
+        assert_eq!(tags, vec!["errands", "home"]);
     }
 
-    #[tokio::test]
-    async fn test_toggle_todo() {
-        let dao = get_dao().await;
+    #[test]
+    fn test_extract_tags_leaves_other_text_alone() {
+        let (description, tags) = extract_tags("Learn C# and F#  # today");
+
+        assert_eq!(description, "Learn C# and F#  # today");
+        assert!(tags.is_empty());
+    }
 
-        let mut todo = dao.add_todo("Buy milk".to_string()).await.unwrap();
-        todo = dao.toggle_todo(todo.id).await.unwrap();
+    #[test]
+    fn test_parse_snippet() {
+        let parts = parse_snippet("buy \u{2}milk\u{3} and \u{2}eggs\u{3}");
+
+        assert_eq!(
+            parts,
+            vec![
+                SnippetPart::new("buy ", false),
+                SnippetPart::new("milk", true),
+                SnippetPart::new(" and ", false),
+                SnippetPart::new("eggs", true),
+            ]
+        );
+    }
 
-        assert!(todo.is_completed());
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query(" buy  mi "), "\"buy\"* \"mi\"*");
+        assert_eq!(
+            fts_query("say \"hi\" OR"),
+            "\"say\"* \"\"\"hi\"\"\"* \"OR\"*"
+        );
+        assert_eq!(fts_query("  "), "");
     }
 
-    #[tokio::test]
-    async fn test_toggle_nonexistent_todo() {
-        let dao = get_dao().await;
+    #[test]
+    fn test_position_between() {
+        assert_eq!(position_between(Some(1.0), Some(2.0)), Some(1.5));
+        assert_eq!(position_between(Some(3.0), None), Some(4.0));
+        assert_eq!(position_between(None, Some(3.0)), Some(2.0));
+        assert_eq!(position_between(None, None), Some(1.0));
+        assert_eq!(position_between(Some(1.0), Some(1.0)), None);
+        let next = f64::from_bits(1.0f64.to_bits() + 1);
+        assert_eq!(position_between(Some(1.0), Some(next)), None);
+    }
 
-        let result = dao.toggle_todo(999).await;
+    impl TestDao for TodoSqliteDao {
+        async fn empty(clock: FakeClock) -> Self {
+            let pool = create_pool("sqlite::memory:")
+                .await
+                .expect("failed to create pool");
+            TodoSqliteDao::new(pool).with_clock(clock)
+        }
 
-        assert!(result.is_err());
+        async fn add_unowned_list(&self, name: &str) -> i64 {
+            query_scalar("INSERT INTO lists (name) VALUES (?1) RETURNING id")
+                .bind(name)
+                .fetch_one(self.pool())
+                .await
+                .expect("failed to add list")
+        }
     }
+
+    dao_tests!(TodoSqliteDao);
 }
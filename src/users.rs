@@ -0,0 +1,200 @@
+use crate::{
+    errors::{Result, TodoError},
+    todos::TodoSqliteDao,
+};
+use serde::Serialize;
+use sqlx::{FromRow, query, query_as};
+use std::time::Duration;
+
+#[cfg(test)]
+use mockall::automock;
+
+/// How long a session cookie stays valid after logging in.
+pub const SESSION_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(FromRow, Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+}
+
+impl User {
+    pub fn new<S>(id: i64, username: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id,
+            username: username.into(),
+        }
+    }
+}
+
+/// A user along with their stored password hash; only used to check logins,
+/// so it never leaves the auth code.
+#[derive(FromRow, PartialEq, Eq, Clone, Debug)]
+pub struct Credentials {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: String,
+}
+
+impl From<Credentials> for User {
+    fn from(credentials: Credentials) -> Self {
+        User::new(credentials.id, credentials.username)
+    }
+}
+
+#[cfg_attr(test, automock)]
+pub trait UserDao {
+    /// Creates a user and gives them somewhere to put their todos. Lists that
+    /// predate accounts are left alone; see `claim_unowned_lists`.
+    fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> impl Future<Output = Result<User>> + Send;
+    fn get_credentials(
+        &self,
+        username: String,
+    ) -> impl Future<Output = Result<Credentials>> + Send;
+    fn create_session(
+        &self,
+        token: String,
+        user_id: i64,
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn get_session_user(
+        &self,
+        token: String,
+    ) -> impl Future<Output = Result<User>> + Send;
+    fn delete_session(
+        &self,
+        token: String,
+    ) -> impl Future<Output = Result<()>> + Send;
+    /// Gives a user every list that predates accounts, along with the todos
+    /// on them, and returns how many there were. Only the command line calls
+    /// this; signing up never does.
+    fn claim_unowned_lists(
+        &self,
+        user_id: i64,
+    ) -> impl Future<Output = Result<u64>> + Send;
+}
+
+impl UserDao for TodoSqliteDao {
+    async fn create_user(
+        &self,
+        username: String,
+        password_hash: String,
+    ) -> Result<User> {
+        let mut tx = self.pool().begin().await?;
+
+        let id = query(
+            "INSERT INTO users (username, password_hash) VALUES (?1, ?2)",
+        )
+        .bind(&username)
+        .bind(&password_hash)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| match TodoError::from(e) {
+            TodoError::Conflict(_) => TodoError::Conflict(
+                "that username is already taken".to_string(),
+            ),
+            e => e,
+        })?
+        .last_insert_rowid();
+
+        query("INSERT INTO lists (user_id, name) VALUES (?1, 'Todos')")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(User { id, username })
+    }
+
+    async fn get_credentials(&self, username: String) -> Result<Credentials> {
+        let credentials = query_as::<_, Credentials>(
+            "SELECT id, username, password_hash FROM users WHERE username = (?1)",
+        )
+        .bind(username)
+        .fetch_one(self.pool())
+        .await?;
+        Ok(credentials)
+    }
+
+    async fn create_session(&self, token: String, user_id: i64) -> Result<()> {
+        let expires_at = self.now() + SESSION_TTL.as_millis() as i64;
+        query("INSERT INTO sessions (id, user_id, expires_at) VALUES (?1, ?2, ?3)")
+            .bind(token)
+            .bind(user_id)
+            .bind(expires_at)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    async fn get_session_user(&self, token: String) -> Result<User> {
+        let user = query_as::<_, User>(
+            "SELECT users.id, users.username FROM sessions
+            JOIN users ON users.id = sessions.user_id
+            WHERE sessions.id = (?1) AND sessions.expires_at > (?2)",
+        )
+        .bind(token)
+        .bind(self.now())
+        .fetch_one(self.pool())
+        .await?;
+        Ok(user)
+    }
+
+    async fn delete_session(&self, token: String) -> Result<()> {
+        query("DELETE FROM sessions WHERE id = (?1)")
+            .bind(token)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    async fn claim_unowned_lists(&self, user_id: i64) -> Result<u64> {
+        let claimed =
+            query("UPDATE lists SET user_id = (?1) WHERE user_id IS NULL")
+                .bind(user_id)
+                .execute(self.pool())
+                .await?
+                .rows_affected();
+        Ok(claimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_pool;
+
+    async fn get_dao() -> TodoSqliteDao {
+        let pool = create_pool("sqlite::memory:")
+            .await
+            .expect("failed to create pool");
+        TodoSqliteDao::new(pool)
+    }
+
+    // the rest of the user tests run against every backend, in `dao_tests`
+    #[tokio::test]
+    async fn test_expired_session() {
+        let dao = get_dao().await;
+
+        let user = dao
+            .create_user("alice".to_string(), "hash".to_string())
+            .await
+            .unwrap();
+        query("INSERT INTO sessions (id, user_id, expires_at) VALUES ('old', ?1, 0)")
+            .bind(user.id)
+            .execute(dao.pool())
+            .await
+            .unwrap();
+
+        let result = dao.get_session_user("old".to_string()).await;
+
+        assert!(matches!(result, Err(TodoError::NotFound)));
+    }
+}
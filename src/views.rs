@@ -1,6 +1,16 @@
-use crate::todos::Todo;
+use crate::{
+    import::LineError,
+    recurrence::Recurrence,
+    todos::{
+        List, Priority, SearchHit, Todo, TodoEvent, TodoEventKind, TodoFilter,
+        TodoOrder,
+    },
+    users::User,
+};
 use axum::response::{IntoResponse, Response, Result as AxumResult};
+use chrono::{DateTime, NaiveDate, Utc};
 use maud::{DOCTYPE, Markup, html};
+use serde::Serialize;
 use std::fmt::Debug;
 
 pub trait Render {
@@ -24,73 +34,520 @@ impl<T: Render + Debug> IntoResponse for RenderResponse<T> {
 
 pub type Result<T> = AxumResult<RenderResponse<T>>;
 
-#[derive(PartialEq, Eq, Debug)]
-pub struct Home(pub Vec<Todo>);
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct Home {
+    pub user: User,
+    pub lists: Vec<List>,
+    pub list: List,
+    pub filter: TodoFilter,
+    pub order: TodoOrder,
+    /// The tag the todos are filtered by, if any.
+    pub tag: Option<String>,
+    pub todos: Vec<Todo>,
+}
 
 impl Render for Home {
     fn render(&self) -> Markup {
-        html! {
-            (DOCTYPE)
-            head {
-                title { "Mash Todos" }
-                meta name="viewport" content="width=device-width, initial-scale=1" {}
-                link rel="icon" href="data:image/svg+xml,<svg xmlns=%22http://www.w3.org/2000/svg%22 viewBox=%220 0 100 100%22><text y=%22.9em%22 font-size=%2290%22>🥔</text></svg>" {}
-                link rel="stylesheet" href="/public/css/bulma_1.0.4/bulma.min.css" {}
-                link rel="stylesheet" href="/public/css/app.css" {}
-                script src="/public/js/htmx_2.0.4/htmx.min.js" type="text/javascript" {}
-            }
-            body {
-                section .section {
-                    div .container {
-                        h1 .title { "Mash Todos" }
-                        br;
-
-                        div .is-size-4 {
-                            ul #todo-list {
-                                // display todos
-                                @for todo in self.0.iter() {
-                                    (render_todo(todo))
+        page(html! {
+            div .level.is-mobile {
+                div .level-left {
+                    h1 .title.level-item { "Mash Todos" }
+                }
+                div .level-right {
+                    a .level-item href="/trash" title="Deleted todos" { "Trash" }
+                    span .level-item.has-text-grey { (self.user.username) }
+                    form .level-item method="post" action="/logout" {
+                        button .button.is-small type="submit" { "Log out" }
+                    }
+                }
+            }
+            div #errors {}
+
+            // live search across every list
+            div .field {
+                div .control {
+                    input .input
+                        type="search"
+                        name="q"
+                        placeholder="Search todos"
+                        title="Search all your todos"
+                        hx-get="/api/v1/todos/search"
+                        hx-trigger="input changed delay:300ms, search"
+                        hx-target="#search-results";
+                }
+            }
+            div #search-results {}
+
+            // list switcher
+            div .tabs {
+                ul #list-tabs {
+                    @for list in self.lists.iter() {
+                        (render_list_tab(list, list.id == self.list.id))
+                    }
+                }
+            }
+            form
+                hx-post="/api/v1/lists"
+                hx-target="#list-tabs"
+                hx-swap="beforeend"
+                hx-on::after-request="if(event.detail.successful) { this.reset(); htmx.find('#new-list-error').replaceChildren() }"
+            {
+                input .input .is-small
+                    type="text"
+                    name="name"
+                    placeholder="New list"
+                    title="Create a new todo list"
+                    required;
+                p #new-list-error .help.is-danger {}
+                input type="submit" tabindex="-1" hidden;
+            }
+            br;
+
+            (render_list_title(&self.list))
+            div .level.is-mobile.mb-3 {
+                div .level-left {
+                    // only the todos get swapped, so the tabs keep track of
+                    // which one is selected themselves
+                    div #filter-tabs .tabs.is-toggle.is-small.level-item.mb-0 {
+                        ul {
+                            @for (filter, label) in [(TodoFilter::All, "All"), (TodoFilter::Active, "Active"), (TodoFilter::Completed, "Completed")] {
+                                @let query = view_query(filter, self.order, self.tag.as_deref());
+                                li .is-active[filter == self.filter] {
+                                    a href=(list_url(self.list.id, &query))
+                                        hx-get={"/api/v1/todos?list_id=" (self.list.id) @if !query.is_empty() { "&" (query) }}
+                                        hx-target="#todo-list"
+                                        hx-push-url=(list_url(self.list.id, &query))
+                                        hx-on::after-request="if(event.detail.successful) { htmx.findAll('#filter-tabs li').forEach(li => li.classList.remove('is-active')); this.parentElement.classList.add('is-active') }"
+                                    { (label) }
                                 }
                             }
-                            form .pt-4
-                                hx-post="/api/v1/todos"
-                                hx-target="#todo-list"
-                                hx-swap="beforeend"
-                                hx-on::after-request="if(event.detail.successful) this.reset()"
-                            {
-                                input .input .is-medium
-                                    type="text"
-                                    id="description"
-                                    name="description"
-                                    placeholder="What do you need to do?"
-                                    title="Add a new item to your todo list"
-                                    required;
-                                input type="submit" tabindex="-1" hidden;
+                        }
+                    }
+                }
+                div .level-right {
+                    div .buttons.has-addons.are-small.level-item {
+                        @for (order, label) in [(TodoOrder::Manual, "My order"), (TodoOrder::Added, "Added"), (TodoOrder::Updated, "Recently changed"), (TodoOrder::DueDate, "Due date"), (TodoOrder::Priority, "Priority")] {
+                            a .button
+                                .is-info[order == self.order]
+                                .is-selected[order == self.order]
+                                href=(list_url(self.list.id, &view_query(self.filter, order, self.tag.as_deref()))) { (label) }
+                        }
+                    }
+                }
+            }
+            @if let Some(tag) = &self.tag {
+                div .notification.is-info.is-light.py-2 {
+                    "Only showing todos tagged "
+                    strong { "#" (tag) }
+                    " · "
+                    a href=(list_url(self.list.id, &view_query(self.filter, self.order, None))) { "show everything" }
+                }
+            }
+            // acts on the todos picked with the checkboxes in the list, or on
+            // the whole list at once
+            form #bulk-form .level.is-mobile.mb-2
+                hx-post="/api/v1/todos/bulk"
+                hx-swap="none"
+                hx-on::after-request="if(event.detail.successful) { this.reset() }"
+            {
+                div .level-left {
+                    div .buttons.are-small.level-item {
+                        button .button type="submit" name="action" value="complete" { "Complete selected" }
+                        button .button type="submit" name="action" value="uncomplete" { "Reopen selected" }
+                        button .button.is-danger.is-outlined type="submit" name="action" value="delete" { "Delete selected" }
+                    }
+                }
+                div .level-right {
+                    div .buttons.are-small.level-item {
+                        button .button
+                            hx-post={"/api/v1/lists/" (self.list.id) "/complete-all"}
+                            hx-swap="none"
+                            title="Complete everything on this list"
+                            type="button" { "Complete all" }
+                        button .button
+                            hx-post={"/api/v1/lists/" (self.list.id) "/uncomplete-all"}
+                            hx-swap="none"
+                            title="Reopen everything on this list"
+                            type="button" { "Reopen all" }
+                        button .button
+                            hx-delete={"/api/v1/lists/" (self.list.id) "/completed"}
+                            hx-swap="none"
+                            hx-confirm="Move everything that's done to the trash?"
+                            title="Move everything that's done to the trash"
+                            type="button" { "Clear completed" }
+                    }
+                }
+            }
+            div .is-size-4 {
+                // todos can only be dragged around when they're shown in the
+                // order they were dragged into
+                ul #todo-list data-sortable[self.order == TodoOrder::Manual] {
+                    // display todos
+                    (render_todos(&self.todos))
+                }
+                form .pt-4
+                    hx-post="/api/v1/todos"
+                    hx-target="#todo-list"
+                    hx-swap="beforeend"
+                    hx-on::after-request="if(event.detail.successful) { this.reset(); htmx.find('#add-todo-error').replaceChildren() }"
+                {
+                    input type="hidden" name="list_id" value=(self.list.id);
+                    div .field.has-addons {
+                        div .control.is-expanded {
+                            input .input .is-medium
+                                type="text"
+                                id="description"
+                                name="description"
+                                placeholder="What do you need to do? Use #tags to label it"
+                                title="Add a new item to your todo list"
+                                required;
+                        }
+                        div .control {
+                            input .input .is-medium
+                                type="date"
+                                name="due_date"
+                                title="When is it due? (optional)";
+                        }
+                        div .control {
+                            div .select.is-medium {
+                                (render_priority_select(Priority::default()))
+                            }
+                        }
+                        div .control {
+                            div .select.is-medium {
+                                select name="recurrence" title="Does it repeat?" {
+                                    (render_recurrence_options(None))
+                                }
                             }
                         }
                     }
+                    p #add-todo-error .help.is-danger {}
+                    input type="submit" tabindex="-1" hidden;
                 }
+            }
 
-                footer .footer {
-                    div .content.has-text-centered {
-                        p {
-                            "Made with ☕, 🦀, and ❤️ by "
-                            a href="github.com/SteveXCIV" { "@stevexciv" }
+            // list settings
+            details .mt-5 {
+                summary .has-text-grey { "List settings" }
+                form .mt-2
+                    hx-patch={"/api/v1/lists/" (self.list.id)}
+                    hx-target="#list-title"
+                    hx-swap="outerHTML"
+                {
+                    div .field.has-addons {
+                        div .control.is-expanded {
+                            input .input
+                                type="text"
+                                name="name"
+                                value=(self.list.name)
+                                title="Rename this list"
+                                required;
                         }
-                        p .is-size-7 .has-text-grey {
-                            "This project is open source under either the MIT or Apache-2.0 licenses."
-                            " Find it "
-                            a href="https://github.com/SteveXCIV/mash_todo" { "on GitHub"}
-                            "."
+                        div .control {
+                            button .button type="submit" { "Rename" }
                         }
                     }
+                    p #rename-list-error .help.is-danger {}
+                }
+                button .button.is-danger.is-outlined.mt-2
+                    hx-delete={"/api/v1/lists/" (self.list.id)}
+                    hx-confirm="Delete this list and everything on it?"
+                    type="button" { "Delete list" }
+            }
+            details .mt-3 {
+                summary .has-text-grey { "Import todos" }
+                form .mt-2
+                    hx-post="/api/v1/todos/import"
+                    hx-encoding="multipart/form-data"
+                    hx-target="#import-result"
+                    hx-on::after-request=(RELOAD_TODO_LIST)
+                {
+                    input type="hidden" name="list_id" value=(self.list.id);
+                    div .field.has-addons {
+                        div .control.is-expanded {
+                            input .input
+                                type="file"
+                                name="file"
+                                accept=".json,.csv,.txt,.md"
+                                title="A JSON, CSV, todo.txt or Markdown file"
+                                required;
+                        }
+                        div .control {
+                            div .select {
+                                select name="format" title="What kind of file is it?" {
+                                    option value="" { "Guess from the name" }
+                                    option value="json" { "JSON" }
+                                    option value="csv" { "CSV" }
+                                    option value="txt" { "todo.txt" }
+                                    option value="md" { "Markdown checklist" }
+                                }
+                            }
+                        }
+                        div .control {
+                            button .button type="submit" { "Import" }
+                        }
+                    }
+                    p .help { "Todos that don't name a list go on this one." }
+                    div #import-result {}
+                }
+            }
+        })
+    }
+}
+
+/// Query string for viewing a list a certain way. Defaults are left out to
+/// keep the URLs tidy.
+fn view_query(
+    filter: TodoFilter,
+    order: TodoOrder,
+    tag: Option<&str>,
+) -> String {
+    let mut params = vec![];
+    if filter != TodoFilter::default() {
+        params.push(format!("filter={}", filter.as_str()));
+    }
+    if order != TodoOrder::default() {
+        params.push(format!("order={}", order.as_str()));
+    }
+    if let Some(tag) = tag {
+        params.push(format!("tag={}", tag));
+    }
+    params.join("&")
+}
+
+fn list_url(list_id: i64, query: &str) -> String {
+    if query.is_empty() {
+        format!("/lists/{}", list_id)
+    } else {
+        format!("/lists/{}?{}", list_id, query)
+    }
+}
+
+/// Re-fetches the page the user is on and swaps in its todo list, for when a
+/// change adds todos somewhere other than where the response would go.
+const RELOAD_TODO_LIST: &str = "if(event.detail.successful) { htmx.ajax('GET', window.location.href, {target: '#todo-list', select: '#todo-list', swap: 'outerHTML'}) }";
+
+/// Wraps page content in the document skeleton every full page shares.
+fn page(content: Markup) -> Markup {
+    html! {
+        (DOCTYPE)
+        head {
+            title { "Mash Todos" }
+            meta name="viewport" content="width=device-width, initial-scale=1" {}
+            link rel="icon" href="data:image/svg+xml,<svg xmlns=%22http://www.w3.org/2000/svg%22 viewBox=%220 0 100 100%22><text y=%22.9em%22 font-size=%2290%22>🥔</text></svg>" {}
+            link rel="stylesheet" href="/public/css/bulma_1.0.4/bulma.min.css" {}
+            link rel="stylesheet" href="/public/css/app.css" {}
+            script src="/public/js/htmx_2.0.4/htmx.min.js" type="text/javascript" {}
+            script src="/public/js/sortable.js" type="text/javascript" {}
+            // error responses are retargeted at #errors, so let htmx swap them in
+            meta name="htmx-config" content=r#"{"responseHandling":[{"code":"204","swap":false},{"code":"[23]..","swap":true},{"code":"[45]..","swap":true,"error":true}]}"# {}
+        }
+        body {
+            section .section {
+                div .container {
+                    (content)
+                }
+            }
+            // toasts from out of band swaps pile up here
+            div #toasts {}
+
+            footer .footer {
+                div .content.has-text-centered {
+                    p {
+                        "Made with ☕, 🦀, and ❤️ by "
+                        a href="github.com/SteveXCIV" { "@stevexciv" }
+                    }
+                    p .is-size-7 .has-text-grey {
+                        "This project is open source under either the MIT or Apache-2.0 licenses."
+                        " Find it "
+                        a href="https://github.com/SteveXCIV/mash_todo" { "on GitHub"}
+                        "."
+                    }
                 }
             }
         }
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Serialize, PartialEq, Eq, Debug, Default)]
+pub struct Login {
+    pub username: String,
+    pub error: Option<String>,
+}
+
+impl Render for Login {
+    fn render(&self) -> Markup {
+        page(html! {
+            h1 .title { "Mash Todos" }
+            h2 .subtitle { "Log in" }
+            (render_credentials_form("/login", "Log in", &self.username, self.error.as_deref()))
+            p .mt-4 {
+                "New here? "
+                a href="/signup" { "Create an account" }
+                "."
+            }
+        })
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug, Default)]
+pub struct Signup {
+    pub username: String,
+    pub error: Option<String>,
+}
+
+impl Render for Signup {
+    fn render(&self) -> Markup {
+        page(html! {
+            h1 .title { "Mash Todos" }
+            h2 .subtitle { "Sign up" }
+            (render_credentials_form("/signup", "Sign up", &self.username, self.error.as_deref()))
+            p .mt-4 {
+                "Already have an account? "
+                a href="/login" { "Log in" }
+                "."
+            }
+        })
+    }
+}
+
+fn render_credentials_form(
+    action: &str,
+    submit: &str,
+    username: &str,
+    error: Option<&str>,
+) -> Markup {
+    html! {
+        form method="post" action=(action) {
+            @if let Some(error) = error {
+                div .notification.is-danger.is-light { (error) }
+            }
+            div .field {
+                label .label for="username" { "Username" }
+                div .control {
+                    input .input
+                        type="text"
+                        id="username"
+                        name="username"
+                        value=(username)
+                        autocomplete="username"
+                        required;
+                }
+            }
+            div .field {
+                label .label for="password" { "Password" }
+                div .control {
+                    input .input
+                        type="password"
+                        id="password"
+                        name="password"
+                        autocomplete=(if action == "/signup" { "new-password" } else { "current-password" })
+                        required;
+                }
+            }
+            button .button.is-primary type="submit" { (submit) }
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct TodoList(pub Vec<Todo>);
+
+impl Render for TodoList {
+    fn render(&self) -> Markup {
+        render_todos(&self.0)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct SearchResults {
+    pub query: String,
+    pub hits: Vec<SearchHit>,
+}
+
+impl Render for SearchResults {
+    fn render(&self) -> Markup {
+        html! {
+            @if !self.query.trim().is_empty() {
+                div .box.mb-5 {
+                    @if self.hits.is_empty() {
+                        p .has-text-grey { "No todos match that." }
+                    }
+                    ul {
+                        @for hit in self.hits.iter() {
+                            li .mb-1 {
+                                a href={"/lists/" (hit.todo.list_id)} {
+                                    @for part in hit.snippet.iter() {
+                                        @if part.highlighted {
+                                            mark { (part.text) }
+                                        } @else {
+                                            (part.text)
+                                        }
+                                    }
+                                }
+                                span .tag.is-light.ml-2 { (hit.list_name) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct Lists(pub Vec<List>);
+
+impl Render for Lists {
+    fn render(&self) -> Markup {
+        html! {
+            @for list in self.0.iter() {
+                (render_list_tab(list, false))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct CreatedList(pub List);
+
+impl Render for CreatedList {
+    fn render(&self) -> Markup {
+        render_list_tab(&self.0, false)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct RenamedList(pub List);
+
+impl Render for RenamedList {
+    fn render(&self) -> Markup {
+        let list = &self.0;
+        html! {
+            (render_list_title(list))
+            // the tab shows the name too, so swap that out-of-band
+            li #{"list-tab-" (list.id)} .is-active hx-swap-oob="outerHTML" {
+                a href={"/lists/" (list.id)} { (list.name) }
+            }
+        }
+    }
+}
+
+fn render_list_tab(list: &List, active: bool) -> Markup {
+    html! {
+        li #{"list-tab-" (list.id)} .is-active[active] {
+            a href={"/lists/" (list.id)} { (list.name) }
+        }
+    }
+}
+
+fn render_list_title(list: &List) -> Markup {
+    html! {
+        h2 #list-title .subtitle { (list.name) }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
 pub struct AddedTodo(pub Todo);
 
 impl Render for AddedTodo {
@@ -99,7 +556,23 @@ impl Render for AddedTodo {
     }
 }
 
-#[derive(PartialEq, Eq, Debug)]
+/// Serializes as the new subtask, but renders its parent so the progress and
+/// the rest of the subtasks come along with it.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct AddedSubtask {
+    #[serde(flatten)]
+    pub subtask: Todo,
+    #[serde(skip)]
+    pub parent: Todo,
+}
+
+impl Render for AddedSubtask {
+    fn render(&self) -> Markup {
+        render_todo(&self.parent)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
 pub struct ToggledTodo(pub Todo);
 
 impl Render for ToggledTodo {
@@ -108,15 +581,526 @@ impl Render for ToggledTodo {
     }
 }
 
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct ShowTodo(pub Todo);
+
+impl Render for ShowTodo {
+    fn render(&self) -> Markup {
+        render_todo(&self.0)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct EditTodo(pub Todo);
+
+impl Render for EditTodo {
+    fn render(&self) -> Markup {
+        let todo = &self.0;
+        let id = format!("todo-{}", todo.id);
+        html! {
+            li #(&id) {
+                form
+                    hx-patch={"/api/v1/todos/" (todo.id)}
+                    hx-target={"#" (&id)}
+                    hx-swap="outerHTML"
+                {
+                    div .field.has-addons {
+                        div .control.is-expanded {
+                            // Escape throws away the edit and swaps the label back in
+                            input .input
+                                hx-get={"/api/v1/todos/" (todo.id)}
+                                hx-trigger="keyup[key=='Escape']"
+                                hx-target={"#" (&id)}
+                                hx-swap="outerHTML"
+                                type="text"
+                                name="description"
+                                value=(todo.description)
+                                title="Edit this item"
+                                required
+                                autofocus;
+                        }
+                        div .control {
+                            // changing the priority saves straight away
+                            div .select {
+                                select
+                                    hx-put={"/api/v1/todos/" (todo.id) "/priority"}
+                                    hx-target={"#" (&id)}
+                                    hx-swap="outerHTML"
+                                    name="priority"
+                                    title="How urgent is it?"
+                                {
+                                    (render_priority_options(todo.priority))
+                                }
+                            }
+                        }
+                        div .control {
+                            div .select {
+                                select
+                                    hx-put={"/api/v1/todos/" (todo.id) "/recurrence"}
+                                    hx-target={"#" (&id)}
+                                    hx-swap="outerHTML"
+                                    name="recurrence"
+                                    title="Does it repeat?"
+                                {
+                                    (render_recurrence_options(todo.recurrence.as_ref()))
+                                }
+                            }
+                        }
+                        div .control {
+                            button .button.is-primary type="submit" { "Save" }
+                        }
+                        div .control {
+                            button .button
+                                hx-get={"/api/v1/todos/" (todo.id)}
+                                hx-target={"#" (&id)}
+                                hx-swap="outerHTML"
+                                type="button" { "Cancel" }
+                        }
+                    }
+                    p #{(&id) "-error"} .help.is-danger {}
+                }
+                form .mt-2
+                    hx-post={"/api/v1/todos/" (todo.id) "/tags"}
+                    hx-target={"#" (&id)}
+                    hx-swap="outerHTML"
+                {
+                    input .input.is-small
+                        type="text"
+                        name="tag"
+                        placeholder="Add a tag"
+                        title="Label this item with a tag";
+                }
+                form .mt-2
+                    hx-post={"/api/v1/todos/" (todo.id) "/subtasks"}
+                    hx-target={"#" (&id)}
+                    hx-swap="outerHTML"
+                {
+                    input .input.is-small
+                        type="text"
+                        name="description"
+                        placeholder="Add a subtask"
+                        title="Break this item down into smaller steps";
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct ReprioritizedTodo(pub Todo);
+
+impl Render for ReprioritizedTodo {
+    fn render(&self) -> Markup {
+        render_todo(&self.0)
+    }
+}
+
+/// Swaps in nothing where the todo was, and pops up a toast offering to undo.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct DeletedTodo(pub Todo);
+
+impl Render for DeletedTodo {
+    fn render(&self) -> Markup {
+        let todo = &self.0;
+        html! {
+            div hx-swap-oob="beforeend:#toasts" {
+                div .notification.is-dark {
+                    button .delete
+                        type="button"
+                        hx-on:click="this.parentElement.remove()" {}
+                    "Moved "
+                    strong { (todo.description) }
+                    " to the trash. "
+                    a hx-post={"/api/v1/trash/" (todo.id) "/restore"}
+                        hx-swap="none"
+                        hx-on::after-request={"this.closest('.notification').remove(); " (RELOAD_TODO_LIST)}
+                    { "Undo" }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct RestoredTodo(pub Todo);
+
+impl Render for RestoredTodo {
+    fn render(&self) -> Markup {
+        render_todo(&self.0)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct Trash {
+    pub user: User,
+    /// How long todos stay in the trash before they're deleted for good.
+    pub retention_days: u64,
+    pub todos: Vec<Todo>,
+}
+
+impl Render for Trash {
+    fn render(&self) -> Markup {
+        page(html! {
+            div .level.is-mobile {
+                div .level-left {
+                    h1 .title.level-item { "Trash" }
+                }
+                div .level-right {
+                    a .level-item href="/" { "Back to your todos" }
+                }
+            }
+            div #errors {}
+            p .has-text-grey.mb-4 {
+                "Todos are deleted for good after "
+                (self.retention_days)
+                " days in the trash."
+            }
+            ul #trash-list .mb-4 {
+                (render_trashed_todos(&self.todos))
+            }
+            @if !self.todos.is_empty() {
+                button .button.is-danger.is-outlined
+                    hx-delete="/api/v1/trash"
+                    hx-target="#trash-list"
+                    hx-confirm="Delete everything in the trash for good?"
+                    type="button" { "Empty trash" }
+            }
+        })
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct TrashList(pub Vec<Todo>);
+
+impl Render for TrashList {
+    fn render(&self) -> Markup {
+        render_trashed_todos(&self.0)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct EmptiedTrash {
+    pub purged: u64,
+}
+
+impl Render for EmptiedTrash {
+    fn render(&self) -> Markup {
+        html! {}
+    }
+}
+
+fn render_trashed_todos(todos: &[Todo]) -> Markup {
+    html! {
+        @for todo in todos {
+            (render_trashed_todo(todo))
+        }
+    }
+}
+
+fn render_trashed_todo(todo: &Todo) -> Markup {
+    let id = format!("trash-{}", todo.id);
+    let deleted = todo
+        .deleted_at
+        .and_then(DateTime::from_timestamp_millis)
+        .map(|deleted_at| deleted_at.format("%Y-%m-%d").to_string());
+    html! {
+        li #(&id) .mb-2 {
+            (todo.description)
+            @if let Some(deleted) = deleted {
+                span .tag.is-light.ml-2 title="When it was deleted" { (deleted) }
+            }
+            button .button.is-small.ml-2
+                hx-post={"/api/v1/trash/" (todo.id) "/restore"}
+                hx-target={"#" (&id)}
+                hx-swap="delete"
+                title="Put this item back"
+                type="button" { "Restore" }
+            button .delete .ml-2
+                hx-delete={"/api/v1/trash/" (todo.id)}
+                hx-target={"#" (&id)}
+                hx-swap="delete"
+                hx-confirm="Delete this for good?"
+                title="Delete this item for good"
+                type="button" {}
+        }
+    }
+}
+
+/// Swaps every todo a bulk action touched wherever it is on the page, taking
+/// out the ones that went in the trash.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct BulkUpdated(pub Vec<Todo>);
+
+impl Render for BulkUpdated {
+    fn render(&self) -> Markup {
+        html! {
+            // subtasks are never picked by the toolbar, and they get swapped
+            // along with the todos above them anyway
+            @for todo in self.0.iter().filter(|todo| todo.parent_id.is_none()) {
+                @if todo.deleted_at.is_some() {
+                    li #{"todo-" (todo.id)} hx-swap-oob="delete" {}
+                } @else {
+                    (render_nested_todo(todo, todo.id, true))
+                }
+            }
+        }
+    }
+}
+
+/// The todos an uploaded file turned into.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct Imported(pub Vec<Todo>);
+
+impl Render for Imported {
+    fn render(&self) -> Markup {
+        html! {
+            div .notification.is-success.is-light {
+                button .delete
+                    type="button"
+                    hx-on:click="this.parentElement.remove()" {}
+                "Imported " (self.0.len())
+                @if self.0.len() == 1 { " todo" } @else { " todos" }
+            }
+        }
+    }
+}
+
+/// Why an uploaded file couldn't be imported, line by line.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct ImportErrors {
+    pub error: String,
+    pub lines: Vec<LineError>,
+}
+
+impl ImportErrors {
+    pub fn new(lines: Vec<LineError>) -> Self {
+        Self {
+            error: "nothing was imported; fix these lines and try again"
+                .to_string(),
+            lines,
+        }
+    }
+}
+
+impl Render for ImportErrors {
+    fn render(&self) -> Markup {
+        html! {
+            div .notification.is-danger.is-light {
+                button .delete
+                    type="button"
+                    hx-on:click="this.parentElement.remove()" {}
+                p { (self.error) }
+                ul {
+                    @for line in self.lines.iter() {
+                        li { "Line " (line.line) ": " (line.message) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct RescheduledTodo(pub Todo);
+
+impl Render for RescheduledTodo {
+    fn render(&self) -> Markup {
+        render_todo(&self.0)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct MovedTodo(pub Todo);
+
+impl Render for MovedTodo {
+    fn render(&self) -> Markup {
+        render_todo(&self.0)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct TaggedTodo(pub Todo);
+
+impl Render for TaggedTodo {
+    fn render(&self) -> Markup {
+        render_todo(&self.0)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct Tags(pub Vec<String>);
+
+impl Render for Tags {
+    fn render(&self) -> Markup {
+        html! {
+            div .tags {
+                @for tag in self.0.iter() {
+                    a .tag.is-info.is-light href={"/?tag=" (tag)} { "#" (tag) }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct EditedTodo(pub Todo);
+
+impl Render for EditedTodo {
+    fn render(&self) -> Markup {
+        render_todo(&self.0)
+    }
+}
+
+fn render_todos(todos: &[Todo]) -> Markup {
+    html! {
+        @for todo in todos {
+            (render_todo(todo))
+        }
+    }
+}
+
+fn render_priority_select(selected: Priority) -> Markup {
+    html! {
+        select name="priority" title="How urgent is it?" {
+            (render_priority_options(selected))
+        }
+    }
+}
+
+fn render_priority_options(selected: Priority) -> Markup {
+    html! {
+        @for priority in Priority::ALL {
+            option value=(priority.as_str()) selected[priority == selected] {
+                (priority.as_str())
+            }
+        }
+    }
+}
+
+/// The rules people can pick from the forms; anything else has to come in
+/// through the API.
+const RECURRENCE_PRESETS: [(&str, &str); 5] = [
+    ("", "Doesn't repeat"),
+    ("FREQ=DAILY", "Every day"),
+    ("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR", "Every weekday"),
+    ("FREQ=WEEKLY", "Every week"),
+    ("FREQ=MONTHLY", "Every month"),
+];
+
+fn render_recurrence_options(selected: Option<&Recurrence>) -> Markup {
+    let selected = selected.map(Recurrence::to_string).unwrap_or_default();
+    let is_preset =
+        RECURRENCE_PRESETS.iter().any(|(rule, _)| *rule == selected);
+    html! {
+        @for (rule, label) in RECURRENCE_PRESETS {
+            option value=(rule) selected[rule == selected] { (label) }
+        }
+        // keep a custom rule from the API selectable
+        @if !is_preset {
+            @if let Ok(recurrence) = selected.parse::<Recurrence>() {
+                option value=(selected) selected { (recurrence.describe()) }
+            }
+        }
+    }
+}
+
+/// Normal priority is the default, so only the others get a badge.
+fn render_priority_badge(priority: Priority) -> Markup {
+    html! {
+        @if priority != Priority::Normal {
+            span .tag .ml-2
+                .is-light[priority == Priority::Low]
+                .is-warning[priority == Priority::High]
+                .is-danger[priority == Priority::Urgent] { (priority.as_str()) }
+        }
+    }
+}
+
+/// Describes a due date relative to today, e.g. "due tomorrow".
+fn describe_due_date(due: NaiveDate, today: NaiveDate) -> String {
+    match (due - today).num_days() {
+        0 => "due today".to_string(),
+        1 => "due tomorrow".to_string(),
+        -1 => "due yesterday".to_string(),
+        days if days > 1 => format!("due in {} days", days),
+        days => format!("{} days overdue", -days),
+    }
+}
+
+/// Describes how long ago something happened, e.g. "3 hours ago".
+fn describe_age(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let age = now - at;
+    match (age.num_minutes(), age.num_hours(), age.num_days()) {
+        (minutes, _, _) if minutes < 1 => "just now".to_string(),
+        (1, _, _) => "a minute ago".to_string(),
+        (minutes, 0, _) => format!("{} minutes ago", minutes),
+        (_, 1, _) => "an hour ago".to_string(),
+        (_, hours, 0) => format!("{} hours ago", hours),
+        (_, _, 1) => "yesterday".to_string(),
+        (_, _, days) if days < 30 => format!("{} days ago", days),
+        _ => format!("on {}", at.format("%Y-%m-%d")),
+    }
+}
+
+/// Says when a todo was added, or last changed if it has been since.
+fn render_timestamps(todo: &Todo, now: DateTime<Utc>) -> Markup {
+    let format = |at: DateTime<Utc>| at.format("%Y-%m-%d %H:%M").to_string();
+    let created = DateTime::from_timestamp_millis(todo.created_at);
+    let updated = DateTime::from_timestamp_millis(todo.updated_at)
+        .filter(|_| todo.updated_at > todo.created_at);
+    html! {
+        @match (created, updated) {
+            (Some(created), Some(updated)) => {
+                span .has-text-grey.is-size-7.ml-2
+                    title={"Added " (format(created)) ", changed " (format(updated))} {
+                    "changed " (describe_age(updated, now))
+                }
+            }
+            (Some(created), None) => {
+                span .has-text-grey.is-size-7.ml-2 title={"Added " (format(created))} {
+                    "added " (describe_age(created, now))
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 fn render_todo(todo: &Todo) -> Markup {
+    render_nested_todo(todo, todo.id, false)
+}
+
+/// Renders a todo somewhere in the tree of subtasks under `root_id`. Toggling
+/// or deleting a subtask can change the todos above it, so those re-fetch the
+/// whole tree instead of swapping in just the subtask.
+///
+/// An out of band todo replaces whichever one on the page has the same id.
+fn render_nested_todo(todo: &Todo, root_id: i64, out_of_band: bool) -> Markup {
     let id = format!("todo-{}", todo.id);
+    let now = Utc::now();
+    let today = now.date_naive();
+    let overdue = todo.is_overdue(today);
+    let nested = todo.id != root_id;
+    let target = (!nested).then(|| format!("#{}", id));
+    let swap = if nested { "none" } else { "outerHTML" };
+    let refresh_root = nested.then(|| {
+        format!(
+            "if(event.detail.successful) {{ htmx.ajax('GET', '/api/v1/todos/{0}', {{target: '#todo-{0}', swap: 'outerHTML'}}) }}",
+            root_id
+        )
+    });
+    // completing a recurring todo adds the next one to the list too
+    let refresh_list =
+        (!nested && todo.recurrence.is_some()).then_some(RELOAD_TODO_LIST);
     html! {
-        li #(&id) {
+        li #(&id) .overdue[overdue] draggable="true"
+            hx-swap-oob=[out_of_band.then_some("true")] {
             label .checkbox {
                 input .big-checkbox .mr-4
                     hx-put={"/api/v1/todos/" (todo.id) "/toggle"}
-                    hx-target={"#" (&id)}
-                    hx-swap="outerHTML"
+                    hx-target=[target.as_ref()]
+                    hx-swap=(swap)
+                    hx-on::after-request=[refresh_root.as_deref().or(refresh_list)]
                     type="checkbox"
                     checked[todo.is_completed()];
                 @if todo.is_completed() {
@@ -125,6 +1109,151 @@ fn render_todo(todo: &Todo) -> Markup {
                     (todo.description)
                 }
             }
+            @if !nested {
+                // picks the todo for the bulk actions above the list
+                input .bulk-select.is-pulled-right.mt-3
+                    type="checkbox"
+                    name="ids"
+                    value=(todo.id)
+                    form="bulk-form"
+                    title="Select this item";
+            }
+            (render_priority_badge(todo.priority))
+            @if let Some((done, total)) = todo.progress() {
+                span .tag.is-light.ml-2
+                    .is-success[done == total]
+                    title="Subtasks done" { (done) "/" (total) }
+            }
+            @for tag in todo.tags.iter() {
+                // clicking the tag filters the list by it
+                span .tags.has-addons.is-inline-flex.mb-0.ml-2 {
+                    a .tag.is-info.is-light.mb-0
+                        href={"/lists/" (todo.list_id) "?tag=" (tag)}
+                        title={"Show everything tagged #" (tag)} { "#" (tag) }
+                    a .tag.is-delete.mb-0
+                        hx-delete={"/api/v1/todos/" (todo.id) "/tags/" (tag)}
+                        hx-target={"#" (&id)}
+                        hx-swap="outerHTML"
+                        title="Remove this tag" {}
+                }
+            }
+            @if let Some(recurrence) = &todo.recurrence {
+                span .tag.is-light.ml-2 title=(recurrence) {
+                    "🔁 " (recurrence.describe())
+                }
+            }
+            @if let Some(due) = todo.due_date() {
+                span .tag.is-light .ml-2
+                    .is-danger[overdue]
+                    title=(due.format("%Y-%m-%d")) { (describe_due_date(due, today)) }
+            }
+            (render_timestamps(todo, now))
+            button .button.is-white.is-small .ml-2
+                hx-get={"/api/v1/todos/" (todo.id) "/history"}
+                hx-target={"#" (&id) "-history"}
+                title="Show the history of this item"
+                type="button" { "🕘" }
+            button .button.is-white.is-small
+                hx-get={"/api/v1/todos/" (todo.id) "/edit"}
+                hx-target={"#" (&id)}
+                hx-swap="outerHTML"
+                title="Edit this item"
+                type="button" { "✏️" }
+            button .delete .ml-2
+                hx-delete={"/api/v1/todos/" (todo.id)}
+                hx-target=[target.as_ref()]
+                hx-swap=(swap)
+                hx-on::after-request=[refresh_root.as_ref()]
+                title="Delete this item"
+                type="button" {}
+            div #{(&id) "-history"} {}
+            @if !todo.subtasks.is_empty() {
+                // subtasks are always shown in the order they were dragged into
+                ul .subtasks.ml-6 data-sortable {
+                    @for subtask in todo.subtasks.iter() {
+                        (render_nested_todo(subtask, root_id, false))
+                    }
+                }
+            }
         }
     }
 }
+
+/// The panel listing everything that's happened to a todo, which opens up
+/// underneath it.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+pub struct TodoHistory(pub Vec<TodoEvent>);
+
+impl Render for TodoHistory {
+    fn render(&self) -> Markup {
+        html! {
+            div .todo-history.box.is-size-7.mt-2 {
+                button .delete.is-small.is-pulled-right
+                    type="button"
+                    hx-on:click="this.parentElement.remove()" {}
+                ol {
+                    @for event in self.0.iter() {
+                        li {
+                            @if let Some(at) = DateTime::from_timestamp_millis(event.created_at) {
+                                time .has-text-grey.mr-2 datetime=(at.to_rfc3339()) {
+                                    (at.format("%Y-%m-%d %H:%M"))
+                                }
+                            }
+                            strong { (event.username.as_deref().unwrap_or("Someone")) }
+                            " " (describe_event(event))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn describe_event(event: &TodoEvent) -> String {
+    let detail = event.detail.as_deref().unwrap_or_default();
+    match event.kind {
+        TodoEventKind::Created => "added it".to_string(),
+        TodoEventKind::Renamed => format!("renamed it to “{}”", detail),
+        TodoEventKind::Completed => "completed it".to_string(),
+        TodoEventKind::Reopened => "reopened it".to_string(),
+        TodoEventKind::Prioritized => {
+            format!("made it {} priority", detail)
+        }
+        TodoEventKind::Repeated => match detail.parse::<Recurrence>() {
+            Ok(recurrence) => {
+                format!("set it to repeat {}", recurrence.describe())
+            }
+            Err(_) => "stopped it repeating".to_string(),
+        },
+        TodoEventKind::Tagged => format!("tagged it #{}", detail),
+        TodoEventKind::Untagged => format!("removed the #{} tag", detail),
+        TodoEventKind::Moved => "moved it".to_string(),
+        TodoEventKind::Trashed => "moved it to the trash".to_string(),
+        TodoEventKind::Restored => "restored it from the trash".to_string(),
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ErrorNotification(pub String);
+
+impl Render for ErrorNotification {
+    fn render(&self) -> Markup {
+        html! {
+            div .notification.is-danger.is-light {
+                button .delete
+                    type="button"
+                    hx-on:click="this.parentElement.remove()" {}
+                (self.0)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ValidationMessage(pub String);
+
+impl Render for ValidationMessage {
+    fn render(&self) -> Markup {
+        html! { (self.0) }
+    }
+}
@@ -2,23 +2,70 @@ use anyhow::{Result, anyhow};
 use axum::{
     Router,
     body::Body,
-    http::{Request, header, request},
+    http::{HeaderValue, Request, header, request},
+    middleware::map_request,
     response::Response,
 };
 use http_body_util::BodyExt;
 use mash_todo::{
-    db::create_pool, handlers::AddTodoForm, routes::create_router,
-    state::AppState, todos::TodoSqliteDao,
+    auth::SESSION_COOKIE,
+    db::{create_pool, create_postgres_pool},
+    handlers::{AddTodoForm, CredentialsForm, EditTodoForm, ListForm},
+    memory::TodoMemoryDao,
+    postgres::TodoPostgresDao,
+    routes::create_router,
+    state::AppState,
+    todos::{Priority, TodoDao, TodoSqliteDao},
+    users::UserDao,
 };
 use scraper::{Html, Selector};
 use serde::Serialize;
+use serde_json::{Value, json};
 use tower::ServiceExt;
 
-async fn create_router_for_test() -> Router {
+async fn create_anonymous_router_for_test() -> (Router, TodoSqliteDao) {
     let pool = create_pool("sqlite::memory:").await.unwrap();
     let dao = TodoSqliteDao::new(pool);
-    let app_state = AppState::new(dao);
-    create_router(app_state)
+    let app_state = AppState::new(dao.clone());
+    (create_router(app_state), dao)
+}
+
+/// Creates a router where every request is made as a logged in user, so tests
+/// that don't care about auth can ignore it.
+async fn create_router_for_test() -> Router {
+    let (router, dao) = create_anonymous_router_for_test().await;
+    log_in_for_test(router, &dao).await
+}
+
+/// Signs up a user and makes every request through the router as them.
+async fn log_in_for_test<T: UserDao>(router: Router, dao: &T) -> Router {
+    let user = dao
+        .create_user("alice".to_string(), "not-a-real-hash".to_string())
+        .await
+        .unwrap();
+    dao.create_session("test-session".to_string(), user.id)
+        .await
+        .unwrap();
+    router.layer(map_request(|mut request: Request<Body>| async move {
+        request.headers_mut().insert(
+            header::COOKIE,
+            HeaderValue::from_static("session=test-session"),
+        );
+        request
+    }))
+}
+
+/// Pulls the session cookie out of a login/signup response, ready to be sent
+/// back as a `Cookie` header.
+fn session_cookie(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next())
+        .find(|cookie| cookie.starts_with(&format!("{}=", SESSION_COOKIE)))
+        .map(str::to_string)
 }
 
 trait RequestBuilderExt {
@@ -46,8 +93,74 @@ impl RequestBuilderExt for request::Builder {
     }
 }
 
+trait JsonRequestBuilderExt {
+    type Output;
+
+    fn json<T>(self, body: T) -> Result<Self::Output>
+    where
+        T: Serialize;
+}
+
+impl JsonRequestBuilderExt for request::Builder {
+    type Output = Request<Body>;
+
+    fn json<T>(self, body: T) -> Result<Self::Output>
+    where
+        T: Serialize,
+    {
+        let body = serde_json::to_string(&body)?;
+        Ok(self
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(body))?)
+    }
+}
+
+trait MultipartRequestBuilderExt {
+    type Output;
+
+    fn multipart(
+        self,
+        filename: &str,
+        contents: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<Self::Output>;
+}
+
+impl MultipartRequestBuilderExt for request::Builder {
+    type Output = Request<Body>;
+
+    fn multipart(
+        self,
+        filename: &str,
+        contents: &str,
+        fields: &[(&str, &str)],
+    ) -> Result<Self::Output> {
+        const BOUNDARY: &str = "mash-todo-boundary";
+        let mut body = format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; \
+             filename=\"{filename}\"\r\n\r\n{contents}\r\n"
+        );
+        for (name, value) in fields {
+            body.push_str(&format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; \
+                 name=\"{name}\"\r\n\r\n{value}\r\n"
+            ));
+        }
+        body.push_str(&format!("--{BOUNDARY}--\r\n"));
+        Ok(self
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(body))?)
+    }
+}
+
 trait ResponseExt {
     async fn html(self) -> Result<Html>;
+    async fn json(self) -> Result<Value>;
 }
 
 impl ResponseExt for Response {
@@ -64,6 +177,11 @@ impl ResponseExt for Response {
             .as_ref(),
         ))
     }
+
+    async fn json(self) -> Result<Value> {
+        let body = self.into_body().collect().await?.to_bytes();
+        Ok(serde_json::from_slice(&body)?)
+    }
 }
 
 #[tokio::test]
@@ -73,6 +191,10 @@ pub async fn test_add_todo() -> Result<()> {
     let response = router
         .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
             description: "Buy potatoes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
         })?)
         .await?;
 
@@ -104,6 +226,10 @@ pub async fn test_toggle_todo() -> Result<()> {
         .as_service()
         .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
             description: "Buy potatoes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
         })?)
         .await?;
     assert_eq!(response_add.status(), 200);
@@ -125,6 +251,10 @@ pub async fn test_toggle_todo() -> Result<()> {
         .as_service()
         .oneshot(Request::put("/api/v1/todos/1/toggle").form(AddTodoForm {
             description: "Buy potatoes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
         })?)
         .await?;
 
@@ -155,6 +285,10 @@ pub async fn test_add_two_todos() -> Result<()> {
         .as_service()
         .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
             description: "Buy potatoes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
         })?)
         .await?;
 
@@ -171,6 +305,10 @@ pub async fn test_add_two_todos() -> Result<()> {
         .as_service()
         .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
             description: "Clean dishes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
         })?)
         .await?;
 
@@ -214,3 +352,1635 @@ pub async fn test_add_two_todos() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+pub async fn test_delete_todo() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    // First, add a todo
+    let response_add = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+            description: "Buy potatoes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_add.status(), 200);
+    let added_todo_html = response_add.html().await?;
+    let button = {
+        let s =
+            Selector::parse("button.delete").map_err(|e| anyhow!("{:?}", e))?;
+        added_todo_html.select(&s).next().unwrap()
+    };
+    assert_eq!(button.value().attr("hx-delete"), Some("/api/v1/todos/1"));
+    assert_eq!(button.value().attr("hx-target"), Some("#todo-1"));
+    assert_eq!(button.value().attr("hx-swap"), Some("outerHTML"));
+
+    // Now, delete the todo
+    let response_delete = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/todos/1").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_delete.status(), 200);
+
+    // Deleting it again should fail
+    let response_delete_again = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/todos/1").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_delete_again.status(), 404);
+
+    // Verify the todo is gone from the list
+    let response_list = router
+        .as_service()
+        .oneshot(Request::get("/").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_list.status(), 200);
+    let home_html = response_list.html().await?;
+    let items = {
+        let s = Selector::parse("ul#todo-list > li")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html.select(&s).count()
+    };
+    assert_eq!(items, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_trash() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for description in ["Buy potatoes", "Buy milk"] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: None,
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+
+    // Deleting a todo offers to undo it
+    let response_delete = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/todos/1").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_delete.status(), 200);
+    let toast_html = response_delete.html().await?;
+    let undo = {
+        let s = Selector::parse("[hx-swap-oob] a")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        toast_html.select(&s).next().unwrap()
+    };
+    assert_eq!(
+        undo.value().attr("hx-post"),
+        Some("/api/v1/trash/1/restore")
+    );
+
+    // It shows up in the trash
+    let response_trash = router
+        .as_service()
+        .oneshot(Request::get("/trash").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_trash.status(), 200);
+    let trash_html = response_trash.html().await?;
+    let trashed = {
+        let s = Selector::parse("ul#trash-list > li")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        trash_html
+            .select(&s)
+            .map(|li| li.value().id().unwrap_or_default().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(trashed, ["trash-1"]);
+
+    // Undoing puts it back on the list
+    let response_restore = router
+        .as_service()
+        .oneshot(
+            Request::post("/api/v1/trash/1/restore")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_restore.status(), 200);
+    let restored = response_restore.json().await?;
+    assert_eq!(restored["description"], "Buy potatoes");
+    assert!(restored["deleted_at"].is_null());
+    let response_get = router
+        .as_service()
+        .oneshot(Request::get("/api/v1/todos/1").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_get.status(), 200);
+
+    // Purging deletes it for good
+    let response_delete = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/todos/2").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_delete.status(), 200);
+    let response_purge = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/trash/2").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_purge.status(), 200);
+    let response_restore = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/trash/2/restore").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_restore.status(), 404);
+
+    // Emptying the trash purges everything in it
+    let response_delete = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/todos/1").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_delete.status(), 200);
+    let response_empty = router
+        .as_service()
+        .oneshot(
+            Request::delete("/api/v1/trash")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_empty.status(), 200);
+    assert_eq!(response_empty.json().await?, json!({ "purged": 1 }));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_bulk_actions() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for description in ["Buy potatoes", "Buy milk", "Buy eggs"] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: None,
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+
+    // Every todo can be picked for the toolbar
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 200);
+    let home_html = response_home.html().await?;
+    let picked = {
+        let s = Selector::parse(
+            "ul#todo-list > li > input.bulk-select[form=bulk-form]",
+        )
+        .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|input| input.value().attr("value").unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(picked, ["1", "2", "3"]);
+
+    // Complete two of them; ticked checkboxes repeat the field
+    let response_bulk = router
+        .as_service()
+        .oneshot(
+            Request::post("/api/v1/todos/bulk")
+                .header(
+                    header::CONTENT_TYPE,
+                    mime::APPLICATION_WWW_FORM_URLENCODED.as_ref(),
+                )
+                .body(Body::from("ids=1&ids=3&action=complete"))?,
+        )
+        .await?;
+    assert_eq!(response_bulk.status(), 200);
+    let bulk_html = response_bulk.html().await?;
+    let swapped = {
+        let s = Selector::parse("li[hx-swap-oob=true]")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        bulk_html
+            .select(&s)
+            .map(|li| li.value().id().unwrap_or_default().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(swapped, ["todo-1", "todo-3"]);
+
+    // Clearing the completed ones takes them off the page
+    let response_clear = router
+        .as_service()
+        .oneshot(
+            Request::delete("/api/v1/lists/1/completed").body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_clear.status(), 200);
+    let clear_html = response_clear.html().await?;
+    let removed = {
+        let s = Selector::parse("li[hx-swap-oob=delete]")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        clear_html
+            .select(&s)
+            .map(|li| li.value().id().unwrap_or_default().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(removed, ["todo-1", "todo-3"]);
+
+    // And completing everything left is one click
+    let response_all = router
+        .as_service()
+        .oneshot(
+            Request::post("/api/v1/lists/1/complete-all")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_all.status(), 200);
+    let completed = response_all.json().await?;
+    assert_eq!(completed.as_array().map(Vec::len), Some(1));
+    assert_eq!(completed[0]["description"], "Buy milk");
+    assert!(completed[0]["completed_at"].is_i64());
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_edit_todo() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    // First, add a todo
+    let response_add = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+            description: "Buy ptoatoes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_add.status(), 200);
+
+    // Switch the todo into edit mode
+    let response_edit = router
+        .as_service()
+        .oneshot(Request::get("/api/v1/todos/1/edit").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_edit.status(), 200);
+    let edit_html = response_edit.html().await?;
+    let form = {
+        let s = Selector::parse("li#todo-1 > form")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        edit_html.select(&s).next().unwrap()
+    };
+    assert_eq!(form.value().attr("hx-patch"), Some("/api/v1/todos/1"));
+    assert_eq!(form.value().attr("hx-target"), Some("#todo-1"));
+    assert_eq!(form.value().attr("hx-swap"), Some("outerHTML"));
+    let input = {
+        let s = Selector::parse("input[name=description]")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        edit_html.select(&s).next().unwrap()
+    };
+    assert_eq!(input.value().attr("value"), Some("Buy ptoatoes"));
+
+    // Submit the new description
+    let response_update = router
+        .as_service()
+        .oneshot(Request::patch("/api/v1/todos/1").form(EditTodoForm {
+            description: "Buy potatoes".to_string(),
+        })?)
+        .await?;
+    assert_eq!(response_update.status(), 200);
+    let updated_html = response_update.html().await?;
+    let label = {
+        let s = Selector::parse("label").map_err(|e| anyhow!("{:?}", e))?;
+        updated_html.select(&s).next().unwrap()
+    };
+    assert_eq!(label.text().collect::<Vec<_>>(), vec!["Buy potatoes"]);
+
+    // Editing a missing todo should fail
+    let response_missing = router
+        .as_service()
+        .oneshot(Request::patch("/api/v1/todos/2").form(EditTodoForm {
+            description: "Buy potatoes".to_string(),
+        })?)
+        .await?;
+    assert_eq!(response_missing.status(), 404);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_history() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    let response_add = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+            description: "Buy ptoatoes".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_add.status(), 200);
+    let added_html = response_add.html().await?;
+    let button = {
+        let s = Selector::parse("li#todo-1 > button[hx-get$='/history']")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        added_html.select(&s).next().unwrap()
+    };
+    assert_eq!(
+        button.value().attr("hx-get"),
+        Some("/api/v1/todos/1/history")
+    );
+    assert_eq!(button.value().attr("hx-target"), Some("#todo-1-history"));
+
+    let response_update = router
+        .as_service()
+        .oneshot(Request::patch("/api/v1/todos/1").form(EditTodoForm {
+            description: "Buy potatoes".to_string(),
+        })?)
+        .await?;
+    assert_eq!(response_update.status(), 200);
+
+    // The panel says who did what
+    let response_history = router
+        .as_service()
+        .oneshot(Request::get("/api/v1/todos/1/history").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_history.status(), 200);
+    let history_html = response_history.html().await?;
+    let entries = {
+        let s = Selector::parse(".todo-history li")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        history_html
+            .select(&s)
+            .map(|li| li.text().skip(1).collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        entries,
+        ["alice added it", "alice renamed it to “Buy potatoes”"]
+    );
+
+    // And so does the JSON
+    let response_history = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/1/history")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_history.status(), 200);
+    let history = response_history.json().await?;
+    assert_eq!(history[1]["username"], "alice");
+    assert_eq!(history[1]["kind"], "renamed");
+    assert_eq!(history[1]["detail"], "Buy potatoes");
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_toggle_nonexistent_todo() -> Result<()> {
+    let router = create_router_for_test().await;
+
+    let response = router
+        .oneshot(Request::put("/api/v1/todos/999/toggle").body(Body::empty())?)
+        .await?;
+
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.headers()["HX-Retarget"], "#errors");
+    let error_html = response.html().await?;
+    let notification = {
+        let s = Selector::parse("div.notification.is-danger")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        error_html.select(&s).next().unwrap()
+    };
+    assert!(
+        notification
+            .text()
+            .collect::<String>()
+            .contains("todo not found")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_add_invalid_todo() -> Result<()> {
+    let router = create_router_for_test().await;
+
+    let response = router
+        .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+            description: "   ".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        })?)
+        .await?;
+
+    assert_eq!(response.status(), 422);
+    assert_eq!(response.headers()["HX-Retarget"], "#add-todo-error");
+    assert_eq!(response.headers()["HX-Reswap"], "innerHTML");
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_due_dates() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for (description, due_date) in
+        [("Someday", None), ("Pay rent", Some("2000-01-01"))]
+    {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: due_date.map(str::to_string),
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+
+    // Sorting by due date puts dated todos first, and flags the late ones
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/lists/1?order=due_date").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 200);
+    let home_html = response_home.html().await?;
+    let labels = {
+        let s = Selector::parse("ul#todo-list > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels, vec!["Pay rent", "Someday"]);
+    let overdue = {
+        let s = Selector::parse("ul#todo-list > li.overdue > .tag")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html.select(&s).collect::<Vec<_>>()
+    };
+    assert_eq!(overdue.len(), 1);
+    assert!(
+        overdue[0]
+            .text()
+            .collect::<String>()
+            .ends_with("days overdue")
+    );
+
+    // Dates have to be real dates
+    let response_invalid = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+            description: "Pay rent".to_string(),
+            list_id: None,
+            due_date: Some("2000-02-30".to_string()),
+            priority: Priority::Normal,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_invalid.status(), 422);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_priority() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for (description, priority) in [
+        ("Water plants", Priority::Low),
+        ("Call mom", Priority::Normal),
+    ] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: None,
+                priority,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+
+    // Bump the second todo up
+    let response_priority = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/2/priority")
+                .form([("priority", "urgent")])?,
+        )
+        .await?;
+    assert_eq!(response_priority.status(), 200);
+    let badge = {
+        let html = response_priority.html().await?;
+        let s = Selector::parse("li#todo-2 > .tag")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        html.select(&s).map(|t| t.text().collect::<String>()).next()
+    };
+    assert_eq!(badge.as_deref(), Some("urgent"));
+
+    // Sorting by priority puts it first
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/?order=priority").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 200);
+    let home_html = response_home.html().await?;
+    let labels = {
+        let s = Selector::parse("ul#todo-list > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels, vec!["Call mom", "Water plants"]);
+
+    // Only the known levels are allowed
+    let response_invalid = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/2/priority")
+                .form([("priority", "whenever")])?,
+        )
+        .await?;
+    assert_eq!(response_invalid.status(), 422);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_tags() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for description in ["Buy milk #errands", "Write report #work"] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: None,
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+        let html = response_add.html().await?;
+        let chip = {
+            let s = Selector::parse("li a.tag[href]")
+                .map_err(|e| anyhow!("{:?}", e))?;
+            html.select(&s).next().unwrap()
+        };
+        let tag = description.split('#').nth(1).unwrap();
+        assert_eq!(chip.text().collect::<String>(), format!("#{}", tag));
+        assert_eq!(
+            chip.value().attr("href"),
+            Some(format!("/lists/1?tag={}", tag).as_str())
+        );
+    }
+
+    // Filtering by a tag only shows todos with that tag
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/?tag=errands").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 200);
+    let home_html = response_home.html().await?;
+    let labels = {
+        let s = Selector::parse("ul#todo-list > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels, vec!["Buy milk"]);
+
+    // Tags can be added and removed later
+    let response_tag = router
+        .as_service()
+        .oneshot(
+            Request::post("/api/v1/todos/2/tags").form([("tag", "urgent")])?,
+        )
+        .await?;
+    assert_eq!(response_tag.status(), 200);
+    let response_untag = router
+        .as_service()
+        .oneshot(
+            Request::delete("/api/v1/todos/2/tags/work").body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_untag.status(), 200);
+
+    let response_tags = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/tags")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_tags.status(), 200);
+    assert_eq!(response_tags.json().await?, json!(["errands", "urgent"]));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_recurring_todos() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    let response_add = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+            description: "Water plants".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: Some("FREQ=WEEKLY;BYDAY=MO,TH".to_string()),
+        })?)
+        .await?;
+    assert_eq!(response_add.status(), 200);
+    let html = response_add.html().await?;
+    let rule = {
+        let s = Selector::parse("li span.tag[title]")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        html.select(&s).next().unwrap()
+    };
+    assert_eq!(rule.value().attr("title"), Some("FREQ=WEEKLY;BYDAY=MO,TH"));
+    assert_eq!(rule.text().collect::<String>(), "🔁 weekly on Mon, Thu");
+
+    // Completing it puts the next one on the list
+    let response_toggle = router
+        .as_service()
+        .oneshot(Request::put("/api/v1/todos/1/toggle").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_toggle.status(), 200);
+    let response_list = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos?filter=active")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_list.status(), 200);
+    let active = response_list.json().await?;
+    assert_eq!(active[0]["id"], 2);
+    assert_eq!(active[0]["recurrence"], "FREQ=WEEKLY;BYDAY=MO,TH");
+    assert!(active[0]["due_at"].is_i64());
+
+    // Rules we don't understand are turned away
+    let response_bad = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/2/recurrence")
+                .form([("recurrence", "FREQ=YEARLY")])?,
+        )
+        .await?;
+    assert_eq!(response_bad.status(), 422);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_subtasks() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    let response_add = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+            description: "Move house".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_add.status(), 200);
+    for description in ["Pack", "Book movers"] {
+        let response_subtask = router
+            .as_service()
+            .oneshot(
+                Request::post("/api/v1/todos/1/subtasks")
+                    .form([("description", description)])?,
+            )
+            .await?;
+        assert_eq!(response_subtask.status(), 200);
+    }
+
+    // Completing a subtask shows up in the parent's progress
+    let response_toggle = router
+        .as_service()
+        .oneshot(Request::put("/api/v1/todos/2/toggle").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_toggle.status(), 200);
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 200);
+    let home_html = response_home.html().await?;
+    let top_level = {
+        let s = Selector::parse("ul#todo-list > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(top_level, vec!["Move house"]);
+    let subtasks = {
+        let s = Selector::parse("li#todo-1 ul.subtasks > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(subtasks, vec!["Pack", "Book movers"]);
+    let progress = {
+        let s = Selector::parse("li#todo-1 > span.tag")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .next()
+            .map(|t| t.text().collect::<String>())
+    };
+    assert_eq!(progress.as_deref(), Some("1/2"));
+
+    // Completing the parent completes the rest
+    let response_complete = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/1/toggle")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_complete.status(), 200);
+    let completed = response_complete.json().await?;
+    assert!(completed["subtasks"][1]["completed_at"].is_i64());
+
+    // Deleting the parent takes its subtasks with it
+    let response_delete = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/todos/1").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_delete.status(), 200);
+    let response_get = router
+        .as_service()
+        .oneshot(Request::get("/api/v1/todos/3").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_get.status(), 404);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_reorder() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for description in ["First", "Second", "Third"] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: None,
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+
+    // Drag the last todo to the top
+    let response_move = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/3/position").form([("before", "1")])?,
+        )
+        .await?;
+    assert_eq!(response_move.status(), 200);
+
+    // The new order sticks, and the list can be dragged around
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 200);
+    let home_html = response_home.html().await?;
+    let labels = {
+        let s = Selector::parse("ul#todo-list[data-sortable] > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels, vec!["Third", "First", "Second"]);
+
+    // Todos can't be moved next to ones in other lists
+    let response_bad_move = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/1/position").form([("after", "99")])?,
+        )
+        .await?;
+    assert_eq!(response_bad_move.status(), 422);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_search() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for description in ["Buy oat milk", "Write report"] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: None,
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+
+    // Matches link back to their list, with the matching word highlighted
+    let response_search = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/search?q=mil").body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_search.status(), 200);
+    let html = response_search.html().await?;
+    let links = {
+        let s =
+            Selector::parse("li a[href]").map_err(|e| anyhow!("{:?}", e))?;
+        html.select(&s)
+            .map(|a| {
+                (
+                    a.value().attr("href").unwrap().to_string(),
+                    a.text().collect::<String>(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        links,
+        vec![("/lists/1".to_string(), "Buy oat milk".to_string())]
+    );
+    let marked = {
+        let s = Selector::parse("mark").map_err(|e| anyhow!("{:?}", e))?;
+        html.select(&s)
+            .map(|m| m.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(marked, vec!["milk"]);
+
+    // The JSON API returns the same hits
+    let response_json = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/search?q=report")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_json.status(), 200);
+    let body = response_json.json().await?;
+    assert_eq!(body["query"], "report");
+    assert_eq!(body["hits"][0]["todo"]["description"], "Write report");
+    assert_eq!(body["hits"][0]["list_name"], "Todos");
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_filters() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for description in ["Buy milk", "Buy eggs"] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: None,
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+    let response_toggle = router
+        .as_service()
+        .oneshot(Request::put("/api/v1/todos/1/toggle").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_toggle.status(), 200);
+
+    // The page only shows what's still to do
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/?filter=active").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 200);
+    let home_html = response_home.html().await?;
+    let labels = {
+        let s = Selector::parse("ul#todo-list > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels, vec!["Buy eggs"]);
+
+    // The tabs just swap the todos and keep the address bar up to date
+    let completed_tab = {
+        let s = Selector::parse("#filter-tabs li:nth-child(3) > a")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        home_html.select(&s).next().unwrap()
+    };
+    assert_eq!(
+        completed_tab.value().attr("hx-get"),
+        Some("/api/v1/todos?list_id=1&filter=completed")
+    );
+    assert_eq!(completed_tab.value().attr("hx-target"), Some("#todo-list"));
+    assert_eq!(
+        completed_tab.value().attr("hx-push-url"),
+        Some("/lists/1?filter=completed")
+    );
+
+    let response_completed = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos?list_id=1&filter=completed")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_completed.status(), 200);
+    let completed = response_completed.json().await?;
+    assert_eq!(completed.as_array().map(Vec::len), Some(1));
+    assert_eq!(completed[0]["description"], "Buy milk");
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_export() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    for description in ["Buy potatoes #groceries", "Write report"] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").json(AddTodoForm {
+                description: description.to_string(),
+                list_id: None,
+                due_date: Some("2025-12-31".to_string()),
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+    let response_toggle = router
+        .as_service()
+        .oneshot(Request::put("/api/v1/todos/1/toggle").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_toggle.status(), 200);
+
+    // JSON is the default
+    let response_json = router
+        .as_service()
+        .oneshot(Request::get("/api/v1/todos/export").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_json.status(), 200);
+    assert_eq!(
+        response_json.headers().get(header::CONTENT_DISPOSITION),
+        Some(&HeaderValue::from_static(
+            "attachment; filename=\"todos.json\""
+        ))
+    );
+    let exported = response_json.json().await?;
+    let todos = exported.as_array().unwrap();
+    assert_eq!(todos.len(), 2);
+    assert_eq!(todos[0]["description"], "Buy potatoes");
+    assert_eq!(todos[0]["list"], "Todos");
+    assert_eq!(todos[0]["tags"], json!(["groceries"]));
+    assert_eq!(todos[0]["due_date"], "2025-12-31");
+    // completion times come from the clock, so just check they're ISO-8601
+    let completed_at = todos[0]["completed_at"].as_str().unwrap();
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(completed_at).is_ok(),
+        "{} should be ISO-8601",
+        completed_at
+    );
+    assert_eq!(todos[1]["completed_at"], Value::Null);
+
+    let response_csv = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/export?format=csv")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_csv.status(), 200);
+    assert_eq!(
+        response_csv.headers().get(header::CONTENT_TYPE),
+        Some(&HeaderValue::from_static("text/csv; charset=utf-8"))
+    );
+    let body = response_csv.into_body().collect().await?.to_bytes();
+    let csv = String::from_utf8(body.to_vec())?;
+    let rows: Vec<_> = csv.lines().collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[1].starts_with("1,Todos,,Buy potatoes,20"));
+    assert!(rows[2].starts_with("2,Todos,,Write report,,"));
+
+    let response_md = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/export?format=md")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_md.status(), 200);
+    let body = response_md.into_body().collect().await?.to_bytes();
+    let markdown = String::from_utf8(body.to_vec())?;
+    let lines: Vec<_> = markdown.lines().collect();
+    assert_eq!(lines[0], "## Todos");
+    assert!(lines[2].starts_with(
+        "- [x] Buy potatoes #groceries (due 2025-12-31) (completed 20"
+    ));
+    assert_eq!(lines[3], "- [ ] Write report (due 2025-12-31)");
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_import() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    // One bad line means nothing gets imported
+    let csv = "description,priority,due_date\n\
+               Buy potatoes,high,2025-12-31\n\
+               ,normal,\n\
+               Write report,whenever,\n";
+    let response_errors = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos/import").multipart(
+            "todos.csv",
+            csv,
+            &[],
+        )?)
+        .await?;
+    assert_eq!(response_errors.status(), 422);
+    let errors = response_errors.json().await?;
+    let lines = errors["lines"].as_array().unwrap();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["line"], 3);
+    assert_eq!(lines[1]["line"], 4);
+
+    let response_list = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_list.json().await?, json!([]));
+
+    // The format comes from the form when the file name doesn't give it away
+    let markdown = "- [x] Buy potatoes #groceries\n\
+                    - [ ] Write report (due 2025-12-31)\n\
+                    \x20 - [x] Find last year's report\n";
+    let response_import = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos/import").multipart(
+            "todos",
+            markdown,
+            &[("format", "md")],
+        )?)
+        .await?;
+    assert_eq!(response_import.status(), 200);
+    let imported = response_import.json().await?;
+    let todos = imported.as_array().unwrap();
+    assert_eq!(todos.len(), 3);
+    assert_eq!(todos[0]["description"], "Buy potatoes");
+    assert_eq!(todos[0]["tags"], json!(["groceries"]));
+    assert!(todos[0]["completed_at"].is_number());
+    assert_eq!(todos[1]["completed_at"], Value::Null);
+    assert_eq!(todos[2]["parent_id"], todos[1]["id"]);
+    assert!(todos[2]["completed_at"].is_number());
+
+    // Without a file there's nothing to do
+    let response_missing = router
+        .as_service()
+        .oneshot(
+            Request::post("/api/v1/todos/import")
+                .header(
+                    header::CONTENT_TYPE,
+                    "multipart/form-data; boundary=nothing",
+                )
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from("--nothing--\r\n"))?,
+        )
+        .await?;
+    assert_eq!(response_missing.status(), 422);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_json_api() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    // Add a todo with a JSON body
+    let response_add = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").json(AddTodoForm {
+            description: "Buy potatoes".to_string(),
+            list_id: None,
+            due_date: Some("2025-12-31".to_string()),
+            priority: Priority::High,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_add.status(), 200);
+    let mut added = response_add.json().await?;
+    // timestamps come from the clock, so just check they're there
+    for field in ["created_at", "updated_at"] {
+        let timestamp = added
+            .as_object_mut()
+            .and_then(|todo| todo.remove(field))
+            .unwrap_or_default();
+        assert!(timestamp.is_i64(), "{} should be a timestamp", field);
+    }
+    assert_eq!(
+        added,
+        json!({
+            "id": 1,
+            "list_id": 1,
+            "description": "Buy potatoes",
+            "completed_at": null,
+            "due_at": 1_767_139_200_000_i64,
+            "priority": "high",
+            "tags": [],
+            "parent_id": null,
+            "recurrence": null,
+            "deleted_at": null,
+            "subtasks": []
+        })
+    );
+
+    // Toggle it
+    let response_toggle = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/1/toggle")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_toggle.status(), 200);
+    let toggled = response_toggle.json().await?;
+    assert!(toggled["completed_at"].is_i64());
+
+    // Fetch it by id
+    let response_get = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/1")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_get.status(), 200);
+    assert_eq!(response_get.json().await?, toggled);
+
+    // List all todos
+    let response_list = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_list.status(), 200);
+    assert_eq!(response_list.json().await?, json!([toggled]));
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_json_api_errors() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    let response_missing = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/999")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_missing.status(), 404);
+    assert_eq!(
+        response_missing.json().await?,
+        json!({"error": "todo not found"})
+    );
+
+    let response_invalid = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").json(AddTodoForm {
+            description: " ".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::Normal,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_invalid.status(), 422);
+    assert_eq!(
+        response_invalid.json().await?,
+        json!({"error": "description must not be empty"})
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_lists() -> Result<()> {
+    let mut router = create_router_for_test().await;
+
+    // Create a second list
+    let response_create = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/lists").form(ListForm {
+            name: "Groceries".to_string(),
+        })?)
+        .await?;
+    assert_eq!(response_create.status(), 200);
+    let tab_html = response_create.html().await?;
+    let tab_link = {
+        let s = Selector::parse("li#list-tab-2 > a")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        tab_html.select(&s).next().unwrap()
+    };
+    assert_eq!(tab_link.value().attr("href"), Some("/lists/2"));
+
+    // Add a todo to each list
+    for (description, list_id) in [("Do laundry", 1), ("Buy potatoes", 2)] {
+        let response_add = router
+            .as_service()
+            .oneshot(Request::post("/api/v1/todos").form(AddTodoForm {
+                description: description.to_string(),
+                list_id: Some(list_id),
+                due_date: None,
+                priority: Priority::Normal,
+                recurrence: None,
+            })?)
+            .await?;
+        assert_eq!(response_add.status(), 200);
+    }
+
+    // The new list's page only shows its own todos
+    let response_list = router
+        .as_service()
+        .oneshot(Request::get("/lists/2").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_list.status(), 200);
+    let list_html = response_list.html().await?;
+    let labels = {
+        let s = Selector::parse("ul#todo-list > li > label")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        list_html
+            .select(&s)
+            .map(|l| l.text().collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels, vec!["Buy potatoes"]);
+    let active_tab = {
+        let s = Selector::parse("#list-tabs > li.is-active > a")
+            .map_err(|e| anyhow!("{:?}", e))?;
+        list_html.select(&s).next().unwrap()
+    };
+    assert_eq!(active_tab.text().collect::<String>(), "Groceries");
+
+    // Delete it, which takes its todos with it
+    let response_delete = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/lists/2").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_delete.status(), 200);
+    assert_eq!(response_delete.headers()["HX-Redirect"], "/");
+
+    let response_gone = router
+        .as_service()
+        .oneshot(Request::get("/lists/2").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_gone.status(), 404);
+
+    // The last list can't be deleted
+    let response_last = router
+        .as_service()
+        .oneshot(Request::delete("/api/v1/lists/1").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_last.status(), 409);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_requires_login() -> Result<()> {
+    let (mut router, _) = create_anonymous_router_for_test().await;
+
+    // Browsers get sent to the login page
+    let response_home = router
+        .as_service()
+        .oneshot(Request::get("/").body(Body::empty())?)
+        .await?;
+    assert_eq!(response_home.status(), 303);
+    assert_eq!(response_home.headers()[header::LOCATION], "/login");
+
+    // htmx gets told to go there itself
+    let response_htmx = router
+        .as_service()
+        .oneshot(
+            Request::put("/api/v1/todos/1/toggle")
+                .header("HX-Request", "true")
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_htmx.status(), 401);
+    assert_eq!(response_htmx.headers()["HX-Redirect"], "/login");
+
+    // API clients just get a 401
+    let response_api = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_api.status(), 401);
+    assert_eq!(
+        response_api.json().await?,
+        json!({"error": "you need to log in first"})
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_signup_login_logout() -> Result<()> {
+    let (mut router, _) = create_anonymous_router_for_test().await;
+    let credentials = || CredentialsForm {
+        username: "alice".to_string(),
+        password: "correct horse".to_string(),
+    };
+
+    // Signing up logs you straight in
+    let response_signup = router
+        .as_service()
+        .oneshot(Request::post("/signup").form(credentials())?)
+        .await?;
+    assert_eq!(response_signup.status(), 303);
+    assert_eq!(response_signup.headers()[header::LOCATION], "/");
+    assert!(session_cookie(&response_signup).is_some());
+
+    // The username is taken now
+    let response_duplicate = router
+        .as_service()
+        .oneshot(Request::post("/signup").form(credentials())?)
+        .await?;
+    assert_eq!(response_duplicate.status(), 409);
+
+    // A wrong password is turned away
+    let response_wrong = router
+        .as_service()
+        .oneshot(Request::post("/login").form(CredentialsForm {
+            username: "alice".to_string(),
+            password: "incorrect horse".to_string(),
+        })?)
+        .await?;
+    assert_eq!(response_wrong.status(), 401);
+    assert!(session_cookie(&response_wrong).is_none());
+
+    // The right one gets a working session
+    let response_login = router
+        .as_service()
+        .oneshot(Request::post("/login").form(credentials())?)
+        .await?;
+    assert_eq!(response_login.status(), 303);
+    let cookie = session_cookie(&response_login).unwrap();
+
+    let response_home = router
+        .as_service()
+        .oneshot(
+            Request::get("/")
+                .header(header::COOKIE, &cookie)
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_home.status(), 200);
+
+    // Logging out ends it
+    let response_logout = router
+        .as_service()
+        .oneshot(
+            Request::post("/logout")
+                .header(header::COOKIE, &cookie)
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_logout.status(), 303);
+    assert_eq!(response_logout.headers()[header::LOCATION], "/login");
+
+    let response_after = router
+        .as_service()
+        .oneshot(
+            Request::get("/")
+                .header(header::COOKIE, &cookie)
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_after.status(), 303);
+
+    Ok(())
+}
+
+#[tokio::test]
+pub async fn test_users_only_see_their_own_todos() -> Result<()> {
+    let (mut router, _) = create_anonymous_router_for_test().await;
+
+    let mut cookies = vec![];
+    for username in ["alice", "bob"] {
+        let response_signup = router
+            .as_service()
+            .oneshot(Request::post("/signup").json(CredentialsForm {
+                username: username.to_string(),
+                password: "correct horse".to_string(),
+            })?)
+            .await?;
+        assert_eq!(response_signup.status(), 200);
+        cookies.push(session_cookie(&response_signup).unwrap());
+    }
+
+    let response_add = router
+        .as_service()
+        .oneshot(
+            Request::post("/api/v1/todos")
+                .header(header::COOKIE, &cookies[0])
+                .json(AddTodoForm {
+                    description: "Alice's secret".to_string(),
+                    list_id: None,
+                    due_date: None,
+                    priority: Priority::Normal,
+                    recurrence: None,
+                })?,
+        )
+        .await?;
+    assert_eq!(response_add.status(), 200);
+    let todo_id = response_add.json().await?["id"].as_i64().unwrap();
+
+    let response_list = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos")
+                .header(header::COOKIE, &cookies[1])
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_list.status(), 200);
+    assert_eq!(response_list.json().await?, json!([]));
+
+    let response_get = router
+        .as_service()
+        .oneshot(
+            Request::get(format!("/api/v1/todos/{}", todo_id))
+                .header(header::COOKIE, &cookies[1])
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_get.status(), 404);
+
+    Ok(())
+}
+
+/// Connects to the Postgres database at `TEST_POSTGRES_URL`, in a schema of
+/// its own so that nothing else gets in the way.
+async fn create_postgres_dao_for_test() -> Result<TodoPostgresDao> {
+    let url = std::env::var("TEST_POSTGRES_URL")?;
+    let schema = format!("test_{:016x}", rand::random::<u64>());
+    let admin = sqlx::PgPool::connect(&url).await?;
+    sqlx::query(&format!("CREATE SCHEMA {}", schema))
+        .execute(&admin)
+        .await?;
+    admin.close().await;
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let pool = create_postgres_pool(&format!(
+        "{}{}options[search_path]={}",
+        url, separator, schema
+    ))
+    .await?;
+    Ok(TodoPostgresDao::new(pool))
+}
+
+/// Adds, lists and searches for a todo through a router mounted on `dao`,
+/// to show the app works the same whatever it's storing things in.
+async fn check_backend<T>(dao: T) -> Result<()>
+where
+    T: TodoDao + UserDao + Clone + Send + Sync + 'static,
+{
+    let router = create_router(AppState::new(dao.clone()));
+    let mut router = log_in_for_test(router, &dao).await;
+
+    let response_add = router
+        .as_service()
+        .oneshot(Request::post("/api/v1/todos").json(AddTodoForm {
+            description: "Buy potatoes #groceries".to_string(),
+            list_id: None,
+            due_date: None,
+            priority: Priority::High,
+            recurrence: None,
+        })?)
+        .await?;
+    assert_eq!(response_add.status(), 200);
+    let added = response_add.json().await?;
+    assert_eq!(added["description"], "Buy potatoes");
+    assert_eq!(added["tags"], json!(["groceries"]));
+
+    let response_list = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_list.status(), 200);
+    assert_eq!(response_list.json().await?, json!([added]));
+
+    let response_search = router
+        .as_service()
+        .oneshot(
+            Request::get("/api/v1/todos/search?q=pot")
+                .header(header::ACCEPT, mime::APPLICATION_JSON.as_ref())
+                .body(Body::empty())?,
+        )
+        .await?;
+    assert_eq!(response_search.status(), 200);
+    assert_eq!(response_search.json().await?["hits"][0]["todo"], added);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database at TEST_POSTGRES_URL"]
+pub async fn test_postgres_backend() -> Result<()> {
+    check_backend(create_postgres_dao_for_test().await?).await
+}
+
+#[tokio::test]
+pub async fn test_memory_backend() -> Result<()> {
+    check_backend(TodoMemoryDao::new()).await
+}